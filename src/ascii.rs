@@ -0,0 +1,306 @@
+use crate::{
+    band_layout::BandLayout,
+    diagram::Diagram,
+    game_tree::{GameTree, SgfNode},
+    markup::MarkupKind,
+};
+
+// lines of a diagram start with this, the first one is the header
+const LINE_PREFIX: &str = "$$";
+
+// marked stones and points as Sensei's Library draws them, by stone state
+const MARKED_SYMBOLS: [(i8, MarkupKind, char); 12] = [
+    (1, MarkupKind::Circle, 'B'), (-1, MarkupKind::Circle, 'W'), (0, MarkupKind::Circle, 'C'),
+    (1, MarkupKind::Square, '#'), (-1, MarkupKind::Square, '@'), (0, MarkupKind::Square, 'S'),
+    (1, MarkupKind::Triangle, 'Y'), (-1, MarkupKind::Triangle, 'Q'), (0, MarkupKind::Triangle, 'T'),
+    (1, MarkupKind::Cross, 'Z'), (-1, MarkupKind::Cross, 'P'), (0, MarkupKind::Cross, 'M'),
+];
+
+fn player_symbol(state: i8) -> char {
+    if state == -1 { 'W' } else { 'B' }
+}
+
+fn symbol(diagram: &Diagram, pos_x: usize, pos_y: usize, first_number: Option<usize>, star_points: &[(usize, usize)]) -> char {
+    let state = diagram.stone_state(pos_x, pos_y);
+    let markup_kind = diagram.markup_kind(pos_x, pos_y);
+    let marked_symbol = MARKED_SYMBOLS.iter()
+        .find(|(marked_state, kind, _)| *marked_state == state && Some(kind) == markup_kind.as_ref());
+    if let Some((_, _, marked_symbol)) = marked_symbol {
+        return *marked_symbol;
+    }
+    // moves are numbered 1 to 10 from the first one shown, 10 as 0
+    if let (Some(first_number), Some(move_number)) = (first_number, diagram.move_number(pos_x, pos_y)) {
+        return char::from_digit(((move_number + 1 - first_number) % 10) as u32, 10).unwrap_or('.');
+    }
+    match (state, markup_kind) {
+        (1, _) => 'X',
+        (-1, _) => 'O',
+        (_, Some(MarkupKind::Label(label))) if label.len() == 1 && label.chars().all(|c| c.is_ascii_lowercase()) => {
+            label.chars().next().unwrap_or('.')
+        },
+        _ if star_points.contains(&(pos_x, pos_y)) => ',',
+        _ => '.',
+    }
+}
+
+// the region of the diagram in the `$$` format of Sensei's Library, move
+// numbers are kept when they fit in ten moves
+pub fn diagram_ascii(diagram: &Diagram) -> String {
+    let dim = diagram.dim();
+    let region = diagram.region();
+    let (left, top, right, bottom) = region.bounds();
+    let star_points = BandLayout::new(dim, 1.0, 1.0, false).star_points();
+    let move_numbers = region.points().into_iter()
+        .filter_map(|(x, y)| diagram.move_number(x, y).map(|move_number| (move_number, diagram.stone_state(x, y))))
+        .collect::<Vec<_>>();
+    let first_move = move_numbers.iter().min().copied();
+    let last_number = move_numbers.iter().map(|(move_number, _)| *move_number).max();
+    let first_move = first_move.filter(|(first_number, _)| last_number.is_some_and(|last_number| last_number - first_number < 10));
+
+    let mut header = format!("{}{}", LINE_PREFIX, player_symbol(first_move.map_or(diagram.to_play(), |(_, state)| state)));
+    if diagram.show_coordinates() {
+        header.push('c');
+    }
+    if dim != 19 {
+        header.push_str(&dim.to_string());
+    }
+    if let Some((first_number, _)) = first_move.filter(|(first_number, _)| *first_number > 1) {
+        header.push_str(&format!("m{}", first_number));
+    }
+
+    let rows = (top..=bottom)
+        .map(|y| {
+            let symbols = (left..=right)
+                .map(|x| symbol(diagram, x, y, first_move.map(|(first_number, _)| first_number), &star_points).to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let left_edge = if left == 0 { "| " } else { "" };
+            let right_edge = if right == dim - 1 { " |" } else { "" };
+            format!("{}{}{}", left_edge, symbols, right_edge)
+        })
+        .collect::<Vec<_>>();
+    // edges of the board are lines, open sides are left out
+    let border = rows[0].chars().map(|c| if c == '|' { '+' } else { '-' }).collect::<String>();
+    let mut lines = vec![header];
+    if top == 0 {
+        lines.push(format!("{} {}", LINE_PREFIX, border));
+    }
+    lines.extend(rows.iter().map(|row| format!("{} {}", LINE_PREFIX, row)));
+    if bottom == dim - 1 {
+        lines.push(format!("{} {}", LINE_PREFIX, border));
+    }
+    lines.join("\n")
+}
+
+fn is_border(line: &str) -> bool {
+    line.contains('-') && line.chars().all(|c| matches!(c, '-' | '+' | ' '))
+}
+
+fn is_row(line: &str) -> bool {
+    line.starts_with('|') || line.ends_with('|')
+        || (!line.is_empty() && line.chars().all(|c| c == ' ' || c == '.' || c == ',' || c == 'X' || c == 'O'))
+}
+
+// what a diagram shows on its points, relative to its first row and column
+#[derive(Debug, Default)]
+struct Points {
+    black: Vec<(usize, usize)>,
+    white: Vec<(usize, usize)>,
+    // number 1 to 10, position
+    moves: Vec<(usize, (usize, usize))>,
+    markups: Vec<((usize, usize), MarkupKind)>,
+}
+
+impl Points {
+    fn add(&mut self, symbol: char, pos: (usize, usize)) -> Result<(), String> {
+        if let Some((state, kind, _)) = MARKED_SYMBOLS.iter().find(|(_, _, marked_symbol)| *marked_symbol == symbol) {
+            self.markups.push((pos, kind.clone()));
+            match state {
+                1 => self.black.push(pos),
+                -1 => self.white.push(pos),
+                _ => {},
+            }
+            return Ok(());
+        }
+        match symbol {
+            'X' => self.black.push(pos),
+            'O' => self.white.push(pos),
+            '.' | ',' | '_' => {},
+            'a'..='z' => self.markups.push((pos, MarkupKind::Label(symbol.to_string()))),
+            '0'..='9' => {
+                let number = symbol.to_digit(10).map_or(10, |digit| if digit == 0 { 10 } else { digit as usize });
+                if self.moves.iter().any(|(move_number, _)| *move_number == number) {
+                    return Err(format!("move {} is numbered twice", symbol));
+                }
+                self.moves.push((number, pos));
+            },
+            _ => return Err(format!("unknown symbol: {}", symbol)),
+        }
+        Ok(())
+    }
+}
+
+// a new game with the stones of the diagram as setup, its numbered stones
+// as moves from the player of the header and its markup on the last node
+pub fn parse_ascii(content: &str) -> Result<GameTree, String> {
+    let lines = content.lines()
+        .filter_map(|line| line.trim_start().strip_prefix(LINE_PREFIX))
+        .map(|line| line.trim_end())
+        .collect::<Vec<_>>();
+    let (header, body) = match lines.split_first() {
+        Some((first, rest)) if !is_row(first.trim()) && !is_border(first) => (*first, rest),
+        Some(_) => ("", lines.as_slice()),
+        None => return Err(format!("no lines start with {}", LINE_PREFIX)),
+    };
+
+    // `B` or `W`, then `c` for coordinates, the board size and `m` with the
+    // number of the first move, the rest is a title
+    let header = header.trim_start();
+    let first_player = if header.starts_with('W') { -1 } else { 1 };
+    let options = header.trim_start_matches(['B', 'W']).trim_start_matches('c');
+    let size_digits = options.chars().take_while(|c| c.is_ascii_digit()).collect::<String>();
+    let header_size = size_digits.parse::<usize>().ok();
+
+    let (mut top_edge, mut bottom_edge) = (false, false);
+    let (mut left_edge, mut right_edge) = (false, false);
+    let mut rows = vec![];
+    for line in body {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if is_border(line) {
+            if rows.is_empty() { top_edge = true } else { bottom_edge = true }
+            continue;
+        }
+        if bottom_edge {
+            return Err("rows after the bottom edge".to_string());
+        }
+        if rows.is_empty() {
+            (left_edge, right_edge) = (line.starts_with('|'), line.ends_with('|'));
+        }
+        rows.push(line.chars().filter(|c| !c.is_whitespace() && *c != '|').collect::<Vec<_>>());
+    }
+    let column_count = rows.first().map_or(0, |row| row.len());
+    if column_count == 0 {
+        return Err("the diagram has no points".to_string());
+    }
+    if rows.iter().any(|row| row.len() != column_count) {
+        return Err("rows of different lengths".to_string());
+    }
+    let row_count = rows.len();
+    let dim = match header_size {
+        Some(header_size) => header_size,
+        None if top_edge && bottom_edge && left_edge && right_edge => {
+            if row_count != column_count {
+                return Err(format!("a whole board of {} by {} points is not square", column_count, row_count));
+            }
+            column_count
+        },
+        None => 19,
+    };
+    if column_count > dim || row_count > dim || dim > 52 {
+        return Err(format!("{} by {} points do not fit a {}x{} board", column_count, row_count, dim, dim));
+    }
+    // a diagram without a left or top edge is placed against the opposite one
+    let offset_x = if !left_edge && right_edge { dim - column_count } else { 0 };
+    let offset_y = if !top_edge && bottom_edge { dim - row_count } else { 0 };
+
+    let mut points = Points::default();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, symbol) in row.iter().enumerate() {
+            points.add(*symbol, (x + offset_x, y + offset_y))?;
+        }
+    }
+    points.moves.sort();
+
+    let point_values = |points: &[(usize, usize)]| points.iter()
+        .map(|(x, y)| format!("[{}]", SgfNode::format_point(*x, *y)))
+        .collect::<String>();
+    let mut sgf = format!("(;GM[1]FF[4]SZ[{}]", dim);
+    if !points.black.is_empty() {
+        sgf.push_str(&format!("AB{}", point_values(&points.black)));
+    }
+    if !points.white.is_empty() {
+        sgf.push_str(&format!("AW{}", point_values(&points.white)));
+    }
+    sgf.push_str(&format!("PL[{}]", player_symbol(first_player)));
+    for (move_number, (x, y)) in &points.moves {
+        let state = if move_number % 2 == 1 { first_player } else { -first_player };
+        sgf.push_str(&format!(";{}[{}]", player_symbol(state), SgfNode::format_point(*x, *y)));
+    }
+    for (kind, key) in [(MarkupKind::Circle, "CR"), (MarkupKind::Square, "SQ"), (MarkupKind::Triangle, "TR"), (MarkupKind::Cross, "MA")] {
+        let marked_points = points.markups.iter()
+            .filter(|(_, markup_kind)| *markup_kind == kind)
+            .map(|(pos, _)| *pos)
+            .collect::<Vec<_>>();
+        if !marked_points.is_empty() {
+            sgf.push_str(&format!("{}{}", key, point_values(&marked_points)));
+        }
+    }
+    let labels = points.markups.iter()
+        .filter_map(|((x, y), markup_kind)| match markup_kind {
+            MarkupKind::Label(label) => Some(format!("[{}:{}]", SgfNode::format_point(*x, *y), label)),
+            _ => None,
+        })
+        .collect::<String>();
+    if !labels.is_empty() {
+        sgf.push_str(&format!("LB{}", labels));
+    }
+    sgf.push(')');
+    GameTree::from_sgf(&sgf)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{diagram::{Diagram, Region}, game_tree::{GameTree, SgfKey}};
+
+    use super::{diagram_ascii, parse_ascii};
+
+    #[test]
+    pub fn test_ascii_export() {
+        let game_tree = GameTree::from_sgf("(;SZ[9]AB[aa];B[cc];W[dd];B[ee]TR[aa]LB[gg:a][hh:AB])").unwrap();
+        let diagram = Diagram::from_game_tree(&game_tree, 3, Some(2..=3));
+        assert_eq!(diagram_ascii(&diagram), [
+            "$$W9m2",
+            "$$ +-------------------+",
+            "$$ | Y . . . . . . . . |",
+            "$$ | . . . . . . . . . |",
+            "$$ | . . X . . . , . . |",
+            "$$ | . . . 1 . . . . . |",
+            "$$ | . . . . 2 . . . . |",
+            "$$ | . . . . . . . . . |",
+            "$$ | . . , . . . a . . |",
+            "$$ | . . . . . . . . . |",
+            "$$ | . . . . . . . . . |",
+            "$$ +-------------------+",
+        ].join("\n"));
+
+        let mut diagram = Diagram::from_game_tree(&game_tree, 1, None);
+        diagram.set_region(Region::parse("ee:ii", 9).unwrap());
+        diagram.set_show_coordinates(true);
+        assert_eq!(diagram_ascii(&diagram).lines().take(3).collect::<Vec<_>>(), vec![
+            "$$Wc9",
+            "$$ . . . . . |",
+            "$$ . . . . . |",
+        ]);
+    }
+
+    #[test]
+    pub fn test_ascii_import() {
+        let content = "An opening\n$$Wm5 Joseki\n$$ . . . . |\n$$ . 1 O X |\n$$ . 2 B a |\n$$ -------+";
+        let game_tree = parse_ascii(content).unwrap();
+        assert_eq!(GameTree::board_size(&game_tree), 19);
+        assert_eq!(game_tree.to_sgf(), GameTree::from_sgf(
+            "(;GM[1]FF[4]SZ[19]AB[sr][rs]AW[rr]PL[W];W[qr];B[qs]CR[rs]LB[ss:a])").unwrap().to_sgf());
+
+        let diagram = parse_ascii("$$B\n$$ -----\n$$ | O . |\n$$ | . X |\n$$ -----").unwrap();
+        assert_eq!(GameTree::board_size(&diagram), 2);
+        assert!(GameTree::get_node_props(&diagram, -1).iter().any(|node| node.node_key() == SgfKey::PL && node.node_val() == "B"));
+
+        assert_eq!(parse_ascii("no diagram").err(), Some("no lines start with $$".to_string()));
+        assert_eq!(parse_ascii("$$B\n$$ . . .\n$$ . .").err(), Some("rows of different lengths".to_string()));
+        assert_eq!(parse_ascii("$$B\n$$ . 1 1").err(), Some("move 1 is numbered twice".to_string()));
+        assert_eq!(parse_ascii("$$B\n$$ . ? .").err(), Some("unknown symbol: ?".to_string()));
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use crate::game_tree::{GameTree, SgfKey};
+
+pub const MIN_INTERVAL_MS: u32 = 200;
+pub const MAX_INTERVAL_MS: u32 = 5000;
+// how much faster or slower one step of the speed control gets
+const INTERVAL_STEP_MS: u32 = 200;
+
+// settings and state of playing the selected variation forward on a timer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoReplay {
+    running: bool,
+    interval_ms: u32,
+    stop_at_comments: bool,
+    stop_at_branches: bool,
+}
+
+impl AutoReplay {
+    pub fn new(interval_ms: u32) -> Self {
+        AutoReplay {
+            running: false,
+            interval_ms: interval_ms.clamp(MIN_INTERVAL_MS, MAX_INTERVAL_MS),
+            stop_at_comments: false,
+            stop_at_branches: false,
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.running
+    }
+
+    pub fn set_running(&mut self, running: bool) {
+        self.running = running;
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms as u64)
+    }
+
+    pub fn interval_ms(&self) -> u32 {
+        self.interval_ms
+    }
+
+    pub fn set_interval_ms(&mut self, interval_ms: u32) {
+        self.interval_ms = interval_ms.clamp(MIN_INTERVAL_MS, MAX_INTERVAL_MS);
+    }
+
+    pub fn faster(&mut self) {
+        self.set_interval_ms(self.interval_ms.saturating_sub(INTERVAL_STEP_MS));
+    }
+
+    pub fn slower(&mut self) {
+        self.set_interval_ms(self.interval_ms + INTERVAL_STEP_MS);
+    }
+
+    pub fn stop_at_comments(&self) -> bool {
+        self.stop_at_comments
+    }
+
+    pub fn set_stop_at_comments(&mut self, stop_at_comments: bool) {
+        self.stop_at_comments = stop_at_comments;
+    }
+
+    pub fn stop_at_branches(&self) -> bool {
+        self.stop_at_branches
+    }
+
+    pub fn set_stop_at_branches(&mut self, stop_at_branches: bool) {
+        self.stop_at_branches = stop_at_branches;
+    }
+
+    // whether a step from `move_count` to `next_move_count` ends the replay,
+    // at the end of the variation and, if asked to, after a commented move or
+    // before a branch point
+    pub fn should_stop(&self, game_tree: &GameTree, move_count: i32, next_move_count: i32) -> bool {
+        let next_moves = GameTree::get_moves(game_tree, next_move_count).unwrap_or_default();
+        let at_end = !next_moves.iter().any(|(_, selected)| *selected);
+        let has_comment = GameTree::get_node_props(game_tree, next_move_count - 1)
+            .iter()
+            .any(|sgf_node| sgf_node.node_key() == SgfKey::C);
+        next_move_count == move_count
+            || at_end
+            || (self.stop_at_comments && has_comment)
+            || (self.stop_at_branches && next_moves.len() > 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::game_tree::GameTree;
+
+    use super::{AutoReplay, MAX_INTERVAL_MS, MIN_INTERVAL_MS};
+
+    #[test]
+    pub fn test_interval() {
+        assert_eq!(AutoReplay::new(50).interval_ms(), MIN_INTERVAL_MS);
+        let mut auto_replay = AutoReplay::new(60_000);
+        assert_eq!(auto_replay.interval_ms(), MAX_INTERVAL_MS);
+        auto_replay.slower();
+        assert_eq!(auto_replay.interval_ms(), MAX_INTERVAL_MS);
+        auto_replay.set_interval_ms(300);
+        auto_replay.faster();
+        assert_eq!(auto_replay.interval_ms(), MIN_INTERVAL_MS);
+        auto_replay.slower();
+        assert_eq!(auto_replay.interval().as_millis(), 400);
+    }
+
+    #[test]
+    pub fn test_should_stop() {
+        let game_tree = GameTree::from_sgf("(;SZ[9];B[aa]C[ko];W[bb](;B[cc];W[dd])(;B[ee]))").unwrap();
+        let mut auto_replay = AutoReplay::new(1000);
+        assert!(!auto_replay.should_stop(&game_tree, 0, 1));
+        assert!(!auto_replay.should_stop(&game_tree, 1, 2));
+        auto_replay.set_stop_at_comments(true);
+        assert!(auto_replay.should_stop(&game_tree, 0, 1));
+        assert!(!auto_replay.should_stop(&game_tree, 1, 2));
+        auto_replay.set_stop_at_branches(true);
+        assert!(auto_replay.should_stop(&game_tree, 1, 2));
+        assert!(!auto_replay.should_stop(&game_tree, 2, 3));
+        // the end of the selected variation
+        assert!(auto_replay.should_stop(&game_tree, 3, 4));
+        assert!(auto_replay.should_stop(&game_tree, 4, 4));
+        assert!(GameTree::select_variation(&game_tree, 2, 1));
+        assert!(auto_replay.should_stop(&game_tree, 2, 3));
+    }
+}
@@ -0,0 +1,106 @@
+use std::{fs, io, path::PathBuf, time::Duration};
+
+use crate::{game_tree::GameTree, prefs::Prefs};
+
+// how often changes other than moves reach the recovery file
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+// keeps the current game in a recovery file until it is saved or closed,
+// a recovery file left at startup means the last session did not end well
+#[derive(Debug, Clone, PartialEq)]
+pub struct Autosave {
+    recovery_path: Option<PathBuf>,
+    // changes not saved to the game's own file
+    dirty: bool,
+    // changes not in the recovery file yet
+    pending: bool,
+}
+
+impl Autosave {
+    pub fn new(recovery_path: Option<PathBuf>) -> Self {
+        Autosave { recovery_path, dirty: false, pending: false }
+    }
+
+    // `recovery.sgf` beside the preferences
+    pub fn recovery_path() -> Option<PathBuf> {
+        Prefs::path()?.parent().map(|prefs_dir| prefs_dir.join("recovery.sgf"))
+    }
+
+    // the recovery file of an earlier session
+    pub fn found(&self) -> Option<PathBuf> {
+        self.recovery_path.clone().filter(|recovery_path| recovery_path.is_file())
+    }
+
+    pub fn dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.pending = true;
+    }
+
+    // the changes are saved or given up while the recovery file is kept
+    pub fn mark_clean(&mut self) {
+        self.dirty = false;
+        self.pending = false;
+    }
+
+    // the file is written beside and renamed, a crash while saving leaves
+    // the previous recovery intact
+    pub fn save(&mut self, game_tree: &GameTree) -> io::Result<()> {
+        let recovery_path = match self.recovery_path.as_ref() {
+            Some(recovery_path) => recovery_path,
+            None => return Ok(()),
+        };
+        if let Some(recovery_dir) = recovery_path.parent() {
+            fs::create_dir_all(recovery_dir)?;
+        }
+        let saving_path = recovery_path.with_extension("sgf.saving");
+        game_tree.save_sgf(&saving_path.to_string_lossy())?;
+        fs::rename(saving_path, recovery_path)?;
+        self.pending = false;
+        Ok(())
+    }
+
+    // the game is saved or given up, nothing is left to recover
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.dirty = false;
+        self.pending = false;
+        match self.found() {
+            Some(recovery_path) => fs::remove_file(recovery_path),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use crate::{config::Config, game_tree::GameTree};
+
+    use super::Autosave;
+
+    #[test]
+    pub fn test_autosave() {
+        let recovery_path = env::temp_dir().join(format!("fastgo-recovery-{}.sgf", std::process::id()));
+        let mut autosave = Autosave::new(Some(recovery_path.clone()));
+        assert_eq!(autosave.found(), None);
+        autosave.mark_dirty();
+        assert!(autosave.dirty() && autosave.pending());
+        autosave.save(&GameTree::from(Config::default())).unwrap();
+        assert!(autosave.dirty() && !autosave.pending());
+        assert_eq!(autosave.found(), Some(recovery_path.clone()));
+        assert!(std::fs::read_to_string(&recovery_path).unwrap().contains("SZ[19]"));
+        autosave.mark_clean();
+        assert!(!autosave.dirty() && autosave.found().is_some());
+        autosave.clear().unwrap();
+        assert!(!autosave.dirty());
+        assert_eq!(autosave.found(), None);
+    }
+}
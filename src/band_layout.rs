@@ -0,0 +1,198 @@
+// column labels as printed on boards, `I` is left out
+const COLUMN_LABELS: [char; 19] = [
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'J', 'K',
+    'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S', 'T',
+];
+
+// Geometry of a band drawn into a `width` x `height` area. Drawing and
+// hit-testing both go through it, so a click always lands on the
+// intersection drawn under the pointer. All values are logical pixels, the
+// unit iced uses for canvas bounds as well as cursor positions, so HiDPI
+// screens need no special treatment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandLayout {
+    dim: usize,
+    board_origin: (f32, f32),
+    board_size: f32,
+    grid_size: f32,
+    band_margin: f32,
+}
+
+impl BandLayout {
+    pub fn new(dim: usize, width: f32, height: f32, show_coordinates: bool) -> Self {
+        let board_size = width.min(height).max(0.0);
+        // coordinates need a whole extra grid around the lines
+        let grid_count = if show_coordinates { dim + 1 } else { dim };
+        let grid_size = board_size / grid_count.max(1) as f32;
+        let band_margin = if show_coordinates { grid_size } else { grid_size / 2.0 };
+        BandLayout {
+            dim,
+            board_origin: ((width - board_size) / 2.0, (height - board_size) / 2.0),
+            board_size,
+            grid_size,
+            band_margin,
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    pub fn grid_size(&self) -> f32 {
+        self.grid_size
+    }
+
+    pub fn band_margin(&self) -> f32 {
+        self.band_margin
+    }
+
+    pub fn board_origin(&self) -> (f32, f32) {
+        self.board_origin
+    }
+
+    pub fn board_size(&self) -> f32 {
+        self.board_size
+    }
+
+    pub fn line_length(&self) -> f32 {
+        (self.dim.max(1) - 1) as f32 * self.grid_size
+    }
+
+    // position of intersection (0, 0), the top left corner of the lines
+    pub fn line_start(&self) -> (f32, f32) {
+        (self.board_origin.0 + self.band_margin, self.board_origin.1 + self.band_margin)
+    }
+
+    pub fn point(&self, pos_x: usize, pos_y: usize) -> (f32, f32) {
+        let (start_x, start_y) = self.line_start();
+        (start_x + pos_x as f32 * self.grid_size, start_y + pos_y as f32 * self.grid_size)
+    }
+
+    // the nearest grid position, outside the band when the point lies more
+    // than half a grid beyond the edge lines
+    pub fn grid_at(&self, pos_x: f32, pos_y: f32) -> (i32, i32) {
+        if self.grid_size <= 0.0 {
+            return (-1, -1);
+        }
+        let (start_x, start_y) = self.line_start();
+        let grid_x = ((pos_x - start_x) / self.grid_size).round() as i32;
+        let grid_y = ((pos_y - start_y) / self.grid_size).round() as i32;
+        (grid_x, grid_y)
+    }
+
+    pub fn intersection_at(&self, pos_x: f32, pos_y: f32) -> Option<(usize, usize)> {
+        let (grid_x, grid_y) = self.grid_at(pos_x, pos_y);
+        let dim = self.dim as i32;
+        if grid_x >= 0 && grid_x < dim && grid_y >= 0 && grid_y < dim {
+            Some((grid_x as usize, grid_y as usize))
+        } else {
+            None
+        }
+    }
+
+    pub fn star_points(&self) -> Vec<(usize, usize)> {
+        match self.dim {
+            9 => vec![(2, 2), (6, 2), (2, 6), (6, 6)],
+            13 => vec![(3, 3), (3, 9), (6, 6), (9, 3), (9, 9)],
+            19 => vec![
+                (3, 3), (3, 9), (3, 15),
+                (9, 3), (9, 9), (9, 15),
+                (15, 3), (15, 9), (15, 15),
+            ],
+            _ => vec![],
+        }
+    }
+
+    // the usual placement of `handicap` stones, empty when the band is too
+    // small for them or has no centre point for an odd count above four
+    pub fn handicap_points(dim: usize, handicap: usize) -> Vec<(usize, usize)> {
+        if dim < 7 || handicap < 2 || handicap > 9 {
+            return vec![];
+        }
+        let edge = if dim >= 13 { 3 } else { 2 };
+        let (near, far, mid) = (edge, dim - 1 - edge, dim / 2);
+        let has_mid = dim % 2 == 1;
+        if !has_mid && handicap > 4 {
+            return vec![];
+        }
+        let corners = vec![(far, near), (near, far), (far, far), (near, near)];
+        let sides = vec![(near, mid), (far, mid), (mid, near), (mid, far)];
+        let mut points = Vec::from(&corners[..handicap.min(4)]);
+        match handicap {
+            5 | 7 | 9 => {
+                points.extend_from_slice(&sides[..handicap - 5]);
+                points.push((mid, mid));
+            },
+            6 | 8 => points.extend_from_slice(&sides[..handicap - 4]),
+            _ => {},
+        }
+        points
+    }
+
+    pub fn column_label(pos_x: usize) -> String {
+        COLUMN_LABELS.get(pos_x).map_or(String::default(), |label| label.to_string())
+    }
+
+    pub fn column_pos(label: char) -> Option<usize> {
+        COLUMN_LABELS.iter().position(|column_label| *column_label == label.to_ascii_uppercase())
+    }
+
+    // rows are counted from the bottom edge
+    pub fn row_label(&self, pos_y: usize) -> String {
+        (self.dim - pos_y).to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BandLayout;
+
+    #[test]
+    pub fn test_landscape_layout() {
+        // 19 grids of 20px, centred horizontally in a 500x380 canvas
+        let band_layout = BandLayout::new(19, 500.0, 380.0, false);
+        assert_eq!(band_layout.grid_size(), 20.0);
+        assert_eq!(band_layout.board_origin(), (60.0, 0.0));
+        assert_eq!(band_layout.line_start(), (70.0, 10.0));
+        assert_eq!(band_layout.point(18, 18), (430.0, 370.0));
+    }
+
+    #[test]
+    pub fn test_intersection_at() {
+        let band_layout = BandLayout::new(19, 380.0, 500.0, false);
+        let (x, y) = band_layout.point(3, 15);
+        assert_eq!(band_layout.intersection_at(x, y), Some((3, 15)));
+        assert_eq!(band_layout.intersection_at(x + 9.9, y - 9.9), Some((3, 15)));
+        assert_eq!(band_layout.intersection_at(x + 10.1, y - 10.1), Some((4, 14)));
+        let (x, y) = band_layout.point(0, 0);
+        assert_eq!(band_layout.intersection_at(x - 9.0, y - 9.0), Some((0, 0)));
+        assert_eq!(band_layout.intersection_at(x - 11.0, y), None);
+        assert_eq!(band_layout.grid_at(x - 11.0, y), (-1, 0));
+        let (x, y) = band_layout.point(18, 18);
+        assert_eq!(band_layout.intersection_at(x + 11.0, y), None);
+    }
+
+    #[test]
+    pub fn test_coordinates_layout() {
+        // coordinates add one grid around the lines: 10 grids of 30px
+        let band_layout = BandLayout::new(9, 300.0, 300.0, true);
+        assert_eq!(band_layout.grid_size(), 30.0);
+        assert_eq!(band_layout.line_start(), (30.0, 30.0));
+        assert_eq!(band_layout.line_length(), 240.0);
+        assert_eq!(band_layout.intersection_at(45.0, 10.0), None);
+        assert_eq!(band_layout.intersection_at(44.0, 44.0), Some((0, 0)));
+        assert_eq!(BandLayout::column_label(8), "J");
+        assert_eq!(band_layout.row_label(0), "9");
+    }
+
+    #[test]
+    pub fn test_handicap_points() {
+        assert_eq!(BandLayout::handicap_points(19, 2), vec![(15, 3), (3, 15)]);
+        assert_eq!(BandLayout::handicap_points(19, 5).last(), Some(&(9, 9)));
+        assert_eq!(BandLayout::handicap_points(19, 7).len(), 7);
+        assert!(!BandLayout::handicap_points(19, 8).contains(&(9, 9)));
+        assert_eq!(BandLayout::handicap_points(9, 3), vec![(6, 2), (2, 6), (6, 6)]);
+        assert_eq!(BandLayout::handicap_points(10, 5), vec![]);
+        assert_eq!(BandLayout::handicap_points(19, 10), vec![]);
+    }
+}
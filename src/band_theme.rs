@@ -0,0 +1,154 @@
+use std::{fmt, str::FromStr};
+
+use iced::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandTheme {
+    ClassicWood,
+    HighContrast,
+    Dark,
+    Monochrome,
+}
+
+impl BandTheme {
+    pub const ALL: [BandTheme; 4] = [
+        BandTheme::ClassicWood,
+        BandTheme::HighContrast,
+        BandTheme::Dark,
+        BandTheme::Monochrome,
+    ];
+
+    pub fn next(&self) -> BandTheme {
+        let idx = BandTheme::ALL.iter().position(|band_theme| band_theme == self).unwrap_or(0);
+        BandTheme::ALL[(idx + 1) % BandTheme::ALL.len()]
+    }
+
+    pub fn band_color(&self) -> Color {
+        match self {
+            BandTheme::ClassicWood => Color::from_rgb8(250, 189, 132),
+            BandTheme::HighContrast => Color::from_rgb8(255, 236, 160),
+            BandTheme::Dark => Color::from_rgb8(52, 56, 64),
+            BandTheme::Monochrome => Color::WHITE,
+        }
+    }
+
+    pub fn line_color(&self) -> Color {
+        match self {
+            BandTheme::Dark => Color::from_rgb8(170, 170, 170),
+            _ => Color::BLACK,
+        }
+    }
+
+    pub fn thin_line_width(&self) -> f32 {
+        match self {
+            BandTheme::HighContrast => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    pub fn wide_line_width(&self) -> f32 {
+        match self {
+            BandTheme::HighContrast => 4.0,
+            BandTheme::Monochrome => 2.0,
+            _ => 3.0,
+        }
+    }
+
+    pub fn star_radius(&self) -> f32 {
+        match self {
+            BandTheme::HighContrast => 4.5,
+            _ => 3.0,
+        }
+    }
+
+    // stone colours by stone state, 1 for black and -1 for white
+    pub fn stone_color(&self, state: i8) -> Color {
+        match (self, state) {
+            (BandTheme::Dark, 1) => Color::from_rgb8(16, 16, 16),
+            (BandTheme::Dark, _) => Color::from_rgb8(225, 225, 225),
+            (_, 1) => Color::BLACK,
+            (_, _) => Color::WHITE,
+        }
+    }
+
+    // outline around stones that would otherwise blend into the band
+    pub fn stone_border(&self, state: i8) -> Option<Color> {
+        match (self, state) {
+            (BandTheme::ClassicWood, -1) => Some(Color::from_rgb8(120, 120, 120)),
+            (BandTheme::HighContrast, _) | (BandTheme::Monochrome, -1) => Some(Color::BLACK),
+            (BandTheme::Dark, 1) => Some(Color::from_rgb8(110, 110, 110)),
+            _ => None,
+        }
+    }
+
+    pub fn stone_shadow(&self) -> Option<Color> {
+        match self {
+            BandTheme::ClassicWood => Some(Color::from_rgba8(60, 30, 0, 0.35)),
+            BandTheme::Dark => Some(Color::from_rgba8(0, 0, 0, 0.5)),
+            _ => None,
+        }
+    }
+
+    // highlight giving stones a rounded look
+    pub fn stone_shading(&self, state: i8) -> Option<Color> {
+        match (self, state) {
+            (BandTheme::ClassicWood | BandTheme::Dark, 1) => Some(Color::from_rgba8(255, 255, 255, 0.18)),
+            (BandTheme::ClassicWood | BandTheme::Dark, _) => Some(Color::from_rgba8(255, 255, 255, 0.6)),
+            _ => None,
+        }
+    }
+
+    // markup and move numbers have to stand out from what lies beneath
+    pub fn contrast_color(&self, state: i8) -> Color {
+        match state {
+            1 => self.stone_color(-1),
+            -1 => self.stone_color(1),
+            _ => self.line_color(),
+        }
+    }
+
+    pub fn background_color(&self) -> Color {
+        match self {
+            BandTheme::Monochrome => Color::WHITE,
+            BandTheme::HighContrast => Color::BLACK,
+            BandTheme::Dark => Color::from_rgb8(24, 26, 30),
+            BandTheme::ClassicWood => Color::BLACK,
+        }
+    }
+
+    pub fn text_color(&self) -> Color {
+        match self {
+            BandTheme::Monochrome => Color::BLACK,
+            _ => Color::WHITE,
+        }
+    }
+}
+
+impl Default for BandTheme {
+    fn default() -> Self {
+        BandTheme::ClassicWood
+    }
+}
+
+impl fmt::Display for BandTheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BandTheme::ClassicWood => "wood",
+            BandTheme::HighContrast => "contrast",
+            BandTheme::Dark => "dark",
+            BandTheme::Monochrome => "print",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for BandTheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BandTheme::ALL.iter()
+            .find(|band_theme| band_theme.to_string() == s)
+            .copied()
+            .ok_or(format!("unknown theme: {}", s))
+    }
+}
@@ -0,0 +1,286 @@
+use std::{fmt, path::Path, slice, str::FromStr};
+
+use crate::{
+    band_theme::BandTheme,
+    config::{Config, OPTIONS},
+    convert::{Conversion, RecordFormat, STDIO_PATH},
+    export::{DiagramExport, DiagramFormat, DEFAULT_DIAGRAM_WIDTH},
+    info::{Info, InfoFormat, COLUMNS},
+    kifu::{KifuExport, KifuFormat, DEFAULT_FIGURES_PER_PAGE, DEFAULT_FIGURE_MOVES},
+    prefs::Prefs,
+    validate::{ReportFormat, Validation},
+};
+
+// name and summary of each subcommand, `play` runs when none is given
+pub const SUBCOMMANDS: [(&str, &str); 7] = [
+    ("play", "open the band window (default)"),
+    ("convert", "convert a game record between sgf and json"),
+    ("validate", "check sgf records for mistakes"),
+    ("info", "list the players, result and size of sgf records"),
+    ("diagram", "draw a position of a game record to a picture"),
+    ("kifu", "print a game record as figures of numbered moves"),
+    ("help", "show the help of a subcommand"),
+];
+
+#[derive(Debug)]
+pub enum CliCommand {
+    Play(Config),
+    Convert(Conversion),
+    Validate(Validation),
+    Info(Info),
+    Diagram(DiagramExport),
+    Kifu(KifuExport),
+    // text to print before exiting
+    Help(String),
+}
+
+// an argument of a subcommand, `-` is the path of stdin or stdout
+pub enum CliArg<'a> {
+    Option(&'a str),
+    Path(String),
+}
+
+// the arguments of a subcommand or of `play`, `--name=value` and `--name
+// value` are both accepted
+pub struct CliArgs<'a> {
+    args: slice::Iter<'a, String>,
+    // short names paired with the long ones they stand for
+    short_names: Vec<(&'a str, &'a str)>,
+    // the option last returned and the value given after its `=`
+    name: &'a str,
+    inline_value: Option<&'a str>,
+}
+
+impl<'a> CliArgs<'a> {
+    pub fn new(args: &'a [String]) -> Self {
+        CliArgs { args: args.iter(), short_names: vec![], name: "", inline_value: None }
+    }
+
+    // options given by a short name are returned by their long name
+    pub fn with_short_names(mut self, short_names: Vec<(&'a str, &'a str)>) -> Self {
+        self.short_names = short_names;
+        self
+    }
+
+    pub fn next_arg(&mut self) -> Option<CliArg<'a>> {
+        let arg = self.args.next()?;
+        if arg == STDIO_PATH || !arg.starts_with('-') {
+            return Some(CliArg::Path(arg.clone()));
+        }
+        (self.name, self.inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        if let Some((_, long)) = self.short_names.iter().find(|(short, _)| *short == self.name) {
+            self.name = long;
+        }
+        Some(CliArg::Option(self.name))
+    }
+
+    // the value of the option last returned, `value_name` tells what it is
+    pub fn value(&mut self, value_name: &str) -> Result<String, String> {
+        self.inline_value.take()
+            .map(|value| value.to_string())
+            .or_else(|| self.args.next().cloned())
+            .ok_or(format!("{} needs a {} value", self.name, value_name))
+    }
+
+    // checks that the option last returned, a switch, was given no value
+    pub fn no_value(&self) -> Result<(), String> {
+        match self.inline_value {
+            Some(_) => Err(format!("{} takes no value", self.name)),
+            None => Ok(()),
+        }
+    }
+
+    // the error for the option last returned
+    pub fn unknown_option(&self) -> String {
+        format!("unknown option: {}", self.name)
+    }
+}
+
+// the format of `all` named `name`, for the `FromStr` of the formats
+pub fn parse_format<T: Copy + fmt::Display>(all: &[T], name: &str) -> Result<T, String> {
+    all.iter()
+        .find(|format| format.to_string() == name)
+        .copied()
+        .ok_or(format!("unknown format: {}", name))
+}
+
+// `svg, png` for the usage
+pub fn format_names<T: fmt::Display>(all: &[T]) -> String {
+    all.iter().map(|format| format.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+// guessed from the file extension
+pub fn format_from_path<T: FromStr>(path: &str) -> Option<T> {
+    let extension = Path::new(path).extension()?.to_string_lossy().to_lowercase();
+    extension.parse().ok()
+}
+
+// the arguments of a command line without quotes, for the tests
+#[cfg(test)]
+pub fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(|arg| arg.to_string()).collect()
+}
+
+fn usage() -> String {
+    let mut usage = format!(
+        "fastgo {}\n\nUsage: fastgo [SUBCOMMAND] [OPTIONS] [FILE]\n\nSubcommands:\n",
+        env!("CARGO_PKG_VERSION"));
+    for (name, summary) in SUBCOMMANDS {
+        usage.push_str(&format!("  {:<10} {}\n", name, summary));
+    }
+    usage.push_str("\nOptions:\n  -h, --help       show this help\n  -V, --version    show the version\n");
+    usage.push_str("\nSee 'fastgo help SUBCOMMAND' for the options of a subcommand.");
+    usage
+}
+
+fn play_usage() -> String {
+    let mut usage = "Usage: fastgo [play] [OPTIONS] [FILE]\n\nOptions:\n".to_string();
+    for (short, long, value_name, description) in OPTIONS {
+        let option = format!("{}, {} {}", short, long, value_name);
+        usage.push_str(&format!("  {:<32} {}\n", option, description));
+    }
+    usage.push_str("  -h, --help                       show this help");
+    if let Some(prefs_path) = Prefs::path() {
+        usage.push_str(&format!("\n\nOptions override the preferences in {}", prefs_path.display()));
+    }
+    usage
+}
+
+fn convert_usage() -> String {
+    let formats = format_names(&RecordFormat::ALL);
+    format!("Usage: fastgo convert [OPTIONS] INPUT OUTPUT\n\n\
+        Reads INPUT and writes it to OUTPUT in another format, - stands for\n\
+        stdin or stdout. Formats are {}, guessed from the file extensions.\n\n\
+        Options:\n  \
+        --from FORMAT    format of INPUT\n  \
+        --to FORMAT      format of OUTPUT\n  \
+        -h, --help       show this help", formats)
+}
+
+fn validate_usage() -> String {
+    let formats = format_names(&ReportFormat::ALL);
+    format!("Usage: fastgo validate [OPTIONS] PATH...\n\n\
+        Checks the syntax of each sgf file, or of the sgf files found in each\n\
+        directory, and replays every variation to find illegal moves. Exits\n\
+        with 1 when errors are found.\n\n\
+        Options:\n  \
+        --format FORMAT  report as {} (default text)\n  \
+        -h, --help       show this help", formats)
+}
+
+fn info_usage() -> String {
+    let formats = format_names(&InfoFormat::ALL);
+    format!("Usage: fastgo info [OPTIONS] PATH...\n\n\
+        Lists the root properties of each sgf file, or of the sgf files found\n\
+        in each directory: {}.\n\n\
+        Options:\n  \
+        --format FORMAT  list as {} (default table)\n  \
+        -h, --help       show this help", COLUMNS.join(", "), formats)
+}
+
+fn diagram_usage() -> String {
+    let formats = format_names(&DiagramFormat::ALL);
+    let themes = BandTheme::ALL.map(|band_theme| band_theme.to_string()).join(", ");
+    format!("Usage: fastgo diagram [OPTIONS] INPUT OUTPUT\n\n\
+        Draws the position after a move of the main line of INPUT to OUTPUT,\n\
+        - stands for stdin or stdout. Formats are {}, guessed from the\n\
+        extension of OUTPUT.\n\n\
+        Options:\n  \
+        --format FORMAT      format of OUTPUT\n  \
+        --move N             position after N moves (default the last)\n  \
+        --numbers FROM-TO    number the moves FROM to TO, or from FROM on\n  \
+        --coordinates        show the coordinates\n  \
+        --no-markup          leave out the markup of the node\n  \
+        --region C1:C2       only the corners C1 to C2, e.g. aa:jj\n  \
+        --width PIXELS       width of the picture (default {})\n  \
+        --theme THEME        one of {} (default print)\n  \
+        -h, --help           show this help", formats, DEFAULT_DIAGRAM_WIDTH, themes)
+}
+
+fn kifu_usage() -> String {
+    let formats = format_names(&KifuFormat::ALL);
+    let themes = BandTheme::ALL.map(|band_theme| band_theme.to_string()).join(", ");
+    format!("Usage: fastgo kifu [OPTIONS] INPUT OUTPUT\n\n\
+        Prints the main line of INPUT as a series of figures with numbered\n\
+        moves below the game info, moves on points taken earlier in a figure\n\
+        are noted as '12 at 5'. Formats are {}, guessed from the extension\n\
+        of OUTPUT, svg pages go to OUTPUT-1.svg, OUTPUT-2.svg and so on.\n\n\
+        Options:\n  \
+        --format FORMAT      format of OUTPUT\n  \
+        --moves N            moves in a figure (default {})\n  \
+        --per-page N         figures on a page (default {})\n  \
+        --coordinates        show the coordinates\n  \
+        --theme THEME        one of {} (default print)\n  \
+        -h, --help           show this help", formats, DEFAULT_FIGURE_MOVES, DEFAULT_FIGURES_PER_PAGE, themes)
+}
+
+fn subcommand_usage(subcommand: &str) -> Result<String, String> {
+    match subcommand {
+        "play" => Ok(play_usage()),
+        "convert" => Ok(convert_usage()),
+        "validate" => Ok(validate_usage()),
+        "info" => Ok(info_usage()),
+        "diagram" => Ok(diagram_usage()),
+        "kifu" => Ok(kifu_usage()),
+        "help" => Ok(usage()),
+        _ => Err(format!("unknown subcommand: {}", subcommand)),
+    }
+}
+
+// `args` without the program name, options override the settings of `config`
+pub fn parse_args(args: &[String], config: &Config) -> Result<CliCommand, String> {
+    let (subcommand, options) = match args.first().map(|arg| arg.as_str()) {
+        None => return Ok(CliCommand::Play(config.clone())),
+        Some("-h") | Some("--help") => return Ok(CliCommand::Help(usage())),
+        Some("-V") | Some("--version") => return Ok(CliCommand::Help(format!("fastgo {}", env!("CARGO_PKG_VERSION")))),
+        Some(arg) if SUBCOMMANDS.iter().any(|(name, _)| *name == arg) => (arg, &args[1..]),
+        Some(_) => ("play", args),
+    };
+    if options.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return subcommand_usage(subcommand).map(CliCommand::Help);
+    }
+    match subcommand {
+        "help" => match options {
+            [] => Ok(CliCommand::Help(usage())),
+            [subcommand] => subcommand_usage(subcommand).map(CliCommand::Help),
+            _ => Err("help takes one subcommand".to_string()),
+        },
+        "convert" => Conversion::from_args(options).map(CliCommand::Convert),
+        "validate" => Validation::from_args(options).map(CliCommand::Validate),
+        "info" => Info::from_args(options).map(CliCommand::Info),
+        "diagram" => DiagramExport::from_args(options).map(CliCommand::Diagram),
+        "kifu" => KifuExport::from_args(options).map(CliCommand::Kifu),
+        _ => config.with_args(options).map(CliCommand::Play),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+
+    use super::{args, parse_args, CliCommand};
+
+    #[test]
+    pub fn test_subcommands() {
+        let config = Config::default();
+        assert!(matches!(parse_args(&[], &config), Ok(CliCommand::Play(_))));
+        assert!(matches!(parse_args(&args("--help"), &config), Ok(CliCommand::Help(help)) if help.contains("Subcommands:")));
+        assert!(matches!(parse_args(&args("help play"), &config), Ok(CliCommand::Help(help)) if help.contains("--handicap")));
+        assert!(matches!(parse_args(&args("play -sz 9 -h"), &config), Ok(CliCommand::Help(help)) if help.contains("--handicap")));
+        match parse_args(&args("play --size 13 -co"), &config) {
+            Ok(CliCommand::Play(config)) => assert_eq!(config.go_sz(), 13),
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(parse_args(&args("convert sgf/test.sgf out.json"), &config), Ok(CliCommand::Convert(_))));
+        assert!(matches!(parse_args(&args("validate --format json sgf"), &config), Ok(CliCommand::Validate(_))));
+        assert!(matches!(parse_args(&args("info --format=csv sgf"), &config), Ok(CliCommand::Info(_))));
+        assert!(parse_args(&args("diagram --move 5 --coordinates sgf/test.sgf -"), &config).is_err());
+        assert!(matches!(parse_args(&args("diagram --move=5 sgf/test.sgf out.svg"), &config), Ok(CliCommand::Diagram(_))));
+        assert!(matches!(parse_args(&args("kifu --moves 100 sgf/test.sgf game.pdf"), &config), Ok(CliCommand::Kifu(_))));
+        assert_eq!(parse_args(&args("help replay"), &config).err(), Some("unknown subcommand: replay".to_string()));
+        assert_eq!(parse_args(&args("-sz"), &config).err(), Some("--size needs a SIZE value".to_string()));
+    }
+}
@@ -1,10 +1,30 @@
+use std::{path::Path, str::FromStr};
+
+use json::JsonValue;
+
+use crate::{
+    auto_replay::{MIN_INTERVAL_MS, MAX_INTERVAL_MS},
+    band_layout::BandLayout,
+    band_theme::BandTheme,
+    cli::{CliArg, CliArgs},
+    game_clock::TimeControl,
+    keymap::Keymap,
+};
+
 pub const WINDOW_WIDTH: u32 = 1024;
 pub const WINDOW_HEIGHT: u32 = 768;
 pub const SCALE_FACTOR: f32 = 3.0 / 4.0;
 pub const GO_KM: f32 = 7.5;
 pub const GO_SZ: i32 = 19;
+pub const MIN_GO_SZ: i32 = 2;
+pub const MAX_GO_SZ: i32 = 19;
+pub const GO_HA: usize = 0;
+pub const SHOW_MOVE_NUMBERS: bool = false;
+pub const SHOW_COORDINATES: bool = false;
+pub const REPLAY_INTERVAL_MS: u32 = 1000;
+pub const RECENT_FILES_LIMIT: usize = 8;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Config {
     wind_width: u32,
     wind_height: u32,
@@ -14,6 +34,14 @@ pub struct Config {
     go_sz: i32,
     go_pb: String,
     go_pw: String,
+    go_ha: usize,
+    show_move_numbers: bool,
+    show_coordinates: bool,
+    band_theme: BandTheme,
+    keymap: Keymap,
+    time_control: Option<TimeControl>,
+    replay_interval_ms: u32,
+    problem_dir: String,
 }
 
 impl Config {
@@ -26,6 +54,14 @@ impl Config {
         go_sz: i32,
         go_pb: String,
         go_pw: String,
+        go_ha: usize,
+        show_move_numbers: bool,
+        show_coordinates: bool,
+        band_theme: BandTheme,
+        keymap: Keymap,
+        time_control: Option<TimeControl>,
+        replay_interval_ms: u32,
+        problem_dir: String,
     ) -> Self {
         Config {
             wind_width,
@@ -36,6 +72,14 @@ impl Config {
             go_sz,
             go_pb,
             go_pw,
+            go_ha,
+            show_move_numbers,
+            show_coordinates,
+            band_theme,
+            keymap,
+            time_control,
+            replay_interval_ms,
+            problem_dir,
         }
     }
 
@@ -67,9 +111,116 @@ impl Config {
         self.go_pw.clone()
     }
 
+    pub fn go_ha(&self) -> usize {
+        self.go_ha
+    }
+
+    // the same settings for a game with other players, size, komi or handicap,
+    // which is not read from a file
+    pub fn for_new_game(&self, go_sz: i32, go_km: f32, go_pb: String, go_pw: String, go_ha: usize) -> Config {
+        Config { sgf_path: String::default(), go_sz, go_km, go_pb, go_pw, go_ha, ..self.clone() }
+    }
+
     pub fn sgf_path(&self) -> String {
         self.sgf_path.clone()
     }
+
+    pub fn show_move_numbers(&self) -> bool {
+        self.show_move_numbers
+    }
+
+    pub fn show_coordinates(&self) -> bool {
+        self.show_coordinates
+    }
+
+    pub fn band_theme(&self) -> BandTheme {
+        self.band_theme
+    }
+
+    pub fn keymap(&self) -> Keymap {
+        self.keymap.clone()
+    }
+
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.time_control
+    }
+
+    pub fn replay_interval_ms(&self) -> u32 {
+        self.replay_interval_ms
+    }
+
+    pub fn problem_dir(&self) -> String {
+        self.problem_dir.clone()
+    }
+
+    pub fn set_window_size(&mut self, wind_width: u32, wind_height: u32) {
+        self.wind_width = wind_width;
+        self.wind_height = wind_height;
+    }
+
+    pub fn set_show_move_numbers(&mut self, show_move_numbers: bool) {
+        self.show_move_numbers = show_move_numbers;
+    }
+
+    pub fn set_show_coordinates(&mut self, show_coordinates: bool) {
+        self.show_coordinates = show_coordinates;
+    }
+
+    pub fn set_band_theme(&mut self, band_theme: BandTheme) {
+        self.band_theme = band_theme;
+    }
+
+    pub fn set_replay_interval_ms(&mut self, replay_interval_ms: u32) {
+        self.replay_interval_ms = replay_interval_ms;
+    }
+
+    // the settings kept in the preferences file, the files to open, key
+    // bindings and time control are given per launch
+    pub fn to_json(&self) -> JsonValue {
+        let mut prefs = JsonValue::new_object();
+        prefs["window_width"] = self.wind_width.into();
+        prefs["window_height"] = self.wind_height.into();
+        prefs["scale_factor"] = self.scale_factor.into();
+        prefs["komi"] = self.go_km.into();
+        prefs["size"] = self.go_sz.into();
+        prefs["black"] = self.go_pb.clone().into();
+        prefs["white"] = self.go_pw.clone().into();
+        prefs["handicap"] = self.go_ha.into();
+        prefs["move_numbers"] = self.show_move_numbers.into();
+        prefs["coordinates"] = self.show_coordinates.into();
+        prefs["theme"] = self.band_theme.to_string().into();
+        prefs["replay_interval_ms"] = self.replay_interval_ms.into();
+        prefs
+    }
+
+    // this config with the settings found in `prefs`, missing ones are kept
+    pub fn with_json(&self, prefs: &JsonValue) -> Result<Config, String> {
+        if !prefs.is_object() {
+            return Err("preferences must be a json object".to_string());
+        }
+        let mut config = self.clone();
+        let invalid = |key: &str| format!("invalid {} in preferences", key);
+        for (key, value) in prefs.entries() {
+            match key {
+                "window_width" => config.wind_width = value.as_u32().ok_or(invalid(key))?,
+                "window_height" => config.wind_height = value.as_u32().ok_or(invalid(key))?,
+                "scale_factor" => config.scale_factor = value.as_f32().ok_or(invalid(key))?,
+                "komi" => config.go_km = value.as_f32().ok_or(invalid(key))?,
+                "size" => config.go_sz = value.as_i32().ok_or(invalid(key))?,
+                "black" => config.go_pb = value.as_str().ok_or(invalid(key))?.to_string(),
+                "white" => config.go_pw = value.as_str().ok_or(invalid(key))?.to_string(),
+                "handicap" => config.go_ha = value.as_usize().ok_or(invalid(key))?,
+                "move_numbers" => config.show_move_numbers = value.as_bool().ok_or(invalid(key))?,
+                "coordinates" => config.show_coordinates = value.as_bool().ok_or(invalid(key))?,
+                "theme" => config.band_theme = value.as_str().ok_or(invalid(key))?.parse()?,
+                "replay_interval_ms" => config.replay_interval_ms = value.as_u32().ok_or(invalid(key))?,
+                // written by other versions
+                _ => {},
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
 }
 
 impl Default for Config {
@@ -82,43 +233,169 @@ impl Default for Config {
             GO_KM,
             GO_SZ,
             String::default(),
+            String::default(),
+            GO_HA,
+            SHOW_MOVE_NUMBERS,
+            SHOW_COORDINATES,
+            BandTheme::default(),
+            Keymap::default(),
+            None,
+            REPLAY_INTERVAL_MS,
             String::default())
     }
 }
 
-impl From<Vec<String>> for Config {
-    fn from(args: Vec<String>) -> Self {
-        let mut config = Config::default();
-
-        for mut _i in 0..args.len() {
-            let arg = args[_i].as_str();
-            if arg.eq("-w") {
-                config.wind_width = args[_i + 1].parse().unwrap();
-                _i += 1;
-            } else if arg.eq("-h") {
-                config.wind_height = args[_i + 1].parse().unwrap();
-                _i += 1;
-            } else if arg.eq("-sf") {
-                config.scale_factor = args[_i + 1].parse().unwrap();
-                _i += 1;
-            } else if arg.eq("-km") {
-                config.go_km = args[_i + 1].parse().unwrap();
-                _i += 1;
-            } else if arg.eq("-sz") {
-                config.go_sz = args[_i + 1].parse().unwrap();
-                _i += 1;
-            } else if arg.eq("-sgf") {
-                config.sgf_path = args[_i + 1].clone();
-                _i += 1;
-            } else if arg.eq("-pb") {
-                config.go_pb = args[_i + 1].clone();
-                _i += 1;
-            } else if arg.eq("-pw") {
-                config.go_pw = args[_i + 1].clone();
-                _i += 1;
+// short and long name, value name (empty for a switch) and description of
+// each option of the band window
+pub const OPTIONS: [(&str, &str, &str, &str); 16] = [
+    ("-w", "--width", "PIXELS", "window width"),
+    ("-H", "--height", "PIXELS", "window height"),
+    ("-sf", "--scale-factor", "FACTOR", "part of the window width taken by the band, up to 1"),
+    ("-sgf", "--sgf", "FILE", "sgf file to open, also given without an option"),
+    ("-km", "--komi", "KOMI", "komi of a new game, a multiple of 0.5"),
+    ("-sz", "--size", "SIZE", "band size of a new game"),
+    ("-pb", "--black", "NAME", "black player of a new game"),
+    ("-pw", "--white", "NAME", "white player of a new game"),
+    ("-ha", "--handicap", "STONES", "handicap stones of a new game"),
+    ("-mn", "--move-numbers", "", "show move numbers"),
+    ("-co", "--coordinates", "", "show coordinates"),
+    ("-th", "--theme", "THEME", "band theme"),
+    ("-keys", "--keys", "SPEC", "key bindings as action=key pairs, e.g. back=h,forward=l"),
+    ("-tc", "--time-control", "SPEC", "e.g. absolute:600, byoyomi:600/5x30, canadian:600/25/300, fischer:300+10"),
+    ("-ri", "--replay-interval", "MS", "auto-replay interval in milliseconds"),
+    ("-tsumego", "--tsumego", "DIR", "directory of problems to solve"),
+];
+
+fn parse_value<T: FromStr>(option: &str, value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("invalid value '{}' for {}", value, option))
+}
+
+impl Config {
+    // the first setting out of range, checked for the command line and new games
+    pub fn validate(&self) -> Result<(), String> {
+        if self.wind_width == 0 || self.wind_height == 0 {
+            return Err("window width and height must be positive".to_string());
+        }
+        if !(self.scale_factor > 0.0 && self.scale_factor <= 1.0) {
+            return Err("scale factor must be above 0 and up to 1".to_string());
+        }
+        if !(MIN_GO_SZ..=MAX_GO_SZ).contains(&self.go_sz) {
+            return Err(format!("size must be a number from {} to {}", MIN_GO_SZ, MAX_GO_SZ));
+        }
+        if !self.go_km.is_finite() || (self.go_km * 2.0).fract() != 0.0 {
+            return Err("komi must be a multiple of 0.5".to_string());
+        }
+        if self.go_ha == 1 || (self.go_ha > 1 && BandLayout::handicap_points(self.go_sz as usize, self.go_ha).is_empty()) {
+            return Err(format!("a {}x{} band cannot take {} handicap stones", self.go_sz, self.go_sz, self.go_ha));
+        }
+        // names are written into sgf values as they are
+        if [&self.go_pb, &self.go_pw].iter().any(|name| name.contains(']') || name.contains('\\')) {
+            return Err("player names cannot contain ] or \\".to_string());
+        }
+        if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&self.replay_interval_ms) {
+            return Err(format!("replay interval must be from {} to {} ms", MIN_INTERVAL_MS, MAX_INTERVAL_MS));
+        }
+        if !self.sgf_path.is_empty() && !Path::new(&self.sgf_path).is_file() {
+            return Err(format!("no such file: {}", self.sgf_path));
+        }
+        if !self.problem_dir.is_empty() && !Path::new(&self.problem_dir).is_dir() {
+            return Err(format!("no such directory: {}", self.problem_dir));
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    // this config with the options after the program name or subcommand
+    pub fn with_args(&self, args: &[String]) -> Result<Config, String> {
+        let mut config = self.clone();
+
+        let short_names = OPTIONS.iter().map(|(short, long, _, _)| (*short, *long)).collect();
+        let mut args = CliArgs::new(args).with_short_names(short_names);
+        while let Some(arg) = args.next_arg() {
+            let long = match arg {
+                CliArg::Option(long) => long,
+                CliArg::Path(path) => {
+                    if !config.sgf_path.is_empty() {
+                        return Err(format!("unexpected argument: {}", path));
+                    }
+                    config.sgf_path = path;
+                    continue;
+                },
+            };
+            let (_, long, value_name, _) = OPTIONS.iter()
+                .find(|(_, option_long, _, _)| *option_long == long)
+                .ok_or(args.unknown_option())?;
+            let value = if value_name.is_empty() {
+                args.no_value()?;
+                String::default()
+            } else {
+                args.value(value_name)?
+            };
+            match *long {
+                "--width" => config.wind_width = parse_value(long, &value)?,
+                "--height" => config.wind_height = parse_value(long, &value)?,
+                "--scale-factor" => config.scale_factor = parse_value(long, &value)?,
+                "--sgf" => config.sgf_path = value,
+                "--komi" => config.go_km = parse_value(long, &value)?,
+                "--size" => config.go_sz = parse_value(long, &value)?,
+                "--black" => config.go_pb = value,
+                "--white" => config.go_pw = value,
+                "--handicap" => config.go_ha = parse_value(long, &value)?,
+                "--move-numbers" => config.show_move_numbers = true,
+                "--coordinates" => config.show_coordinates = true,
+                "--theme" => config.band_theme = value.parse()?,
+                "--keys" => config.keymap = Keymap::from_spec(&value)?,
+                "--time-control" => config.time_control = Some(value.parse()?),
+                "--replay-interval" => config.replay_interval_ms = parse_value(long, &value)?,
+                _ => config.problem_dir = value,
             }
         }
 
-        config
+        config.validate()?;
+        Ok(config)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{band_theme::BandTheme, cli::args};
+
+    use super::Config;
+
+    #[test]
+    pub fn test_parse_args() {
+        let config = Config::default().with_args(&args("-w 800 --height=600 --size 9 -ha 4 -mn --black Lee")).unwrap();
+        assert_eq!((config.window_width(), config.window_height()), (800, 600));
+        assert_eq!((config.go_sz(), config.go_ha(), config.go_pb()), (9, 4, "Lee".to_string()));
+        assert!(config.show_move_numbers());
+        assert!(!config.show_coordinates());
+
+        for (line, err) in [
+            ("-w", "--width needs a PIXELS value"),
+            ("--width wide", "invalid value 'wide' for --width"),
+            ("--size 25", "size must be a number from 2 to 19"),
+            ("--komi 6.2", "komi must be a multiple of 0.5"),
+            ("-sz 5 -ha 2", "a 5x5 band cannot take 2 handicap stones"),
+            ("--coordinates=yes", "--coordinates takes no value"),
+            ("--colour black", "unknown option: --colour"),
+            ("no-such-file.sgf", "no such file: no-such-file.sgf"),
+        ] {
+            assert_eq!(Config::default().with_args(&args(line)).err(), Some(err.to_string()), "{}", line);
+        }
+    }
+
+    #[test]
+    pub fn test_json_prefs() {
+        let mut config = Config::default().with_args(&args("--size 13 --white Cho --theme dark")).unwrap();
+        config.set_window_size(800, 600);
+        let prefs = Config::default().with_json(&config.to_json()).unwrap();
+        assert_eq!((prefs.go_sz(), prefs.go_pw(), prefs.band_theme()), (13, "Cho".to_string(), BandTheme::Dark));
+        assert_eq!((prefs.window_width(), prefs.window_height()), (800, 600));
+
+        let prefs = json::parse(r#"{"komi": 0.5, "unknown": 1}"#).unwrap();
+        assert_eq!(Config::default().with_json(&prefs).unwrap().go_km(), 0.5);
+        let prefs = json::parse(r#"{"size": "nineteen"}"#).unwrap();
+        assert_eq!(Config::default().with_json(&prefs).err(), Some("invalid size in preferences".to_string()));
+    }
+}
@@ -0,0 +1,144 @@
+use std::{fmt, fs, io::{self, Read, Write}, str::FromStr};
+
+use crate::{
+    cli::{self, CliArg, CliArgs},
+    game_tree::GameTree,
+};
+
+// `-` reads from stdin or writes to stdout
+pub const STDIO_PATH: &str = "-";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    Sgf,
+    Json,
+}
+
+impl RecordFormat {
+    pub const ALL: [RecordFormat; 2] = [
+        RecordFormat::Sgf,
+        RecordFormat::Json,
+    ];
+
+    pub fn from_path(path: &str) -> Option<RecordFormat> {
+        cli::format_from_path(path)
+    }
+
+    pub fn parse_record(&self, content: &str) -> Result<GameTree, String> {
+        match self {
+            RecordFormat::Sgf => GameTree::from_sgf(content),
+            RecordFormat::Json => {
+                let json = json::parse(content).map_err(|err| err.to_string())?;
+                GameTree::from_json(&json)
+            },
+        }
+    }
+
+    pub fn format_record(&self, game_tree: &GameTree) -> String {
+        match self {
+            RecordFormat::Sgf => game_tree.to_sgf(),
+            RecordFormat::Json => game_tree.to_json().map(|json| json::stringify_pretty(json, 2)).unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for RecordFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RecordFormat::Sgf => "sgf",
+            RecordFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for RecordFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        cli::parse_format(&RecordFormat::ALL, s)
+    }
+}
+
+// one record read in a format and written in another
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    input: String,
+    output: String,
+    from: RecordFormat,
+    to: RecordFormat,
+}
+
+impl Conversion {
+    // `[--from FORMAT] [--to FORMAT] INPUT OUTPUT`, formats default to the
+    // file extensions
+    pub fn from_args(args: &[String]) -> Result<Conversion, String> {
+        let mut from = None;
+        let mut to = None;
+        let mut paths = vec![];
+        let mut args = CliArgs::new(args);
+        while let Some(arg) = args.next_arg() {
+            match arg {
+                CliArg::Option("--from") => from = Some(args.value("FORMAT")?.parse()?),
+                CliArg::Option("--to") => to = Some(args.value("FORMAT")?.parse()?),
+                CliArg::Option(_) => return Err(args.unknown_option()),
+                CliArg::Path(path) => paths.push(path),
+            }
+        }
+        let (input, output) = match paths.as_slice() {
+            [input, output] => (input.clone(), output.clone()),
+            _ => return Err("convert needs an INPUT and an OUTPUT file".to_string()),
+        };
+        let from = from.or(RecordFormat::from_path(&input)).ok_or(format!("give --from for {}", input))?;
+        let to = to.or(RecordFormat::from_path(&output)).ok_or(format!("give --to for {}", output))?;
+        Ok(Conversion { input, output, from, to })
+    }
+
+    pub fn run(&self) -> Result<(), String> {
+        let content = read_input(&self.input)?;
+        let game_tree = self.from.parse_record(&content)
+            .map_err(|err| format!("{}: {}", self.input, err))?;
+        write_output(&self.output, self.to.format_record(&game_tree).as_bytes())
+    }
+}
+
+// the content of `path`, or of stdin for `-`
+pub fn read_input(path: &str) -> Result<String, String> {
+    let content = if path == STDIO_PATH {
+        let mut content = String::new();
+        io::stdin().read_to_string(&mut content).map(|_| content)
+    } else {
+        fs::read_to_string(path)
+    };
+    content.map_err(|err| format!("cannot read {}: {}", path, err))
+}
+
+// writes to `path`, or to stdout for `-`
+pub fn write_output(path: &str, content: &[u8]) -> Result<(), String> {
+    let written = if path == STDIO_PATH {
+        io::stdout().write_all(content)
+    } else {
+        fs::write(path, content)
+    };
+    written.map_err(|err| format!("cannot write {}: {}", path, err))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::args;
+
+    use super::{Conversion, RecordFormat};
+
+    #[test]
+    pub fn test_convert() {
+        let conversion = Conversion::from_args(&args("sgf/test.sgf --to=json -")).unwrap();
+        assert_eq!((conversion.from, conversion.to), (RecordFormat::Sgf, RecordFormat::Json));
+        assert_eq!(Conversion::from_args(&args("- game.json")).err(), Some("give --from for -".to_string()));
+        assert_eq!(Conversion::from_args(&args("game.sgf")).err(), Some("convert needs an INPUT and an OUTPUT file".to_string()));
+
+        let content = std::fs::read_to_string("sgf/test.sgf").unwrap();
+        let game_tree = RecordFormat::Sgf.parse_record(&content).unwrap();
+        let json = RecordFormat::Json.format_record(&game_tree);
+        assert_eq!(RecordFormat::Json.parse_record(&json).unwrap(), game_tree);
+    }
+}
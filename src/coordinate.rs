@@ -0,0 +1,104 @@
+use std::fmt;
+
+use crate::band_layout::BandLayout;
+
+// what can be typed into the command bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coordinate {
+    Point(usize, usize),
+    MoveNumber(usize),
+    Pass,
+}
+
+impl Coordinate {
+    // accepts GTP (`Q16`, rows from the bottom, no `I`), SGF (`pd`),
+    // numeric (`16,4`, 1-based column and row from the top left), `#120`
+    // for a move number and `pass`
+    pub fn parse(input: &str, dim: usize) -> Result<Coordinate, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err("nothing entered".to_string());
+        }
+        if input.eq_ignore_ascii_case("pass") {
+            return Ok(Coordinate::Pass);
+        }
+        if let Some(move_number) = input.strip_prefix('#') {
+            return move_number.trim().parse()
+                .map(Coordinate::MoveNumber)
+                .map_err(|_| format!("not a move number: {}", input));
+        }
+        let point = if let Some((x, y)) = input.split_once(',') {
+            Coordinate::parse_numeric(x, y)
+        } else if input.len() == 2 && input.chars().all(|ch| ch.is_ascii_lowercase()) {
+            Coordinate::parse_sgf(input)
+        } else {
+            Coordinate::parse_gtp(input, dim)
+        };
+        match point {
+            Some((x, y)) if x < dim && y < dim => Ok(Coordinate::Point(x, y)),
+            Some(_) => Err(format!("off the {}x{} band: {}", dim, dim, input)),
+            None => Err(format!("not a coordinate: {}", input)),
+        }
+    }
+
+    fn parse_gtp(input: &str, dim: usize) -> Option<(usize, usize)> {
+        let mut chars = input.chars();
+        let x = BandLayout::column_pos(chars.next()?)?;
+        let row: usize = chars.as_str().parse().ok()?;
+        if row == 0 || row > dim {
+            return None;
+        }
+        Some((x, dim - row))
+    }
+
+    fn parse_sgf(input: &str) -> Option<(usize, usize)> {
+        let mut chars = input.chars().map(|ch| ch as usize - 'a' as usize);
+        Some((chars.next()?, chars.next()?))
+    }
+
+    fn parse_numeric(x: &str, y: &str) -> Option<(usize, usize)> {
+        let x: usize = x.trim().parse().ok()?;
+        let y: usize = y.trim().parse().ok()?;
+        if x == 0 || y == 0 {
+            return None;
+        }
+        Some((x - 1, y - 1))
+    }
+}
+
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Coordinate::Point(x, y) => write!(f, "{}{}", (b'a' + *x as u8) as char, (b'a' + *y as u8) as char),
+            Coordinate::MoveNumber(move_number) => write!(f, "#{}", move_number),
+            Coordinate::Pass => write!(f, "pass"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Coordinate;
+
+    #[test]
+    pub fn test_parse_coordinate() {
+        assert_eq!(Coordinate::parse("Q16", 19), Ok(Coordinate::Point(15, 3)));
+        assert_eq!(Coordinate::parse("q16", 19), Ok(Coordinate::Point(15, 3)));
+        assert_eq!(Coordinate::parse("J1", 9), Ok(Coordinate::Point(8, 8)));
+        assert_eq!(Coordinate::parse("pd", 19), Ok(Coordinate::Point(15, 3)));
+        assert_eq!(Coordinate::parse("16, 4", 19), Ok(Coordinate::Point(15, 3)));
+        assert_eq!(Coordinate::parse(" #120 ", 19), Ok(Coordinate::MoveNumber(120)));
+        assert_eq!(Coordinate::parse("Pass", 19), Ok(Coordinate::Pass));
+    }
+
+    #[test]
+    pub fn test_invalid_coordinate() {
+        assert!(Coordinate::parse("I5", 19).is_err());
+        assert!(Coordinate::parse("Q20", 19).is_err());
+        assert!(Coordinate::parse("K5", 9).is_err());
+        assert!(Coordinate::parse("pd", 9).is_err());
+        assert!(Coordinate::parse("0,3", 19).is_err());
+        assert!(Coordinate::parse("#x", 19).is_err());
+        assert!(Coordinate::parse("", 19).is_err());
+    }
+}
@@ -0,0 +1,481 @@
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use iced::Color;
+
+use crate::{
+    band_layout::BandLayout,
+    band_theme::BandTheme,
+    coordinate::Coordinate,
+    game_tree::GameTree,
+    go_band::GoBand,
+    go_move::GoMove,
+    markup::{Markup, MarkupKind},
+};
+
+// intersections from `left`, `top` to `right`, `bottom`, both included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+}
+
+impl Region {
+    pub fn full(dim: usize) -> Self {
+        Region { left: 0, top: 0, right: dim.max(1) - 1, bottom: dim.max(1) - 1 }
+    }
+
+    // two opposite corners in any form the command bar takes, e.g. `aa:jj`
+    // or `A19:K10`
+    pub fn parse(input: &str, dim: usize) -> Result<Region, String> {
+        let (first, second) = input.split_once(':').ok_or(format!("not a region: {}", input))?;
+        let corner = |corner: &str| match Coordinate::parse(corner, dim)? {
+            Coordinate::Point(x, y) => Ok((x, y)),
+            _ => Err(format!("not a point: {}", corner)),
+        };
+        let (x1, y1) = corner(first)?;
+        let (x2, y2) = corner(second)?;
+        Ok(Region { left: x1.min(x2), top: y1.min(y2), right: x1.max(x2), bottom: y1.max(y2) })
+    }
+
+    pub fn contains(&self, pos_x: usize, pos_y: usize) -> bool {
+        (self.left..=self.right).contains(&pos_x) && (self.top..=self.bottom).contains(&pos_y)
+    }
+
+    // left, top, right and bottom
+    pub fn bounds(&self) -> (usize, usize, usize, usize) {
+        (self.left, self.top, self.right, self.bottom)
+    }
+
+    pub fn points(&self) -> Vec<(usize, usize)> {
+        (self.top..=self.bottom)
+            .flat_map(|y| (self.left..=self.right).map(move |x| (x, y)))
+            .collect()
+    }
+}
+
+// what gets painted, in the logical pixels of a `BandLayout`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Rect { origin: (f32, f32), size: (f32, f32), fill: Option<Color>, stroke: Option<(f32, Color)> },
+    Circle { center: (f32, f32), radius: f32, fill: Option<Color>, stroke: Option<(f32, Color)> },
+    // round capped, a closed line joins its last point to the first
+    Line { points: Vec<(f32, f32)>, closed: bool, width: f32, color: Color },
+    // centred on `position`
+    Text { content: String, position: (f32, f32), size: f32, color: Color },
+}
+
+impl Shape {
+    pub fn translated(&self, offset_x: f32, offset_y: f32) -> Shape {
+        let translate = |(x, y): (f32, f32)| (x + offset_x, y + offset_y);
+        match self.clone() {
+            Shape::Rect { origin, size, fill, stroke } => Shape::Rect { origin: translate(origin), size, fill, stroke },
+            Shape::Circle { center, radius, fill, stroke } => Shape::Circle { center: translate(center), radius, fill, stroke },
+            Shape::Line { points, closed, width, color } => Shape::Line { points: points.into_iter().map(translate).collect(), closed, width, color },
+            Shape::Text { content, position, size, color } => Shape::Text { content, position: translate(position), size, color },
+        }
+    }
+}
+
+// everything a band shows, taken apart from the band so the canvas and the
+// exporters draw it with the same shapes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagram {
+    dim: usize,
+    // stone states by column and row, as `GoBand::stone_state`
+    stones: Vec<Vec<i8>>,
+    move_numbers: HashMap<(usize, usize), usize>,
+    last_move: Option<(usize, usize)>,
+    markups: Vec<Markup>,
+    dimmed_points: Vec<(usize, usize)>,
+    hints: Vec<(i32, i32, i8, bool)>,
+    preview: Option<(i32, i32, i8)>,
+    show_coordinates: bool,
+    region: Region,
+    // the player to move, 1 for black and -1 for white
+    to_play: i8,
+}
+
+impl Diagram {
+    pub fn new(dim: usize, stones: Vec<Vec<i8>>, show_coordinates: bool) -> Self {
+        Diagram {
+            dim,
+            stones,
+            move_numbers: HashMap::new(),
+            last_move: None,
+            markups: vec![],
+            dimmed_points: vec![],
+            hints: vec![],
+            preview: None,
+            show_coordinates,
+            region: Region::full(dim),
+            to_play: 1,
+        }
+    }
+
+    // the position after `move_count` moves of the selected variation with
+    // the markup of that node, the moves in `numbered` (1 for the first move)
+    // carry their numbers
+    pub fn from_game_tree(game_tree: &GameTree, move_count: usize, numbered: Option<RangeInclusive<usize>>) -> Self {
+        let mut go_band = GoBand::new(GameTree::board_size(game_tree), 0, 0, 0, 0);
+        let move_id = move_count as i32 - 1;
+        let path_nodes = GameTree::get_path_nodes_until(game_tree, move_id);
+        for sgf_node in &path_nodes {
+            go_band.apply_sgf_node(sgf_node);
+        }
+        go_band.set_markups(Markup::from_sgf_nodes(&GameTree::get_node_props(game_tree, move_id)), Markup::dimmed_points(&path_nodes));
+        let mut diagram = go_band.diagram().printable();
+        if let Some(numbered) = numbered {
+            let move_range = numbered.start().saturating_sub(1)..=numbered.end().saturating_sub(1);
+            diagram.number_moves(&go_band.go_moves(), move_range, 0);
+        }
+        diagram
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    pub fn stone_state(&self, pos_x: usize, pos_y: usize) -> i8 {
+        self.stones[pos_x][pos_y]
+    }
+
+    pub fn move_number(&self, pos_x: usize, pos_y: usize) -> Option<usize> {
+        self.move_numbers.get(&(pos_x, pos_y)).copied()
+    }
+
+    // the first markup on the point
+    pub fn markup_kind(&self, pos_x: usize, pos_y: usize) -> Option<MarkupKind> {
+        self.markups.iter().find(|markup| markup.pos() == (pos_x, pos_y)).map(|markup| markup.kind())
+    }
+
+    pub fn show_coordinates(&self) -> bool {
+        self.show_coordinates
+    }
+
+    pub fn set_show_coordinates(&mut self, show_coordinates: bool) {
+        self.show_coordinates = show_coordinates;
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn to_play(&self) -> i8 {
+        self.to_play
+    }
+
+    pub fn set_to_play(&mut self, to_play: i8) {
+        self.to_play = to_play;
+    }
+
+    pub fn set_last_move(&mut self, last_move: Option<(usize, usize)>) {
+        self.last_move = last_move;
+    }
+
+    pub fn set_markups(&mut self, markups: Vec<Markup>, dimmed_points: Vec<(usize, usize)>) {
+        self.markups = markups;
+        self.dimmed_points = dimmed_points;
+    }
+
+    pub fn set_hints(&mut self, hints: Vec<(i32, i32, i8, bool)>) {
+        self.hints = hints;
+    }
+
+    pub fn set_preview(&mut self, preview: Option<(i32, i32, i8)>) {
+        self.preview = preview;
+    }
+
+    // leaves out what only helps while playing: the pointer preview, the
+    // variation hints and the mark on the last move
+    pub fn printable(mut self) -> Self {
+        self.preview = None;
+        self.hints.clear();
+        self.last_move = None;
+        self
+    }
+
+    // labels the latest move on each point with its index + 1 - `offset`,
+    // when the index is in `move_range` and its stone is still there
+    pub fn number_moves(&mut self, go_moves: &[GoMove], move_range: RangeInclusive<usize>, offset: usize) {
+        let mut latest_moves = HashMap::new();
+        for (move_idx, go_move) in go_moves.iter().enumerate() {
+            if go_move.is_pass() {
+                continue;
+            }
+            let (move_x, move_y, move_state) = go_move.move_pos();
+            latest_moves.insert((move_x, move_y), (move_idx, move_state));
+        }
+        self.move_numbers = latest_moves.into_iter()
+            .filter(|((x, y), (move_idx, move_state))| move_range.contains(move_idx) && self.stones[*x][*y] == *move_state)
+            .map(|(pos, (move_idx, _))| (pos, move_idx + 1 - offset))
+            .collect();
+    }
+
+    // a stone labelled with its move number whatever happens to it later,
+    // as the figures of a game record show them
+    pub fn place_numbered(&mut self, pos_x: usize, pos_y: usize, state: i8, move_number: usize) {
+        self.stones[pos_x][pos_y] = state;
+        self.move_numbers.insert((pos_x, pos_y), move_number);
+    }
+
+    // the area on show: the whole board, or the lines of the region with the
+    // band margin around them
+    pub fn viewport(&self, band_layout: &BandLayout) -> (f32, f32, f32, f32) {
+        if self.region == Region::full(self.dim) {
+            let (origin_x, origin_y) = band_layout.board_origin();
+            let board_size = band_layout.board_size();
+            return (origin_x, origin_y, board_size, board_size);
+        }
+        let margin = band_layout.band_margin();
+        let (left, top) = band_layout.point(self.region.left, self.region.top);
+        let (right, bottom) = band_layout.point(self.region.right, self.region.bottom);
+        (left - margin, top - margin, right - left + margin * 2.0, bottom - top + margin * 2.0)
+    }
+
+    // a layout of the whole band that makes the viewport `width` wide
+    pub fn layout_for_width(&self, width: f32) -> BandLayout {
+        let margin_grids = if self.show_coordinates { 1.0 } else { 0.5 };
+        let view_grids = (self.region.right - self.region.left) as f32 + margin_grids * 2.0;
+        let grid_count = if self.show_coordinates { self.dim + 1 } else { self.dim };
+        let board_size = width / view_grids * grid_count as f32;
+        BandLayout::new(self.dim, board_size, board_size, self.show_coordinates)
+    }
+
+    fn markup_color(&self, band_theme: BandTheme, pos_x: usize, pos_y: usize) -> Color {
+        band_theme.contrast_color(self.stone_state(pos_x, pos_y))
+    }
+
+    // back to front, shapes outside the viewport may be left to clipping
+    pub fn shapes(&self, band_layout: &BandLayout, band_theme: BandTheme) -> Vec<Shape> {
+        let dim = self.dim;
+        let region = self.region;
+        let point = |x: usize, y: usize| band_layout.point(x, y);
+        let grid_size = band_layout.grid_size();
+        let band_color = band_theme.band_color();
+        let line_color = band_theme.line_color();
+        let mut shapes = vec![];
+
+        let (view_x, view_y, view_width, view_height) = self.viewport(band_layout);
+        shapes.push(Shape::Rect { origin: (view_x, view_y), size: (view_width, view_height), fill: Some(band_color), stroke: None });
+
+        for i in 0..dim {
+            let width = if i == 0 || i == dim - 1 {
+                band_theme.wide_line_width()
+            } else {
+                band_theme.thin_line_width()
+            };
+            if (region.top..=region.bottom).contains(&i) {
+                shapes.push(Shape::Line { points: vec![point(0, i), point(dim - 1, i)], closed: false, width, color: line_color });
+            }
+            if (region.left..=region.right).contains(&i) {
+                shapes.push(Shape::Line { points: vec![point(i, 0), point(i, dim - 1)], closed: false, width, color: line_color });
+            }
+        }
+
+        for (x, y) in band_layout.star_points() {
+            if region.contains(x, y) {
+                shapes.push(Shape::Circle { center: point(x, y), radius: band_theme.star_radius(), fill: Some(line_color), stroke: None });
+            }
+        }
+
+        if self.show_coordinates {
+            let label_offset = band_layout.band_margin() * 0.55;
+            let label = |content: String, position: (f32, f32)| Shape::Text { content, position, size: grid_size * 0.4, color: line_color };
+            for x in region.left..=region.right {
+                let (pos_x, top_y) = point(x, region.top);
+                let (_, bottom_y) = point(x, region.bottom);
+                shapes.push(label(BandLayout::column_label(x), (pos_x, top_y - label_offset)));
+                shapes.push(label(BandLayout::column_label(x), (pos_x, bottom_y + label_offset)));
+            }
+            for y in region.top..=region.bottom {
+                let (left_x, pos_y) = point(region.left, y);
+                let (right_x, _) = point(region.right, y);
+                shapes.push(label(band_layout.row_label(y), (left_x - label_offset, pos_y)));
+                shapes.push(label(band_layout.row_label(y), (right_x + label_offset, pos_y)));
+            }
+        }
+
+        if let Some((x, y, state)) = self.preview {
+            let (center_x, center_y) = point(x.max(0) as usize, y.max(0) as usize);
+            let preview_half = grid_size / 4.0;
+            shapes.push(Shape::Rect {
+                origin: (center_x - preview_half, center_y - preview_half),
+                size: (preview_half * 2.0, preview_half * 2.0),
+                fill: Some(band_theme.stone_color(state)),
+                stroke: None,
+            });
+        }
+
+        for (x, y) in region.points() {
+            let band_state = self.stone_state(x, y);
+            if band_state == 0 {
+                continue;
+            }
+            let (center_x, center_y) = point(x, y);
+            if let Some(shadow_color) = band_theme.stone_shadow() {
+                let shadow_offset = grid_size * 0.06;
+                shapes.push(Shape::Circle {
+                    center: (center_x + shadow_offset, center_y + shadow_offset),
+                    radius: grid_size / 2.0,
+                    fill: Some(shadow_color),
+                    stroke: None,
+                });
+            }
+            shapes.push(Shape::Circle { center: (center_x, center_y), radius: grid_size / 2.0, fill: Some(band_theme.stone_color(band_state)), stroke: None });
+            if let Some(shading_color) = band_theme.stone_shading(band_state) {
+                shapes.push(Shape::Circle {
+                    center: (center_x - grid_size * 0.14, center_y - grid_size * 0.14),
+                    radius: grid_size * 0.16,
+                    fill: Some(shading_color),
+                    stroke: None,
+                });
+            }
+            if let Some(border_color) = band_theme.stone_border(band_state) {
+                shapes.push(Shape::Circle { center: (center_x, center_y), radius: grid_size / 2.0 - 0.5, fill: None, stroke: Some((1.0, border_color)) });
+            }
+
+            if self.markups.iter().any(|markup| markup.pos() == (x, y)) {
+                continue;
+            }
+            if let Some(move_number) = self.move_numbers.get(&(x, y)) {
+                let content = move_number.to_string();
+                let size = if content.len() > 2 { grid_size * 0.4 } else { grid_size * 0.5 };
+                shapes.push(Shape::Text { content, position: (center_x, center_y), size, color: self.markup_color(band_theme, x, y) });
+            } else if self.last_move == Some((x, y)) {
+                shapes.push(Shape::Circle {
+                    center: (center_x, center_y),
+                    radius: grid_size / 4.0,
+                    fill: Some(band_theme.contrast_color(band_state)),
+                    stroke: None,
+                });
+            }
+        }
+
+        for (x, y, state, selected) in self.hints.clone() {
+            if x < 0 || y < 0 || !region.contains(x as usize, y as usize) || state == 0 {
+                continue;
+            }
+            let color = if selected { band_theme.stone_color(state) } else { Color { a: 0.85, ..band_theme.stone_color(state) } };
+            shapes.push(Shape::Circle { center: point(x as usize, y as usize), radius: grid_size / 4.0, fill: Some(color), stroke: None });
+        }
+
+        for (x, y) in self.dimmed_points.clone() {
+            if region.contains(x, y) {
+                shapes.push(Shape::Circle { center: point(x, y), radius: grid_size / 2.0, fill: Some(Color { a: 0.6, ..band_color }), stroke: None });
+            }
+        }
+
+        for markup in self.markups.clone() {
+            let (x, y) = markup.pos();
+            if !region.contains(x, y) {
+                continue;
+            }
+            let (center_x, center_y) = point(x, y);
+            let color = self.markup_color(band_theme, x, y);
+            let markup_stroke = Some((2.0, color));
+            match markup.kind() {
+                MarkupKind::Circle => {
+                    shapes.push(Shape::Circle { center: (center_x, center_y), radius: grid_size * 0.25, fill: None, stroke: markup_stroke });
+                },
+                MarkupKind::Cross => {
+                    let half = grid_size * 0.2;
+                    shapes.push(Shape::Line {
+                        points: vec![(center_x - half, center_y - half), (center_x + half, center_y + half)],
+                        closed: false,
+                        width: 2.0,
+                        color,
+                    });
+                    shapes.push(Shape::Line {
+                        points: vec![(center_x + half, center_y - half), (center_x - half, center_y + half)],
+                        closed: false,
+                        width: 2.0,
+                        color,
+                    });
+                },
+                MarkupKind::Square => {
+                    let half = grid_size * 0.22;
+                    shapes.push(Shape::Rect { origin: (center_x - half, center_y - half), size: (half * 2.0, half * 2.0), fill: None, stroke: markup_stroke });
+                },
+                MarkupKind::Triangle => {
+                    let radius = grid_size * 0.3;
+                    shapes.push(Shape::Line {
+                        points: vec![
+                            (center_x, center_y - radius),
+                            (center_x + radius * 0.866, center_y + radius * 0.5),
+                            (center_x - radius * 0.866, center_y + radius * 0.5),
+                        ],
+                        closed: true,
+                        width: 2.0,
+                        color,
+                    });
+                },
+                MarkupKind::Label(label) => {
+                    if self.stone_state(x, y) == 0 {
+                        // hide the grid lines behind the label
+                        shapes.push(Shape::Circle { center: (center_x, center_y), radius: grid_size * 0.4, fill: Some(band_color), stroke: None });
+                    }
+                    shapes.push(Shape::Text { content: label, position: (center_x, center_y), size: grid_size * 0.6, color });
+                },
+                MarkupKind::TerritoryBlack | MarkupKind::TerritoryWhite => {
+                    let half = grid_size * 0.15;
+                    let territory_state = if markup.kind() == MarkupKind::TerritoryBlack { 1 } else { -1 };
+                    shapes.push(Shape::Rect {
+                        origin: (center_x - half, center_y - half),
+                        size: (half * 2.0, half * 2.0),
+                        fill: Some(band_theme.stone_color(territory_state)),
+                        stroke: None,
+                    });
+                },
+            }
+        }
+        shapes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{band_layout::BandLayout, band_theme::BandTheme, game_tree::GameTree};
+
+    use super::{Diagram, Region, Shape};
+
+    #[test]
+    pub fn test_game_tree_diagram() {
+        let game_tree = GameTree::from_sgf("(;SZ[9]AB[aa];B[cc];W[dd];B[ee]TR[cc]LB[gg:A])").unwrap();
+        let diagram = Diagram::from_game_tree(&game_tree, 2, Some(1..=2));
+        assert_eq!((diagram.stone_state(0, 0), diagram.stone_state(3, 3), diagram.stone_state(4, 4)), (1, -1, 0));
+        let shapes = diagram.shapes(&BandLayout::new(9, 180.0, 180.0, false), BandTheme::Monochrome);
+        let texts = shapes.iter()
+            .filter_map(|shape| match shape {
+                Shape::Text { content, .. } => Some(content.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["1", "2"]);
+
+        // markup hides the number underneath
+        let diagram = Diagram::from_game_tree(&game_tree, 3, Some(1..=3));
+        let shapes = diagram.shapes(&BandLayout::new(9, 180.0, 180.0, false), BandTheme::Monochrome);
+        assert!(shapes.contains(&Shape::Text { content: "A".to_string(), position: (130.0, 130.0), size: 12.0, color: iced::Color::BLACK }));
+        assert!(!shapes.iter().any(|shape| matches!(shape, Shape::Text { content, .. } if content == "1")));
+    }
+
+    #[test]
+    pub fn test_region() {
+        assert_eq!(Region::parse("ee:aa", 9), Ok(Region { left: 0, top: 0, right: 4, bottom: 4 }));
+        assert_eq!(Region::parse("A9:E5", 9), Ok(Region { left: 0, top: 0, right: 4, bottom: 4 }));
+        assert!(Region::parse("aa", 9).is_err());
+        assert!(Region::parse("aa:#3", 9).is_err());
+
+        let mut diagram = Diagram::new(9, vec![vec![0; 9]; 9], false);
+        let band_layout = BandLayout::new(9, 180.0, 180.0, false);
+        assert_eq!(diagram.viewport(&band_layout), (0.0, 0.0, 180.0, 180.0));
+        diagram.set_region(Region::parse("aa:ee", 9).unwrap());
+        assert_eq!(diagram.viewport(&band_layout), (0.0, 0.0, 100.0, 100.0));
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+
+use crate::game_tree::SgfKey;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditTool {
+    Play,
+    AddBlack,
+    AddWhite,
+    AddEmpty,
+    Triangle,
+    Square,
+    Circle,
+    Cross,
+    Letter,
+    Number,
+}
+
+impl EditTool {
+    pub const ALL: [EditTool; 10] = [
+        EditTool::Play,
+        EditTool::AddBlack,
+        EditTool::AddWhite,
+        EditTool::AddEmpty,
+        EditTool::Triangle,
+        EditTool::Square,
+        EditTool::Circle,
+        EditTool::Cross,
+        EditTool::Letter,
+        EditTool::Number,
+    ];
+
+    pub fn sgf_key(&self) -> Option<SgfKey> {
+        match self {
+            EditTool::Play => None,
+            EditTool::AddBlack => Some(SgfKey::AB),
+            EditTool::AddWhite => Some(SgfKey::AW),
+            EditTool::AddEmpty => Some(SgfKey::AE),
+            EditTool::Triangle => Some(SgfKey::TR),
+            EditTool::Square => Some(SgfKey::SQ),
+            EditTool::Circle => Some(SgfKey::CR),
+            EditTool::Cross => Some(SgfKey::MA),
+            EditTool::Letter | EditTool::Number => Some(SgfKey::LB),
+        }
+    }
+
+    pub fn is_setup(&self) -> bool {
+        match self {
+            EditTool::AddBlack | EditTool::AddWhite | EditTool::AddEmpty => true,
+            _ => false,
+        }
+    }
+
+    // the first letter or number not used by `labels` yet
+    pub fn next_label(&self, labels: &Vec<String>) -> Option<String> {
+        let candidates: Vec<String> = match self {
+            EditTool::Letter => ('A'..='Z').map(|ch| ch.to_string()).collect(),
+            EditTool::Number => (1..=361).map(|num: u32| num.to_string()).collect(),
+            _ => vec![],
+        };
+        candidates.into_iter().find(|candidate| !labels.contains(candidate))
+    }
+}
+
+impl fmt::Display for EditTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EditTool::Play => "Play",
+            EditTool::AddBlack => "Add black",
+            EditTool::AddWhite => "Add white",
+            EditTool::AddEmpty => "Add empty",
+            EditTool::Triangle => "Triangle",
+            EditTool::Square => "Square",
+            EditTool::Circle => "Circle",
+            EditTool::Cross => "Cross",
+            EditTool::Letter => "Letter",
+            EditTool::Number => "Number",
+        };
+        write!(f, "{}", name)
+    }
+}
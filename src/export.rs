@@ -0,0 +1,190 @@
+use std::{fmt, str::FromStr};
+
+use crate::{
+    ascii,
+    band_theme::BandTheme,
+    cli::{self, CliArg, CliArgs},
+    convert::{self, RecordFormat},
+    diagram::{Diagram, Region},
+    game_tree::GameTree,
+    png,
+    svg,
+};
+
+// pixels across a diagram unless asked otherwise
+pub const DEFAULT_DIAGRAM_WIDTH: u32 = 400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagramFormat {
+    Svg,
+    Png,
+    Ascii,
+}
+
+impl DiagramFormat {
+    pub const ALL: [DiagramFormat; 3] = [
+        DiagramFormat::Svg,
+        DiagramFormat::Png,
+        DiagramFormat::Ascii,
+    ];
+
+    pub fn render(&self, diagram: &Diagram, band_theme: BandTheme, width: u32) -> Vec<u8> {
+        match self {
+            DiagramFormat::Svg => svg::diagram_svg(diagram, band_theme, width).into_bytes(),
+            DiagramFormat::Png => png::diagram_png(diagram, band_theme, width),
+            DiagramFormat::Ascii => format!("{}\n", ascii::diagram_ascii(diagram)).into_bytes(),
+        }
+    }
+}
+
+impl fmt::Display for DiagramFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DiagramFormat::Svg => "svg",
+            DiagramFormat::Png => "png",
+            DiagramFormat::Ascii => "ascii",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for DiagramFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        cli::parse_format(&DiagramFormat::ALL, s)
+    }
+}
+
+// `FROM-TO` or `FROM`, the latter numbers up to the move on show
+fn parse_numbers(value: &str) -> Result<(usize, Option<usize>), String> {
+    let parse_number = |number: &str| number.trim().parse::<usize>()
+        .ok()
+        .filter(|number| *number > 0)
+        .ok_or(format!("not a move number: {}", number));
+    match value.split_once('-') {
+        Some((from, to)) => Ok((parse_number(from)?, Some(parse_number(to)?))),
+        None => Ok((parse_number(value)?, None)),
+    }
+}
+
+// one node of a record drawn to a picture
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagramExport {
+    input: String,
+    output: String,
+    diagram_format: DiagramFormat,
+    // moves played before the diagram, the whole main line when not given
+    move_count: Option<usize>,
+    numbers: Option<(usize, Option<usize>)>,
+    show_coordinates: bool,
+    show_markup: bool,
+    // parsed once the board size is known
+    region: Option<String>,
+    width: u32,
+    band_theme: BandTheme,
+}
+
+impl DiagramExport {
+    // `[--format FORMAT] [--move N] [--numbers FROM-TO] [--coordinates]
+    // [--no-markup] [--region C1:C2] [--width PIXELS] [--theme THEME] INPUT
+    // OUTPUT`
+    pub fn from_args(args: &[String]) -> Result<DiagramExport, String> {
+        let mut diagram_format = None;
+        let mut move_count = None;
+        let mut numbers = None;
+        let mut show_coordinates = false;
+        let mut show_markup = true;
+        let mut region = None;
+        let mut width = DEFAULT_DIAGRAM_WIDTH;
+        let mut band_theme = BandTheme::Monochrome;
+        let mut paths = vec![];
+        let mut args = CliArgs::new(args);
+        while let Some(arg) = args.next_arg() {
+            match arg {
+                CliArg::Option("--format") => diagram_format = Some(args.value("FORMAT")?.parse()?),
+                CliArg::Option("--move") => {
+                    let value = args.value("N")?;
+                    move_count = Some(value.parse::<usize>().map_err(|_| format!("not a move number: {}", value))?);
+                },
+                CliArg::Option("--numbers") => numbers = Some(parse_numbers(&args.value("FROM-TO")?)?),
+                CliArg::Option("--coordinates") => show_coordinates = true,
+                CliArg::Option("--no-markup") => show_markup = false,
+                CliArg::Option("--region") => region = Some(args.value("C1:C2")?),
+                CliArg::Option("--width") => {
+                    let value = args.value("PIXELS")?;
+                    width = value.parse::<u32>()
+                        .ok()
+                        .filter(|width| *width > 0)
+                        .ok_or(format!("not a width: {}", value))?;
+                },
+                CliArg::Option("--theme") => band_theme = args.value("THEME")?.parse()?,
+                CliArg::Option(_) => return Err(args.unknown_option()),
+                CliArg::Path(path) => paths.push(path),
+            }
+        }
+        let (input, output) = match paths.as_slice() {
+            [input, output] => (input.clone(), output.clone()),
+            _ => return Err("diagram needs an INPUT record and an OUTPUT file".to_string()),
+        };
+        let diagram_format = diagram_format
+            .or(cli::format_from_path(&output))
+            .ok_or(format!("give --format for {}", output))?;
+        Ok(DiagramExport {
+            input, output, diagram_format, move_count, numbers, show_coordinates, show_markup, region, width, band_theme,
+        })
+    }
+
+    pub fn diagram(&self, game_tree: &GameTree) -> Result<Diagram, String> {
+        GameTree::supported_board_size(game_tree)?;
+        let path_move_count = GameTree::get_path_nodes(game_tree).iter().filter(|node| node.is_move()).count();
+        let move_count = self.move_count.unwrap_or(path_move_count);
+        if move_count > path_move_count {
+            return Err(format!("only {} moves in the main line", path_move_count));
+        }
+        let numbered = self.numbers.map(|(from, to)| from..=to.unwrap_or(move_count));
+        let mut diagram = Diagram::from_game_tree(game_tree, move_count, numbered);
+        diagram.set_show_coordinates(self.show_coordinates);
+        if !self.show_markup {
+            diagram.set_markups(vec![], vec![]);
+        }
+        if let Some(region) = self.region.as_ref() {
+            diagram.set_region(Region::parse(region, diagram.dim())?);
+        }
+        Ok(diagram)
+    }
+
+    pub fn run(&self) -> Result<(), String> {
+        let content = convert::read_input(&self.input)?;
+        let record_format = RecordFormat::from_path(&self.input).unwrap_or(RecordFormat::Sgf);
+        let game_tree = record_format.parse_record(&content)
+            .map_err(|err| format!("{}: {}", self.input, err))?;
+        let diagram = self.diagram(&game_tree)?;
+        convert::write_output(&self.output, &self.diagram_format.render(&diagram, self.band_theme, self.width))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{cli::{self, args}, game_tree::GameTree};
+
+    use super::{DiagramExport, DiagramFormat};
+
+    #[test]
+    pub fn test_diagram_export() {
+        let diagram_export = DiagramExport::from_args(&args("--move 2 --numbers=1 --region aa:ee game.sgf -")).err();
+        assert_eq!(diagram_export, Some("give --format for -".to_string()));
+        let diagram_export = DiagramExport::from_args(&args("--move 2 --numbers=1 --region aa:ee game.sgf out.svg")).unwrap();
+        assert_eq!(diagram_export.diagram_format, DiagramFormat::Svg);
+        assert_eq!(cli::format_from_path::<DiagramFormat>("position.PNG"), Some(DiagramFormat::Png));
+        assert_eq!(DiagramExport::from_args(&args("--width 0 a.sgf b.svg")).err(), Some("not a width: 0".to_string()));
+        assert_eq!(DiagramExport::from_args(&args("--numbers 0-3 a.sgf b.svg")).err(), Some("not a move number: 0".to_string()));
+
+        let game_tree = GameTree::from_sgf("(;SZ[9];B[cc];W[dd];B[ee])").unwrap();
+        let diagram = diagram_export.diagram(&game_tree).unwrap();
+        assert_eq!((diagram.stone_state(2, 2), diagram.stone_state(4, 4)), (1, 0));
+        assert!(DiagramExport::from_args(&args("--move 4 a.sgf b.svg")).unwrap().diagram(&game_tree).is_err());
+        let game_tree = GameTree::from_sgf("(;SZ[0])").unwrap();
+        assert!(diagram_export.diagram(&game_tree).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use crate::{config::RECENT_FILES_LIMIT, convert::RecordFormat};
+
+// `path` moved to the front of the recent files, which keep each file once
+// and only the latest few
+pub fn add_recent_file(recent_files: &mut Vec<String>, path: String) {
+    recent_files.retain(|recent_file| *recent_file != path);
+    recent_files.insert(0, path);
+    recent_files.truncate(RECENT_FILES_LIMIT);
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowserEntry {
+    name: String,
+    is_dir: bool,
+}
+
+impl BrowserEntry {
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+}
+
+// lists the sub directories and game records of one directory at a time
+#[derive(Debug, Clone)]
+pub struct FileBrowser {
+    dir: PathBuf,
+    entries: Vec<BrowserEntry>,
+}
+
+impl FileBrowser {
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        let mut entries = fs::read_dir(dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let is_dir = entry.path().is_dir();
+                // hidden files are left out like most file dialogs do
+                if name.starts_with('.') || !(is_dir || RecordFormat::from_path(&name).is_some()) {
+                    None
+                } else {
+                    Some(BrowserEntry { name, is_dir })
+                }
+            })
+            .collect::<Vec<_>>();
+        // directories first, each group by name
+        entries.sort_by(|entry, other| other.is_dir.cmp(&entry.is_dir).then(entry.name.cmp(&other.name)));
+        Ok(FileBrowser { dir: dir.to_path_buf(), entries })
+    }
+
+    pub fn dir(&self) -> String {
+        self.dir.to_string_lossy().to_string()
+    }
+
+    pub fn entries(&self) -> Vec<BrowserEntry> {
+        self.entries.clone()
+    }
+
+    pub fn entry_path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    pub fn parent(&self) -> Option<PathBuf> {
+        self.dir.parent().map(|parent| parent.to_path_buf())
+    }
+
+    // the records in `path` and its sub directories, in browsing order, or
+    // `path` itself when it is not a directory
+    pub fn find_records(path: &Path, record_format: RecordFormat) -> Result<Vec<PathBuf>, String> {
+        if !path.is_dir() {
+            return Ok(vec![path.to_path_buf()]);
+        }
+        let file_browser = FileBrowser::open(path).map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+        let mut records = vec![];
+        for entry in file_browser.entries() {
+            let entry_path = file_browser.entry_path(&entry.name());
+            if entry.is_dir() {
+                records.extend(FileBrowser::find_records(&entry_path, record_format)?);
+            } else if RecordFormat::from_path(&entry.name()) == Some(record_format) {
+                records.push(entry_path);
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs};
+
+    use crate::{config::RECENT_FILES_LIMIT, convert::RecordFormat};
+
+    use super::{add_recent_file, FileBrowser};
+
+    #[test]
+    pub fn test_browse() {
+        let dir = env::temp_dir().join(format!("fastgo-browser-{}", std::process::id()));
+        fs::create_dir_all(dir.join("pro")).unwrap();
+        for name in ["b.sgf", "a.SGF", "notes.txt", ".hidden.sgf", "pro/c.sgf", "pro/d.json"] {
+            fs::write(dir.join(name), "(;SZ[9])").unwrap();
+        }
+        let file_browser = FileBrowser::open(&dir).unwrap();
+        let entries = file_browser.entries().iter().map(|entry| (entry.name(), entry.is_dir())).collect::<Vec<_>>();
+        assert_eq!(entries, vec![
+            ("pro".to_string(), true),
+            ("a.SGF".to_string(), false),
+            ("b.sgf".to_string(), false),
+        ]);
+        assert_eq!(file_browser.parent(), Some(env::temp_dir()));
+        let records = FileBrowser::find_records(&dir, RecordFormat::Sgf).unwrap();
+        assert_eq!(records, vec![dir.join("pro/c.sgf"), dir.join("a.SGF"), dir.join("b.sgf")]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(FileBrowser::open(&dir).is_err());
+    }
+
+    #[test]
+    pub fn test_recent_files() {
+        let mut recent_files = vec!["a.sgf".to_string(), "b.sgf".to_string()];
+        add_recent_file(&mut recent_files, "b.sgf".to_string());
+        assert_eq!(recent_files, vec!["b.sgf".to_string(), "a.sgf".to_string()]);
+        for idx in 0..RECENT_FILES_LIMIT {
+            add_recent_file(&mut recent_files, format!("{}.sgf", idx));
+        }
+        assert_eq!(recent_files.len(), RECENT_FILES_LIMIT);
+        assert_eq!(recent_files[0], format!("{}.sgf", RECENT_FILES_LIMIT - 1));
+        assert!(!recent_files.contains(&"a.sgf".to_string()));
+    }
+}
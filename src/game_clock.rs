@@ -0,0 +1,312 @@
+use std::{fmt, str::FromStr, time::{Duration, Instant}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControl {
+    Absolute { main_time: Duration },
+    // `periods` periods of `period_time` each, a move within a period keeps it
+    ByoYomi { main_time: Duration, period_time: Duration, periods: u32 },
+    // `stones` moves have to be played within `period_time`
+    Canadian { main_time: Duration, period_time: Duration, stones: u32 },
+    Fischer { main_time: Duration, increment: Duration },
+}
+
+impl TimeControl {
+    pub fn main_time(&self) -> Duration {
+        match self {
+            TimeControl::Absolute { main_time }
+            | TimeControl::ByoYomi { main_time, .. }
+            | TimeControl::Canadian { main_time, .. }
+            | TimeControl::Fischer { main_time, .. } => *main_time,
+        }
+    }
+
+    // value of the `OT` property, None for absolute time
+    pub fn overtime(&self) -> Option<String> {
+        match self {
+            TimeControl::Absolute { .. } => None,
+            TimeControl::ByoYomi { period_time, periods, .. } =>
+                Some(format!("{}x{} byo-yomi", periods, period_time.as_secs())),
+            TimeControl::Canadian { period_time, stones, .. } =>
+                Some(format!("{}/{} Canadian", stones, period_time.as_secs())),
+            TimeControl::Fischer { increment, .. } =>
+                Some(format!("{} fischer", increment.as_secs())),
+        }
+    }
+}
+
+// `absolute:600`, `byoyomi:600/5x30`, `canadian:600/25/300` or
+// `fischer:300+10`, all times in seconds
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("unknown time control: {}", s);
+        let secs = |val: &str| val.trim().parse::<u64>().map(Duration::from_secs).map_err(|_| err());
+        let count = |val: &str| val.trim().parse::<u32>().ok().filter(|count| *count > 0).ok_or(err());
+        let (kind, spec) = s.split_once(':').ok_or(err())?;
+        match kind {
+            "absolute" => Ok(TimeControl::Absolute { main_time: secs(spec)? }),
+            "byoyomi" => {
+                let (main_time, overtime) = spec.split_once('/').ok_or(err())?;
+                let (periods, period_time) = overtime.split_once('x').ok_or(err())?;
+                Ok(TimeControl::ByoYomi { main_time: secs(main_time)?, period_time: secs(period_time)?, periods: count(periods)? })
+            },
+            "canadian" => {
+                let mut parts = spec.split('/');
+                let main_time = secs(parts.next().ok_or(err())?)?;
+                let stones = count(parts.next().ok_or(err())?)?;
+                let period_time = secs(parts.next().ok_or(err())?)?;
+                Ok(TimeControl::Canadian { main_time, period_time, stones })
+            },
+            "fischer" => {
+                let (main_time, increment) = spec.split_once('+').ok_or(err())?;
+                Ok(TimeControl::Fischer { main_time: secs(main_time)?, increment: secs(increment)? })
+            },
+            _ => Err(err()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PlayerClock {
+    main_left: Duration,
+    period_left: Duration,
+    // byo-yomi periods or Canadian stones still to play in the period
+    overtime_count: u32,
+    flagged: bool,
+}
+
+// clocks of both players, index 0 for black and 1 for white
+#[derive(Debug, Clone)]
+pub struct GameClock {
+    time_control: TimeControl,
+    clocks: [PlayerClock; 2],
+    running: Option<i8>,
+    last_tick: Option<Instant>,
+}
+
+impl GameClock {
+    pub fn new(time_control: TimeControl) -> Self {
+        let (period_left, overtime_count) = match time_control {
+            TimeControl::ByoYomi { period_time, periods, .. } => (period_time, periods),
+            TimeControl::Canadian { period_time, stones, .. } => (period_time, stones),
+            _ => (Duration::ZERO, 0),
+        };
+        let clock = PlayerClock {
+            main_left: time_control.main_time(),
+            period_left,
+            overtime_count,
+            flagged: false,
+        };
+        GameClock { time_control, clocks: [clock; 2], running: None, last_tick: None }
+    }
+
+    fn clock_idx(state: i8) -> usize {
+        if state == 1 { 0 } else { 1 }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.time_control
+    }
+
+    pub fn running(&self) -> Option<i8> {
+        self.running
+    }
+
+    // runs the clock of `state`, a flagged game stays stopped
+    pub fn start(&mut self, state: i8) {
+        if self.flagged().is_none() {
+            self.running = Some(state);
+            self.last_tick = None;
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.running = None;
+        self.last_tick = None;
+    }
+
+    // the state of the player who ran out of time
+    pub fn flagged(&self) -> Option<i8> {
+        if self.clocks[0].flagged {
+            Some(1)
+        } else if self.clocks[1].flagged {
+            Some(-1)
+        } else {
+            None
+        }
+    }
+
+    pub fn tick(&mut self, now: Instant) {
+        if let Some(last_tick) = self.last_tick {
+            self.elapse(now.saturating_duration_since(last_tick));
+        }
+        self.last_tick = self.running.map(|_| now);
+    }
+
+    pub fn elapse(&mut self, elapsed: Duration) {
+        let state = match self.running {
+            Some(state) => state,
+            None => return,
+        };
+        let time_control = self.time_control;
+        let clock = &mut self.clocks[GameClock::clock_idx(state)];
+        if clock.main_left > elapsed {
+            clock.main_left -= elapsed;
+            return;
+        }
+        let mut left = elapsed - clock.main_left;
+        clock.main_left = Duration::ZERO;
+        match time_control {
+            TimeControl::ByoYomi { period_time, .. } => {
+                while left >= clock.period_left && !clock.flagged {
+                    left -= clock.period_left;
+                    clock.overtime_count = clock.overtime_count.saturating_sub(1);
+                    if clock.overtime_count == 0 {
+                        clock.period_left = Duration::ZERO;
+                        clock.flagged = true;
+                    } else {
+                        clock.period_left = period_time;
+                    }
+                }
+                if !clock.flagged {
+                    clock.period_left -= left;
+                }
+            },
+            TimeControl::Canadian { .. } if clock.period_left > left => clock.period_left -= left,
+            TimeControl::Canadian { .. } => {
+                clock.period_left = Duration::ZERO;
+                clock.flagged = true;
+            },
+            TimeControl::Absolute { .. } | TimeControl::Fischer { .. } => clock.flagged = true,
+        }
+        if clock.flagged {
+            self.running = None;
+        }
+    }
+
+    // `state` has played, the overtime or the increment is settled and the
+    // opponent's clock starts
+    pub fn move_played(&mut self, state: i8) {
+        let clock = &mut self.clocks[GameClock::clock_idx(state)];
+        if clock.flagged {
+            return;
+        }
+        let in_overtime = clock.main_left == Duration::ZERO;
+        match self.time_control {
+            TimeControl::ByoYomi { period_time, .. } if in_overtime => clock.period_left = period_time,
+            TimeControl::Canadian { period_time, stones, .. } if in_overtime => {
+                clock.overtime_count -= 1;
+                if clock.overtime_count == 0 {
+                    clock.overtime_count = stones;
+                    clock.period_left = period_time;
+                }
+            },
+            TimeControl::Fischer { increment, .. } => clock.main_left += increment,
+            _ => {},
+        }
+        if self.running.is_some() {
+            self.start(-state);
+        }
+    }
+
+    // what `BL`/`WL` record: main time, or the period once it is used up
+    pub fn time_left(&self, state: i8) -> Duration {
+        let clock = &self.clocks[GameClock::clock_idx(state)];
+        if clock.main_left > Duration::ZERO {
+            clock.main_left
+        } else {
+            clock.period_left
+        }
+    }
+
+    // what `OB`/`OW` record: periods or stones left, only in overtime
+    pub fn overtime_left(&self, state: i8) -> Option<u32> {
+        let clock = &self.clocks[GameClock::clock_idx(state)];
+        match self.time_control {
+            TimeControl::ByoYomi { .. } | TimeControl::Canadian { .. } if clock.main_left == Duration::ZERO =>
+                Some(clock.overtime_count),
+            _ => None,
+        }
+    }
+
+    pub fn clock_text(&self, state: i8) -> String {
+        let secs = self.time_left(state).as_secs();
+        let time_text = format!("{:02}:{:02}", secs / 60, secs % 60);
+        match (self.time_control, self.overtime_left(state)) {
+            _ if self.clocks[GameClock::clock_idx(state)].flagged => format!("{} time out", time_text),
+            (TimeControl::ByoYomi { .. }, Some(periods)) => format!("{} ({})", time_text, periods),
+            (TimeControl::Canadian { .. }, Some(stones)) => format!("{} / {}", time_text, stones),
+            _ => time_text,
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let main_secs = self.main_time().as_secs();
+        match self.overtime() {
+            Some(overtime) => write!(f, "{}m + {}", main_secs / 60, overtime),
+            None => write!(f, "{}m", main_secs / 60),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{GameClock, TimeControl};
+
+    #[test]
+    pub fn test_parse_time_control() {
+        assert_eq!("byoyomi:600/5x30".parse(), Ok(TimeControl::ByoYomi {
+            main_time: Duration::from_secs(600), period_time: Duration::from_secs(30), periods: 5 }));
+        assert_eq!("canadian:600/25/300".parse::<TimeControl>().unwrap().overtime(), Some("25/300 Canadian".to_string()));
+        assert_eq!("fischer:300+10".parse::<TimeControl>().unwrap().overtime(), Some("10 fischer".to_string()));
+        assert!("byoyomi:600/0x30".parse::<TimeControl>().is_err());
+        assert!("hourglass:60".parse::<TimeControl>().is_err());
+    }
+
+    #[test]
+    pub fn test_byo_yomi() {
+        let mut game_clock = GameClock::new("byoyomi:10/3x5".parse().unwrap());
+        game_clock.start(1);
+        game_clock.elapse(Duration::from_secs(13));
+        assert_eq!(game_clock.time_left(1), Duration::from_secs(2));
+        assert_eq!(game_clock.overtime_left(1), Some(3));
+        game_clock.move_played(1);
+        assert_eq!(game_clock.running(), Some(-1));
+        assert_eq!(game_clock.time_left(1), Duration::from_secs(5));
+        game_clock.move_played(-1);
+        // 7s use up the first period, the next 10s the two others
+        game_clock.elapse(Duration::from_secs(7));
+        assert_eq!(game_clock.overtime_left(1), Some(2));
+        game_clock.elapse(Duration::from_secs(10));
+        assert_eq!(game_clock.flagged(), Some(1));
+        assert_eq!(game_clock.running(), None);
+    }
+
+    #[test]
+    pub fn test_canadian_and_fischer() {
+        let mut game_clock = GameClock::new("canadian:0/2/20".parse().unwrap());
+        game_clock.start(-1);
+        game_clock.elapse(Duration::from_secs(8));
+        game_clock.move_played(-1);
+        assert_eq!(game_clock.time_left(-1), Duration::from_secs(12));
+        assert_eq!(game_clock.overtime_left(-1), Some(1));
+        game_clock.move_played(1);
+        game_clock.elapse(Duration::from_secs(8));
+        game_clock.move_played(-1);
+        assert_eq!(game_clock.time_left(-1), Duration::from_secs(20));
+        assert_eq!(game_clock.overtime_left(-1), Some(2));
+
+        let mut game_clock = GameClock::new("fischer:60+10".parse().unwrap());
+        game_clock.start(1);
+        game_clock.elapse(Duration::from_secs(15));
+        game_clock.move_played(1);
+        assert_eq!(game_clock.time_left(1), Duration::from_secs(55));
+        game_clock.elapse(Duration::from_secs(61));
+        assert_eq!(game_clock.flagged(), Some(-1));
+    }
+}
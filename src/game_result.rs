@@ -0,0 +1,35 @@
+use std::fmt;
+
+// how a game played in the app ended, winners are given by stone state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Resign(i8),
+    Time(i8),
+    Draw,
+    NoResult,
+}
+
+impl GameResult {
+    // value of the `RE` property
+    pub fn to_sgf(&self) -> String {
+        let winner = |state: i8| if state == 1 { "B" } else { "W" };
+        match self {
+            GameResult::Resign(state) => format!("{}+R", winner(*state)),
+            GameResult::Time(state) => format!("{}+T", winner(*state)),
+            GameResult::Draw => "0".to_string(),
+            GameResult::NoResult => "Void".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let winner = |state: i8| if state == 1 { "Black" } else { "White" };
+        match self {
+            GameResult::Resign(state) => write!(f, "{} wins by resignation", winner(*state)),
+            GameResult::Time(state) => write!(f, "{} wins on time", winner(*state)),
+            GameResult::Draw => write!(f, "Draw"),
+            GameResult::NoResult => write!(f, "No result"),
+        }
+    }
+}
@@ -1,18 +1,20 @@
-use std::{vec, fs::{File, OpenOptions}, io::{BufReader, Read, Write}, rc::Rc, cell::RefCell, collections::{LinkedList, HashMap}, borrow::BorrowMut, cmp::Ordering};
+use std::{vec, fs::{File, OpenOptions}, io::{BufReader, Read, Write}, rc::Rc, cell::RefCell, collections::{LinkedList, HashMap}, borrow::BorrowMut, cmp::Ordering, str::FromStr};
 
 use chrono::{DateTime, Utc};
 use json::JsonValue;
 
-use crate::{go_move::GoMove, config::Config};
+use crate::{go_move::GoMove, config::{Config, MAX_GO_SZ, MIN_GO_SZ}, band_layout::BandLayout};
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Clone)]
 pub enum SgfToken {
     SEGSTART, SPLIT, SEGEND, VALSTART, VALEND,
     CA, FF, AP, GM, SZ, PB, PW, BR, WR, RE,
-    KM, HA, TM, DT, EV, RO, PC, RU, GN, ON, SO,
+    KM, HA, TM, OT, BL, WL, OB, OW, DT, EV, RO, PC, RU, GN, ON, SO,
     US, AN, CP, GC,
     AB, AW, AE, PL, B, W, C,
-    CR, MA, SQ, TR, LB, TB, TW, VAL(String),
+    CR, MA, SQ, TR, LB, TB, TW, DD, VAL(String),
+    // a property fastgo does not use, kept to be written back as it was
+    OTHER(String),
 }
 
 impl Default for SgfToken {
@@ -33,6 +35,138 @@ impl SgfToken {
             _ => true,
         }
     }
+
+    pub fn is_list_sgf_key(&self) -> bool {
+        match self {
+            SgfKey::AB | SgfKey::AW | SgfKey::AE
+            | SgfKey::CR | SgfKey::MA | SgfKey::SQ | SgfKey::TR
+            | SgfKey::LB | SgfKey::TB | SgfKey::TW | SgfKey::DD
+            | SgfKey::OTHER(_) => true,
+            _ => false,
+        }
+    }
+
+    // the property key as written in sgf
+    pub fn name(&self) -> &str {
+        match self {
+            SgfToken::CA => "CA",
+            SgfToken::FF => "FF",
+            SgfToken::AP => "AP",
+            SgfToken::GM => "GM",
+            SgfToken::SZ => "SZ",
+            SgfToken::PB => "PB",
+            SgfToken::PW => "PW",
+            SgfToken::BR => "BR",
+            SgfToken::WR => "WR",
+            SgfToken::RE => "RE",
+            SgfToken::KM => "KM",
+            SgfToken::HA => "HA",
+            SgfToken::TM => "TM",
+            SgfToken::OT => "OT",
+            SgfToken::BL => "BL",
+            SgfToken::WL => "WL",
+            SgfToken::OB => "OB",
+            SgfToken::OW => "OW",
+            SgfToken::DT => "DT",
+            SgfToken::EV => "EV",
+            SgfToken::RO => "RO",
+            SgfToken::PC => "PC",
+            SgfToken::RU => "RU",
+            SgfToken::GN => "GN",
+            SgfToken::ON => "ON",
+            SgfToken::SO => "SO",
+            SgfToken::US => "US",
+            SgfToken::AN => "AN",
+            SgfToken::CP => "CP",
+            SgfToken::GC => "GC",
+            SgfToken::AB => "AB",
+            SgfToken::AW => "AW",
+            SgfToken::AE => "AE",
+            SgfToken::PL => "PL",
+            SgfToken::B => "B",
+            SgfToken::W => "W",
+            SgfToken::C => "C",
+            SgfToken::CR => "CR",
+            SgfToken::MA => "MA",
+            SgfToken::SQ => "SQ",
+            SgfToken::TR => "TR",
+            SgfToken::LB => "LB",
+            SgfToken::TB => "TB",
+            SgfToken::TW => "TW",
+            SgfToken::DD => "DD",
+            SgfToken::OTHER(key) => key,
+            SgfToken::SPLIT => ";",
+            _ => "",
+        }
+    }
+
+    // a property fastgo knows, or one kept as `OTHER` when its key is made
+    // of capitals as sgf keys are
+    pub fn from_key(key: &str) -> Result<SgfToken, String> {
+        key.parse().or_else(|err| {
+            if !key.is_empty() && key.chars().all(|ch| ch.is_ascii_uppercase()) {
+                Ok(SgfToken::OTHER(key.to_string()))
+            } else {
+                Err(err)
+            }
+        })
+    }
+}
+
+// the property keys, `B` for `SgfToken::B` and so on
+impl FromStr for SgfToken {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "CA" => Ok(SgfToken::CA),
+            "FF" => Ok(SgfToken::FF),
+            "AP" => Ok(SgfToken::AP),
+            "GM" => Ok(SgfToken::GM),
+            "SZ" => Ok(SgfToken::SZ),
+            "PB" => Ok(SgfToken::PB),
+            "PW" => Ok(SgfToken::PW),
+            "BR" => Ok(SgfToken::BR),
+            "WR" => Ok(SgfToken::WR),
+            "RE" => Ok(SgfToken::RE),
+            "KM" => Ok(SgfToken::KM),
+            "HA" => Ok(SgfToken::HA),
+            "TM" => Ok(SgfToken::TM),
+            "OT" => Ok(SgfToken::OT),
+            "BL" => Ok(SgfToken::BL),
+            "WL" => Ok(SgfToken::WL),
+            "OB" => Ok(SgfToken::OB),
+            "OW" => Ok(SgfToken::OW),
+            "DT" => Ok(SgfToken::DT),
+            "EV" => Ok(SgfToken::EV),
+            "RO" => Ok(SgfToken::RO),
+            "PC" => Ok(SgfToken::PC),
+            "RU" => Ok(SgfToken::RU),
+            "GN" => Ok(SgfToken::GN),
+            "ON" => Ok(SgfToken::ON),
+            "SO" => Ok(SgfToken::SO),
+            "US" => Ok(SgfToken::US),
+            "AN" => Ok(SgfToken::AN),
+            "CP" => Ok(SgfToken::CP),
+            "GC" => Ok(SgfToken::GC),
+            "AB" => Ok(SgfToken::AB),
+            "AW" => Ok(SgfToken::AW),
+            "AE" => Ok(SgfToken::AE),
+            "PL" => Ok(SgfToken::PL),
+            "B" => Ok(SgfToken::B),
+            "W" => Ok(SgfToken::W),
+            "C" => Ok(SgfToken::C),
+            "CR" => Ok(SgfToken::CR),
+            "MA" => Ok(SgfToken::MA),
+            "SQ" => Ok(SgfToken::SQ),
+            "TR" => Ok(SgfToken::TR),
+            "LB" => Ok(SgfToken::LB),
+            "TB" => Ok(SgfToken::TB),
+            "TW" => Ok(SgfToken::TW),
+            "DD" => Ok(SgfToken::DD),
+            _ => Err(format!("not a property: {}", s)),
+        }
+    }
 }
 
 pub trait Parse<Token> {
@@ -54,7 +188,7 @@ impl ReadFile for SgfReader {
             return Err("this isn't a sgf file!".to_string());
         }
         let sfg_file = File::open(filename)
-            .map_err(|e| e.to_string()).unwrap();
+            .map_err(|e| e.to_string())?;
         let mut sgf_reader = BufReader::new(sfg_file);
         let mut sgf_buf: Vec<u8> = vec![];
         match sgf_reader.read_to_end(&mut sgf_buf) {
@@ -70,14 +204,30 @@ impl ReadFile for SgfReader {
     }
 }
 
+impl From<String> for SgfReader {
+    fn from(content: String) -> Self {
+        SgfReader { content }
+    }
+}
+
 impl Parse<SgfToken> for SgfReader {
     type Output = Vec<SgfToken>;
     fn parse(&self) -> Vec<SgfToken> {
         let mut sgf_tokens = vec![];
         let content_chs = self.content.chars().collect::<Vec<_>>();
         let mut i = 0;
+        // values of a key that is not a property are dropped with it, they
+        // would otherwise read as more values of the property before
+        let mut skip_values = false;
         while i < content_chs.len() {
             let cur_ch = content_chs[i];
+            if cur_ch.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            if !self.is_val_start_token(cur_ch) && !self.is_big_character_ch(cur_ch) {
+                skip_values = false;
+            }
             if self.is_seg_start_token(cur_ch) {
                 sgf_tokens.push(SgfToken::SEGSTART);
             } else if self.is_seg_end_token(cur_ch) {
@@ -85,18 +235,24 @@ impl Parse<SgfToken> for SgfReader {
             } else if self.is_seg_split_token(cur_ch) {
                 sgf_tokens.push(SgfToken::SPLIT);
             } else if self.is_val_start_token(cur_ch) {
-                sgf_tokens.push(SgfToken::VALSTART);
                 let mut token_chs = vec![];
                 let mut j = i + 1;
                 while j < content_chs.len() && !self.is_val_end_token(content_chs[j]) {
+                    // `\` escapes the next character, e.g. a `]` in a comment
+                    if content_chs[j] == '\\' && j + 1 < content_chs.len() {
+                        j += 1;
+                    }
                     token_chs.push(content_chs[j]);
                     j += 1;
                 }
-                let token_str = token_chs.iter().collect::<String>();
-                sgf_tokens.push(SgfToken::VAL(token_str));
-                sgf_tokens.push(SgfToken::VALEND);
+                if !skip_values {
+                    let token_str = token_chs.iter().collect::<String>();
+                    sgf_tokens.push(SgfToken::VALSTART);
+                    sgf_tokens.push(SgfToken::VAL(token_str));
+                    sgf_tokens.push(SgfToken::VALEND);
+                }
 
-                i += token_chs.len() + 2;
+                i = j + 1;
                 continue;
             } else {
                 let mut token_chs = vec![];
@@ -107,47 +263,12 @@ impl Parse<SgfToken> for SgfReader {
                     j += 1;
                 }
                 let token_str = token_chs.iter().collect::<String>();
-                match token_str.as_str() {
-                    "CA" => sgf_tokens.push(SgfToken::CA),
-                    "FF" => sgf_tokens.push(SgfToken::FF),
-                    "AP" => sgf_tokens.push(SgfToken::AP),
-                    "GM" => sgf_tokens.push(SgfToken::GM),
-                    "SZ" => sgf_tokens.push(SgfToken::SZ),
-                    "PB" => sgf_tokens.push(SgfToken::PB),
-                    "PW" => sgf_tokens.push(SgfToken::PW),
-                    "BR" => sgf_tokens.push(SgfToken::BR),
-                    "WR" => sgf_tokens.push(SgfToken::WR),
-                    "RE" => sgf_tokens.push(SgfToken::RE),
-                    "KM" => sgf_tokens.push(SgfToken::KM),
-                    "HA" => sgf_tokens.push(SgfToken::HA),
-                    "TM" => sgf_tokens.push(SgfToken::TM),
-                    "DT" => sgf_tokens.push(SgfToken::DT),
-                    "EV" => sgf_tokens.push(SgfToken::EV),
-                    "RO" => sgf_tokens.push(SgfToken::RO),
-                    "PC" => sgf_tokens.push(SgfToken::PC),
-                    "RU" => sgf_tokens.push(SgfToken::RU),
-                    "GN" => sgf_tokens.push(SgfToken::GN),
-                    "ON" => sgf_tokens.push(SgfToken::ON),
-                    "SO" => sgf_tokens.push(SgfToken::SO),
-                    "US" => sgf_tokens.push(SgfToken::US),
-                    "AN" => sgf_tokens.push(SgfToken::AN),
-                    "CP" => sgf_tokens.push(SgfToken::CP),
-                    "GC" => sgf_tokens.push(SgfToken::GC),
-                    "AB" => sgf_tokens.push(SgfToken::AB),
-                    "AW" => sgf_tokens.push(SgfToken::AW),
-                    "AE" => sgf_tokens.push(SgfToken::AE),
-                    "PL" => sgf_tokens.push(SgfToken::PL),
-                    "B" => sgf_tokens.push(SgfToken::B),
-                    "W" => sgf_tokens.push(SgfToken::W),
-                    "C" => sgf_tokens.push(SgfToken::C),
-                    "CR" => sgf_tokens.push(SgfToken::CR),
-                    "MA" => sgf_tokens.push(SgfToken::MA),
-                    "SQ" => sgf_tokens.push(SgfToken::SQ),
-                    "TR" => sgf_tokens.push(SgfToken::TR),
-                    "LB" => sgf_tokens.push(SgfToken::LB),
-                    "TB" => sgf_tokens.push(SgfToken::TB),
-                    "TW" => sgf_tokens.push(SgfToken::TW),
-                    _ => {},
+                match SgfToken::from_key(&token_str) {
+                    Ok(sgf_token) => {
+                        sgf_tokens.push(sgf_token);
+                        skip_values = false;
+                    },
+                    Err(_) => skip_values = true,
                 }
                 i += token_chs.len();
                 continue;
@@ -186,6 +307,21 @@ impl SgfReader {
 
 pub type SgfKey = SgfToken;
 
+// version of the json written by `GameTree::to_json`, a game tree is
+//
+//     {
+//         "version": 1,             // the root only, 1 when missing
+//         "selected": true,         // followed by default, true when missing
+//         "nodes": [{"B": "pd"}],   // one property each, in sgf order
+//         "sub_trees": [...]        // variations, exactly one selected
+//     }
+//
+// property keys are the sgf ones fastgo knows and values are sgf values
+// without escapes, a move starts a new node as in `(;B[pd]C[...];W[dd])`;
+// since version 2 `{";": ""}` starts a node without a move, so the `AB`
+// node of `(;SZ[9];AB[aa];B[cc])` stays apart from the root
+pub const JSON_VERSION: u32 = 2;
+
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq)]
 pub struct GameTree {
     is_root: bool,
@@ -221,7 +357,8 @@ impl From<Config> for GameTree {
             config.go_km(),
             config.go_sz(),
             config.go_pb(),
-            config.go_pw()
+            config.go_pw(),
+            config.go_ha()
         )
     }
 }
@@ -231,7 +368,8 @@ impl GameTree {
         km: f32,
         sz: i32,
         pb: String,
-        pw: String) -> Self {
+        pw: String,
+        ha: usize) -> Self {
         let utc: DateTime<Utc> = Utc::now();
         let game_tree = GameTree {
             is_root: true,
@@ -252,12 +390,21 @@ impl GameTree {
         nodes_ref.push(km_node);
         let sz_node = SgfNode::new(SgfKey::SZ, sz.to_string());
         nodes_ref.push(sz_node);
-        let dt_node = SgfNode::new(SgfKey::DT, format!("{}", utc.format("%Y-%m-%d %H:%M:%S")));
+        let dt_node = SgfNode::new(SgfKey::DT, format!("{}", utc.format("%Y-%m-%d")));
         nodes_ref.push(dt_node);
         let pb_node = SgfNode::new(SgfKey::PB, pb);
         nodes_ref.push(pb_node);
         let pw_node = SgfNode::new(SgfKey::PW, pw);
         nodes_ref.push(pw_node);
+        // handicap stones are set up in the root, white moves first
+        let handicap_points = BandLayout::handicap_points(sz as usize, ha);
+        if !handicap_points.is_empty() {
+            nodes_ref.push(SgfNode::new(SgfKey::HA, ha.to_string()));
+            for (x, y) in handicap_points {
+                nodes_ref.push(SgfNode::new(SgfKey::AB, SgfNode::format_point(x, y)));
+            }
+            nodes_ref.push(SgfNode::new(SgfKey::PL, "W".to_string()));
+        }
         game_tree.nodes.as_ref().unwrap().borrow_mut().replace(nodes_ref);
         game_tree
     }
@@ -268,7 +415,7 @@ impl GameTree {
         let mut game_tree = GameTree {
             is_root,
             selected,
-            nodes: None,
+            nodes: Some(Rc::new(RefCell::new(vec![]))),
             sub_game_trees: None,
         };
 
@@ -302,27 +449,22 @@ impl GameTree {
             } else {
                 if sgf_tokens[i] == SgfKey::SPLIT
                     && sgf_tokens[i + 1].is_prop_sgf_key() {
-                    let sgf_node = game_tree.parse_sgf_node(sgf_tokens, i + 1);
-                    if let Some(sgf_node) = sgf_node {
-                        if let None = game_tree.nodes {
-                            game_tree.nodes = Some(Rc::new(RefCell::new(vec![])));
-                        }
-                        let mut nodes_ref = game_tree.nodes.as_ref().unwrap().borrow_mut().take();
-                        nodes_ref.push(sgf_node);
-                        game_tree.nodes.as_ref().unwrap().borrow_mut().replace(nodes_ref);
+                    // a move starts its own node, any other node is marked
+                    // by a `SPLIT` unless it is the root node
+                    let is_move = sgf_tokens[i + 1] == SgfKey::B || sgf_tokens[i + 1] == SgfKey::W;
+                    let is_root_node = is_root && game_tree.nodes.as_ref().unwrap().borrow().is_empty();
+                    if !is_move && !is_root_node {
+                        game_tree.push_sgf_nodes(vec![SgfNode::new(SgfKey::SPLIT, String::default())]);
                     }
-                    i += 4;
+                    let sgf_nodes = game_tree.parse_sgf_nodes(sgf_tokens, i + 1);
+                    let val_count = sgf_nodes.len().max(1);
+                    game_tree.push_sgf_nodes(sgf_nodes);
+                    i += 1 + 3 * val_count;
                 } else if sgf_tokens[i].is_prop_sgf_key() {
-                    let sgf_node = game_tree.parse_sgf_node(sgf_tokens, i);
-                    if let Some(sgf_node) = sgf_node {
-                        if let None = game_tree.nodes {
-                            game_tree.nodes = Some(Rc::new(RefCell::new(vec![])));
-                        }
-                        let mut nodes_ref = game_tree.nodes.as_ref().unwrap().borrow_mut().take();
-                        nodes_ref.push(sgf_node);
-                        game_tree.nodes.as_ref().unwrap().borrow_mut().replace(nodes_ref);
-                    }
-                    i += 3;
+                    let sgf_nodes = game_tree.parse_sgf_nodes(sgf_tokens, i);
+                    let val_count = sgf_nodes.len().max(1);
+                    game_tree.push_sgf_nodes(sgf_nodes);
+                    i += 3 * val_count;
                 }
             }
             i += 1;
@@ -336,29 +478,18 @@ impl GameTree {
         }
         let nodes = game_tree.nodes.as_ref().unwrap().borrow_mut().take();
         let mut move_count = 0;
-        let mut skip_count = 0;
         let mut node_map: HashMap<i32, SgfNode> = HashMap::new();
         for node in nodes.clone() {
-            match node.node_key {
-                SgfKey::W | SgfKey::B => {
-                    node_map.insert(move_count, node.clone());
-                    move_count += 1;
-                },
-                _ => skip_count += 1,
+            if node.is_move() {
+                node_map.insert(move_count, node.clone());
+                move_count += 1;
             }
         }
-        println!("move_count={}, skip_count={}", move_count, skip_count);
         game_tree.nodes.as_ref().unwrap().borrow_mut().replace(nodes.clone());
         if move_id <= move_count {
             if move_id < move_count {
                 let sgf_node = node_map.get(&move_id).unwrap();
-                let poses = sgf_node.node_val.chars().map(|c| SgfNode::convert_mark_to_pos(c)).collect::<Vec<_>>();
-                let cur_state = match sgf_node.node_key {
-                    SgfKey::B => 1,
-                    SgfKey::W => -1,
-                    _ => 0,
-                };
-                return Some(vec![(GoMove::new(move_id as usize, poses[0], poses[1], cur_state), true)]);
+                return Some(vec![(sgf_node.to_go_move(move_id as usize), true)]);
             } else {
                 let mut go_moves: Vec<(GoMove, bool)> = vec![];
                 match game_tree.sub_game_trees.as_ref() {
@@ -371,13 +502,7 @@ impl GameTree {
                                     // println!("sub_nodes={:?}", sub_nodes);
                                     if sub_nodes.len() > 0 {
                                         let sgf_node = sub_nodes.get((0) as usize).unwrap();
-                                        let poses = sgf_node.node_val.chars().map(|c| SgfNode::convert_mark_to_pos(c)).collect::<Vec<_>>();
-                                        let cur_state = match sgf_node.node_key {
-                                            SgfKey::B => 1,
-                                            SgfKey::W => -1,
-                                            _ => 0,
-                                        };
-                                        go_moves.push((GoMove::new(move_id as usize, poses[0], poses[1], cur_state), sub_game_tree.selected));
+                                        go_moves.push((sgf_node.to_go_move(move_id as usize), sub_game_tree.selected));
                                     }
                                     sub_nodes_ref.borrow_mut().replace(sub_nodes);
                                 },
@@ -394,16 +519,13 @@ impl GameTree {
             match game_tree.sub_game_trees.as_ref() {
                 Some(mut sub_game_trees_ref) => {
                     let sub_game_trees = sub_game_trees_ref.borrow_mut().take();
-                    let mut i = 0;
-                    for sub_game_tree in sub_game_trees.clone() {
-                        if !sub_game_tree.selected {
-                            i += 1;
-                        } else {
-                            break;
-                        }
-                    }
-                    let sub_game_tree = sub_game_trees[i].clone();
-                    let next_moves = GameTree::get_moves(&sub_game_tree, move_id - move_count);
+                    let i = sub_game_trees.iter()
+                        .position(|sub_game_tree| sub_game_tree.selected)
+                        .unwrap_or(0);
+                    let next_moves = match sub_game_trees.get(i) {
+                        Some(sub_game_tree) => GameTree::get_moves(sub_game_tree, move_id - move_count),
+                        None => None,
+                    };
                     sub_game_trees_ref.borrow_mut().replace(sub_game_trees);
                     return next_moves;
                 },
@@ -413,6 +535,226 @@ impl GameTree {
         None
     }
 
+    // nodes along the selected variation, from the root down to the last node
+    pub fn get_path_nodes(game_tree: &GameTree) -> Vec<SgfNode> {
+        let mut path_nodes = match game_tree.nodes.as_ref() {
+            Some(nodes_ref) => nodes_ref.borrow().clone(),
+            None => vec![],
+        };
+        if let Some(sub_game_trees_ref) = game_tree.sub_game_trees.as_ref() {
+            let sub_game_trees = sub_game_trees_ref.borrow();
+            let selected = sub_game_trees.iter()
+                .find(|sub_game_tree| sub_game_tree.selected)
+                .or(sub_game_trees.first());
+            if let Some(sub_game_tree) = selected {
+                path_nodes.extend(GameTree::get_path_nodes(sub_game_tree));
+            }
+        }
+        path_nodes
+    }
+
+    // the `SZ` of the root node, 19 when it is missing
+    pub fn board_size(game_tree: &GameTree) -> usize {
+        GameTree::get_node_props(game_tree, -1)
+            .iter()
+            .find(|sgf_node| sgf_node.node_key() == SgfKey::SZ)
+            .and_then(|sgf_node| sgf_node.node_val().parse::<usize>().ok())
+            .unwrap_or(19)
+    }
+
+    // `board_size` when the band can show it, the record is not drawn or
+    // played otherwise
+    pub fn supported_board_size(game_tree: &GameTree) -> Result<usize, String> {
+        let sz = GameTree::get_node_props(game_tree, -1)
+            .into_iter()
+            .find(|sgf_node| sgf_node.node_key() == SgfKey::SZ)
+            .map(|sgf_node| sgf_node.node_val());
+        match sz {
+            None => Ok(GameTree::board_size(game_tree)),
+            Some(sz) => sz.trim().parse::<i32>()
+                .ok()
+                .filter(|dim| (MIN_GO_SZ..=MAX_GO_SZ).contains(dim))
+                .map(|dim| dim as usize)
+                .ok_or(format!("SZ[{}] is not supported, square sizes from {} to {} are", sz, MIN_GO_SZ, MAX_GO_SZ)),
+        }
+    }
+
+    // nodes from the root down to the end of every variation, selected or
+    // not, in sgf order
+    pub fn variation_paths(game_tree: &GameTree) -> Vec<Vec<SgfNode>> {
+        let nodes = match game_tree.nodes.as_ref() {
+            Some(nodes_ref) => nodes_ref.borrow().clone(),
+            None => vec![],
+        };
+        let sub_game_trees = match game_tree.sub_game_trees.as_ref() {
+            Some(sub_game_trees_ref) => sub_game_trees_ref.borrow().clone(),
+            None => vec![],
+        };
+        if sub_game_trees.is_empty() {
+            return vec![nodes];
+        }
+        sub_game_trees.iter()
+            .flat_map(GameTree::variation_paths)
+            .map(|sub_path| nodes.iter().cloned().chain(sub_path).collect())
+            .collect()
+    }
+
+    // index of the first move of the innermost side variation on the
+    // selected path, 0 while following the main line
+    pub fn get_variation_start(game_tree: &GameTree) -> usize {
+        let mut variation_start = 0;
+        let mut move_offset = 0;
+        let mut cur_game_tree = game_tree.clone();
+        loop {
+            if let Some(nodes_ref) = cur_game_tree.nodes.as_ref() {
+                move_offset += nodes_ref.borrow().iter().filter(|node| node.is_move()).count();
+            }
+            let sub_game_trees = match cur_game_tree.sub_game_trees.as_ref() {
+                Some(sub_game_trees_ref) => sub_game_trees_ref.borrow().clone(),
+                None => break,
+            };
+            if sub_game_trees.len() == 0 {
+                break;
+            }
+            let selected_idx = sub_game_trees.iter()
+                .position(|sub_game_tree| sub_game_tree.selected)
+                .unwrap_or(0);
+            if selected_idx > 0 {
+                variation_start = move_offset;
+            }
+            cur_game_tree = sub_game_trees[selected_idx].clone();
+        }
+        variation_start
+    }
+
+    // nodes along the selected variation up to the end of the node holding
+    // move `move_id`, a negative `move_id` stops right before the first move
+    pub fn get_path_nodes_until(game_tree: &GameTree, move_id: i32) -> Vec<SgfNode> {
+        let mut path_nodes = vec![];
+        let mut move_count = 0;
+        for node in GameTree::get_path_nodes(game_tree) {
+            if node.is_move() {
+                if move_count > move_id {
+                    break;
+                }
+                move_count += 1;
+            }
+            path_nodes.push(node);
+        }
+        path_nodes
+    }
+
+    // properties attached to the node of move `move_id` (the move itself
+    // excluded), or the root properties when `move_id` is negative
+    pub fn get_node_props(game_tree: &GameTree, move_id: i32) -> Vec<SgfNode> {
+        let path_nodes = GameTree::get_path_nodes_until(game_tree, move_id);
+        let node_start = path_nodes.iter()
+            .rposition(|node| node.is_move())
+            .map_or(0, |idx| idx + 1);
+        let node_end = GameTree::node_end(&path_nodes, node_start, move_id);
+        Vec::from(&path_nodes[node_start..node_end])
+    }
+
+    // the end of the properties from `start`: the next move, or for the
+    // root the next node as well, nodes without a move after a move are
+    // shown and edited along with it
+    fn node_end(nodes: &[SgfNode], start: usize, move_id: i32) -> usize {
+        nodes[start..].iter()
+            .position(|node| node.is_move() || (move_id < 0 && node.node_key == SgfKey::SPLIT))
+            .map_or(nodes.len(), |idx| start + idx)
+    }
+
+    // the node list holding the node of move `move_id` on the selected
+    // variation, along with the range of that node's properties
+    fn locate_node(game_tree: &GameTree, move_id: i32) -> Option<(Rc<RefCell<Vec<SgfNode>>>, usize, usize)> {
+        let nodes_ref = game_tree.nodes.as_ref()?;
+        let nodes = nodes_ref.borrow().clone();
+        let move_idxes = nodes.iter().enumerate()
+            .filter(|(_, node)| node.is_move())
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        if move_id < move_idxes.len() as i32 {
+            let start = if move_id < 0 { 0 } else { move_idxes[move_id as usize] + 1 };
+            let end = GameTree::node_end(&nodes, start, move_id);
+            return Some((nodes_ref.clone(), start, end));
+        }
+        let sub_game_trees = game_tree.sub_game_trees.as_ref()?.borrow().clone();
+        let sub_game_tree = sub_game_trees.iter()
+            .find(|sub_game_tree| sub_game_tree.selected)
+            .or(sub_game_trees.first())?;
+        GameTree::locate_node(sub_game_tree, move_id - move_idxes.len() as i32)
+    }
+
+    pub fn add_node_prop(game_tree: &mut GameTree, move_id: i32, sgf_node: SgfNode) -> bool {
+        match GameTree::locate_node(game_tree, move_id) {
+            Some((nodes_ref, start, end)) => {
+                let mut nodes = nodes_ref.take();
+                // keep the values of one property together, so they are written as `KEY[a][b]`
+                let insert_idx = nodes[start..end].iter()
+                    .rposition(|node| node.node_key == sgf_node.node_key)
+                    .map_or(end, |idx| start + idx + 1);
+                nodes.insert(insert_idx, sgf_node);
+                nodes_ref.replace(nodes);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // like `add_node_prop`, but replaces the values the property had before
+    pub fn set_node_prop(game_tree: &mut GameTree, move_id: i32, sgf_node: SgfNode) -> bool {
+        match GameTree::locate_node(game_tree, move_id) {
+            Some((nodes_ref, start, end)) => {
+                let mut nodes = nodes_ref.take();
+                let insert_idx = nodes[start..end].iter()
+                    .position(|node| node.node_key == sgf_node.node_key)
+                    .map_or(end, |idx| start + idx);
+                let mut idx = end;
+                while idx > start {
+                    idx -= 1;
+                    if nodes[idx].node_key == sgf_node.node_key {
+                        nodes.remove(idx);
+                    }
+                }
+                nodes.insert(insert_idx, sgf_node);
+                nodes_ref.replace(nodes);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // removes `pos` from the point list properties `keys` of the node of move
+    // `move_id`, returns the keys the point was removed from
+    pub fn remove_node_point(game_tree: &mut GameTree, move_id: i32, keys: &[SgfKey], pos: (usize, usize)) -> Vec<SgfKey> {
+        let mut removed_keys = vec![];
+        if let Some((nodes_ref, start, end)) = GameTree::locate_node(game_tree, move_id) {
+            let nodes = nodes_ref.take();
+            let mut new_nodes = Vec::from(&nodes[..start]);
+            for node in &nodes[start..end] {
+                let point_val = match node.node_key {
+                    SgfKey::LB => node.node_val.split(':').next().unwrap_or_default(),
+                    _ => node.node_val.as_str(),
+                };
+                let points = SgfNode::parse_points(point_val).unwrap_or_default();
+                if !keys.contains(&node.node_key) || !points.contains(&pos) {
+                    new_nodes.push(node.clone());
+                    continue;
+                }
+                removed_keys.push(node.node_key.clone());
+                if node.node_key != SgfKey::LB {
+                    // a compressed rectangle is split up into the remaining points
+                    for (x, y) in points.into_iter().filter(|point| *point != pos) {
+                        new_nodes.push(SgfNode::new(node.node_key.clone(), SgfNode::format_point(x, y)));
+                    }
+                }
+            }
+            new_nodes.extend_from_slice(&nodes[end..]);
+            nodes_ref.replace(new_nodes);
+        }
+        removed_keys
+    }
+
     pub fn record_move<'a>(game_tree: &'a mut GameTree, move_id: i32, go_move: GoMove) -> Option<&'a GameTree> {
         if move_id < 0 {
             return None;
@@ -531,37 +873,87 @@ impl GameTree {
             Some(game_tree)
         }
     }
+
+    // selects a sibling of the innermost variation branching off at or
+    // before the move following `move_id`, `step` siblings further on and
+    // wrapping around, false when there is none
+    pub fn select_variation(game_tree: &GameTree, move_id: i32, step: i32) -> bool {
+        let move_count = match game_tree.nodes.as_ref() {
+            Some(nodes_ref) => nodes_ref.borrow().iter().filter(|node| node.is_move()).count() as i32,
+            None => 0,
+        };
+        let sub_game_trees_ref = match game_tree.sub_game_trees.as_ref() {
+            Some(sub_game_trees_ref) => sub_game_trees_ref,
+            None => return false,
+        };
+        if move_id < move_count - 1 {
+            return false;
+        }
+        let mut sub_game_trees = sub_game_trees_ref.take();
+        if sub_game_trees.len() == 0 {
+            sub_game_trees_ref.replace(sub_game_trees);
+            return false;
+        }
+        let selected_idx = sub_game_trees.iter()
+            .position(|sub_game_tree| sub_game_tree.selected)
+            .unwrap_or(0);
+        let mut switched = GameTree::select_variation(&sub_game_trees[selected_idx], move_id - move_count, step);
+        if !switched && sub_game_trees.len() > 1 {
+            let next_idx = (selected_idx as i32 + step).rem_euclid(sub_game_trees.len() as i32) as usize;
+            for (idx, sub_game_tree) in sub_game_trees.iter_mut().enumerate() {
+                sub_game_tree.selected = idx == next_idx;
+            }
+            switched = true;
+        }
+        sub_game_trees_ref.replace(sub_game_trees);
+        switched
+    }
 }
 
 impl GameTree {
-    fn parse_sgf_node(&mut self, sgf_tokens: &Vec<SgfToken>, idx: usize) -> Option<SgfNode> {
-        let mut sgf_node = SgfNode::default();
-        if sgf_tokens[idx + 1] == SgfKey::VALSTART
-            && sgf_tokens[idx + 3] == SgfKey::VALEND {
-            match &sgf_tokens[idx + 2] {
-                SgfToken::VAL(val) => {
-                    sgf_node.node_key = sgf_tokens[idx].clone();
-                    sgf_node.node_val = val.clone();
-                    return Some(sgf_node);
-                },
-                _ => {},
+    // a property may carry several values (e.g. `TR[aa][bb]`), each of them
+    // becomes its own node with the same key
+    fn parse_sgf_nodes(&mut self, sgf_tokens: &Vec<SgfToken>, idx: usize) -> Vec<SgfNode> {
+        let mut sgf_nodes = vec![];
+        let mut val_idx = idx + 1;
+        while val_idx + 2 < sgf_tokens.len()
+            && sgf_tokens[val_idx] == SgfKey::VALSTART
+            && sgf_tokens[val_idx + 2] == SgfKey::VALEND {
+            if let SgfToken::VAL(val) = &sgf_tokens[val_idx + 1] {
+                sgf_nodes.push(SgfNode::new(sgf_tokens[idx].clone(), val.clone()));
             }
+            val_idx += 3;
         }
-        None
+        sgf_nodes
+    }
+
+    fn push_sgf_nodes(&mut self, sgf_nodes: Vec<SgfNode>) {
+        if sgf_nodes.len() == 0 {
+            return;
+        }
+        if let None = self.nodes {
+            self.nodes = Some(Rc::new(RefCell::new(vec![])));
+        }
+        let mut nodes_ref = self.nodes.as_ref().unwrap().borrow_mut().take();
+        nodes_ref.extend(sgf_nodes);
+        self.nodes.as_ref().unwrap().borrow_mut().replace(nodes_ref);
     }
 
+    // see `JSON_VERSION` for the schema
     pub fn to_json(&self) -> Option<JsonValue> {
         let mut root = json::JsonValue::new_object();
         let mut nodes = json::JsonValue::new_array();
         let mut sub_game_trees_json = json::JsonValue::new_array();
-        let sgf_nodes = self.nodes.as_ref().unwrap().borrow_mut().take();
-        for sgf_node in sgf_nodes.clone() {
-            let node_key = format!("{:?}", sgf_node.node_key);
+        let sgf_nodes = self.nodes.as_ref().map_or(vec![], |nodes_ref| nodes_ref.borrow().clone());
+        for sgf_node in sgf_nodes {
+            let node_key = sgf_node.node_key.name();
             let mut node = json::JsonValue::new_object();
             node[node_key] = sgf_node.node_val.into();
             nodes.push(node).expect("push node failed");
         }
-        self.nodes.as_ref().unwrap().borrow_mut().replace(sgf_nodes);
+        if self.is_root {
+            root["version"] = JSON_VERSION.into();
+        }
         root["selected"] = json::JsonValue::Boolean(self.selected);
         root["nodes"] = nodes;
         match self.sub_game_trees.as_ref() {
@@ -579,17 +971,130 @@ impl GameTree {
         Some(root)
     }
 
+    // reads what `to_json` writes, see `JSON_VERSION` for the schema; errors
+    // name the offending place, e.g. `sub_trees[1].nodes[0]`
+    pub fn from_json(json: &JsonValue) -> Result<GameTree, String> {
+        if !json.is_object() {
+            return Err("a game tree must be an object".to_string());
+        }
+        let version = match &json["version"] {
+            JsonValue::Null => 1,
+            version => version.as_u32().ok_or("version must be a number".to_string())?,
+        };
+        if version == 0 || version > JSON_VERSION {
+            return Err(format!("unsupported version {}, up to {} can be read", version, JSON_VERSION));
+        }
+        GameTree::from_json_tree(json, "", true)
+    }
+
+    fn from_json_tree(json: &JsonValue, path: &str, is_root: bool) -> Result<GameTree, String> {
+        let at = |field: &str| if path.is_empty() { field.to_string() } else { format!("{}.{}", path, field) };
+        if !json.is_object() {
+            return Err(format!("{}: a game tree must be an object", if path.is_empty() { "root" } else { path }));
+        }
+        for (field, _) in json.entries() {
+            match field {
+                "selected" | "nodes" | "sub_trees" => {},
+                "version" if is_root => {},
+                _ => return Err(format!("{}: unknown field", at(field))),
+            }
+        }
+        let selected = match &json["selected"] {
+            JsonValue::Null => is_root,
+            selected => selected.as_bool().ok_or(format!("{}: must be true or false", at("selected")))?,
+        };
+        let mut nodes = vec![];
+        match &json["nodes"] {
+            JsonValue::Null => {},
+            JsonValue::Array(json_nodes) => for (idx, node) in json_nodes.iter().enumerate() {
+                let node_at = at(&format!("nodes[{}]", idx));
+                let (node_key, node_val) = node.entries()
+                    .next()
+                    .filter(|_| node.is_object() && node.len() == 1)
+                    .ok_or(format!("{}: a node must be an object with one property", node_at))?;
+                let node_key = match node_key {
+                    ";" => SgfKey::SPLIT,
+                    node_key => SgfToken::from_key(node_key).map_err(|err| format!("{}: {}", node_at, err))?,
+                };
+                let node_val = node_val.as_str().ok_or(format!("{}: the value must be a string", node_at))?;
+                nodes.push(SgfNode::new(node_key, node_val.to_string()));
+            },
+            _ => return Err(format!("{}: must be an array", at("nodes"))),
+        }
+        let sub_game_trees = match &json["sub_trees"] {
+            JsonValue::Null => vec![],
+            JsonValue::Array(sub_trees) => sub_trees.iter()
+                .enumerate()
+                .map(|(idx, sub_tree)| GameTree::from_json_tree(sub_tree, &at(&format!("sub_trees[{}]", idx)), false))
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(format!("{}: must be an array", at("sub_trees"))),
+        };
+        // the variation followed from here
+        let selected_count = sub_game_trees.iter().filter(|sub_game_tree| sub_game_tree.selected).count();
+        if !sub_game_trees.is_empty() && selected_count != 1 {
+            return Err(format!("{}: exactly one sub tree must be selected, not {}", at("sub_trees"), selected_count));
+        }
+        Ok(GameTree {
+            is_root,
+            selected,
+            nodes: Some(Rc::new(RefCell::new(nodes))),
+            sub_game_trees: if sub_game_trees.is_empty() { None } else { Some(Rc::new(RefCell::new(sub_game_trees))) },
+        })
+    }
+
+    // the first game tree of `content`, which must be balanced and have a
+    // node at the start of each tree
+    pub fn from_sgf(content: &str) -> Result<GameTree, String> {
+        let sgf_tokens = SgfReader::from(content.to_string()).parse();
+        if sgf_tokens.first() != Some(&SgfKey::SEGSTART) {
+            return Err("no game tree found".to_string());
+        }
+        let mut depth = 0;
+        for (idx, sgf_token) in sgf_tokens.iter().enumerate() {
+            match sgf_token {
+                SgfKey::SEGSTART if sgf_tokens.get(idx + 1) != Some(&SgfKey::SPLIT) =>
+                    return Err("a game tree must start with a node".to_string()),
+                SgfKey::SEGSTART => depth += 1,
+                SgfKey::SEGEND => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return GameTree::from_sgf_tokens(&sgf_tokens, 0, idx, true, true)
+                            .ok_or("cannot read the game tree".to_string());
+                    }
+                },
+                _ => {},
+            }
+        }
+        Err("the game tree is not closed".to_string())
+    }
+
+    pub fn to_sgf(&self) -> String {
+        self._to_string().unwrap_or_default()
+    }
+
     fn _to_string(&self) -> Option<String> {
         let mut sgf_str = if self.is_root { String::from("(;") } else { String::from("(") };
-        let nodes = self.nodes.as_ref().unwrap().borrow_mut().take();
-        for node in nodes.clone() {
+        let nodes = self.nodes.as_ref().map_or(vec![], |nodes_ref| nodes_ref.borrow().clone());
+        let mut last_key: Option<SgfKey> = None;
+        for node in nodes {
             if node.node_key == SgfKey::B
                 || node.node_key == SgfKey::W {
                 sgf_str += &";".to_string().clone();
             }
-            sgf_str += &SgfNode::_to_string(node).clone();
+            // the start of a node without a move
+            if node.node_key == SgfKey::SPLIT {
+                sgf_str += ";";
+                last_key = None;
+                continue;
+            }
+            let node_key = node.node_key.clone();
+            if last_key.as_ref() == Some(&node_key) && node_key.is_list_sgf_key() {
+                sgf_str += &format!("[{}]", SgfNode::escape_val(&node.node_val));
+            } else {
+                sgf_str += &SgfNode::_to_string(node).clone();
+            }
+            last_key = Some(node_key);
         }
-        self.nodes.as_ref().unwrap().borrow_mut().replace(nodes);
         match self.sub_game_trees.as_ref() {
             Some(mut sub_game_trees_ref) => {
                 let sub_game_trees = sub_game_trees_ref.borrow_mut().take();
@@ -605,29 +1110,15 @@ impl GameTree {
         Some(sgf_str)
     }
 
-    fn _save_sgf(&self, filename: &str) -> std::io::Result<()> {
-        let sgf_str = self._to_string();
-        match sgf_str {
-            Some(str) => {
-                let mut file = match OpenOptions::new()  
-                    .write(true)  
-                    .create(true)  
-                    .truncate(true)  
-                    .open(filename) {  
-                        Ok(file) => file,  
-                        Err(why) => {  
-                            println!(" couldn't open file: {}", why);  
-                            return Ok(());  
-                        }  
-                    }; 
-                file.write_all(str.as_bytes())  
-                    .expect("failed to write to file");  
-                file.flush()  
-                    .expect("failed to flush file");
-            },
-            None => {},
-        }
-        Ok(())
+    pub fn save_sgf(&self, filename: &str) -> std::io::Result<()> {
+        let sgf_str = self.to_sgf();
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(filename)?;
+        file.write_all(sgf_str.as_bytes())?;
+        file.flush()
     }
 }
 
@@ -638,9 +1129,63 @@ pub struct SgfNode {
 }
 
 impl SgfNode {
-    fn new(key: SgfKey, val: String) -> Self {
+    pub fn new(key: SgfKey, val: String) -> Self {
         SgfNode { node_key: key, node_val: val }
     }
+
+    pub fn node_key(&self) -> SgfKey {
+        self.node_key.clone()
+    }
+
+    pub fn node_val(&self) -> String {
+        self.node_val.clone()
+    }
+
+    pub fn format_point(pos_x: usize, pos_y: usize) -> String {
+        format!("{}{}", SgfNode::convert_pos_to_mark(pos_x), SgfNode::convert_pos_to_mark(pos_y))
+    }
+
+    pub fn is_move(&self) -> bool {
+        self.node_key == SgfKey::B || self.node_key == SgfKey::W
+    }
+
+    // `B[]` and, on boards up to 19x19, `B[tt]` are passes
+    pub fn to_go_move(&self, move_id: usize) -> GoMove {
+        let cur_state = match self.node_key {
+            SgfKey::B => 1,
+            SgfKey::W => -1,
+            _ => 0,
+        };
+        match SgfNode::parse_points(&self.node_val).unwrap_or_default().first() {
+            Some((x, y)) if self.node_val != "tt" => GoMove::new(move_id, *x, *y, cur_state),
+            _ => GoMove::new_pass(move_id, cur_state),
+        }
+    }
+
+    // a point list value is either a single point `aa` or a compressed
+    // rectangle `aa:cc` spanning two opposite corners, `None` for anything
+    // else, including points past `ss`
+    pub fn parse_points(val: &str) -> Option<Vec<(usize, usize)>> {
+        let corners = val.split(':')
+            .map(|point| match point.chars().collect::<Vec<_>>()[..] {
+                [x, y] => Some((SgfNode::convert_mark_to_pos(x)?, SgfNode::convert_mark_to_pos(y)?)),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        match corners[..] {
+            [_] => Some(corners),
+            [(x1, y1), (x2, y2)] => {
+                let mut points = vec![];
+                for x in x1.min(x2)..=x1.max(x2) {
+                    for y in y1.min(y2)..=y1.max(y2) {
+                        points.push((x, y));
+                    }
+                }
+                Some(points)
+            },
+            _ => None,
+        }
+    }
 }
 
 impl Default for SgfNode {
@@ -659,7 +1204,11 @@ impl From<GoMove> for SgfNode {
         } else if player == -1 {
             sgf_node.node_key = SgfKey::W;
         }
-        sgf_node.node_val = format!("{}{}", SgfNode::convert_pos_to_mark(x), SgfNode::convert_pos_to_mark(y));
+        sgf_node.node_val = if go_move.is_pass() {
+            String::default()
+        } else {
+            SgfNode::format_point(x, y)
+        };
         // println!("{:?}", sgf_node);
         sgf_node
     }
@@ -691,80 +1240,43 @@ impl SgfNode {
         }
     }
 
-    fn convert_mark_to_pos(mark: char) -> usize {
+    fn convert_mark_to_pos(mark: char) -> Option<usize> {
         match mark {
-            'a' => 0,
-            'b' => 1,
-            'c' => 2,
-            'd' => 3,
-            'e' => 4,
-            'f' => 5,
-            'g' => 6,
-            'h' => 7,
-            'i' => 8,
-            'j' => 9,
-            'k' => 10,
-            'l' => 11,
-            'm' => 12,
-            'n' => 13,
-            'o' => 14,
-            'p' => 15,
-            'q' => 16,
-            'r' => 17,
-            's' => 18,
-            _ => 0,
+            'a' => Some(0),
+            'b' => Some(1),
+            'c' => Some(2),
+            'd' => Some(3),
+            'e' => Some(4),
+            'f' => Some(5),
+            'g' => Some(6),
+            'h' => Some(7),
+            'i' => Some(8),
+            'j' => Some(9),
+            'k' => Some(10),
+            'l' => Some(11),
+            'm' => Some(12),
+            'n' => Some(13),
+            'o' => Some(14),
+            'p' => Some(15),
+            'q' => Some(16),
+            'r' => Some(17),
+            's' => Some(18),
+            _ => None,
         }
     }
 }
 
 impl SgfNode {
+    fn escape_val(val: &str) -> String {
+        val.replace('\\', "\\\\").replace(']', "\\]")
+    }
+
     fn _to_string(sgf_node: SgfNode) -> String {
         let mut node_str = String::new();
-        let key_str = match sgf_node.node_key {
-            SgfToken::CA => "CA",
-            SgfToken::FF => "FF",
-            SgfToken::AP => "AP",
-            SgfToken::GM => "GM",
-            SgfToken::SZ => "SZ",
-            SgfToken::PB => "PB",
-            SgfToken::PW => "PW",
-            SgfToken::BR => "BR",
-            SgfToken::WR => "WR",
-            SgfToken::RE => "RE",
-            SgfToken::KM => "KM",
-            SgfToken::HA => "HA",
-            SgfToken::TM => "TM",
-            SgfToken::DT => "DT",
-            SgfToken::EV => "EV",
-            SgfToken::RO => "RO",
-            SgfToken::PC => "PC",
-            SgfToken::RU => "RU",
-            SgfToken::GN => "GN",
-            SgfToken::ON => "ON",
-            SgfToken::SO => "SO",
-            SgfToken::US => "US",
-            SgfToken::AN => "AN",
-            SgfToken::CP => "CP",
-            SgfToken::GC => "GC",
-            SgfToken::AB => "AB",
-            SgfToken::AW => "AW",
-            SgfToken::AE => "AE",
-            SgfToken::PL => "PL",
-            SgfToken::B => "B",
-            SgfToken::W => "W",
-            SgfToken::C => "C",
-            SgfToken::CR => "CR",
-            SgfToken::MA => "MA",
-            SgfToken::SQ => "SQ",
-            SgfToken::TR => "TR",
-            SgfToken::LB => "LB",
-            SgfToken::TB => "TB",
-            SgfToken::TW => "TW",
-            _ => "",
-        };
+        let key_str = sgf_node.node_key.name();
         node_str += &key_str.to_string().clone();
         node_str += &"[".to_string().clone();
-        node_str += &sgf_node.node_val.clone();
+        node_str += &SgfNode::escape_val(&sgf_node.node_val);
         node_str += &"]".to_string().clone();
         node_str
     }
@@ -774,7 +1286,7 @@ impl SgfNode {
 mod test {
     use crate::go_move::GoMove;
 
-    use super::{SgfReader, ReadFile, Parse, GameTree};
+    use super::{SgfReader, ReadFile, Parse, GameTree, SgfKey, SgfNode};
 
     #[test]
     pub fn test_scan_sgf() {
@@ -796,8 +1308,13 @@ mod test {
             7.5,
             19,
             "a".to_string(),
-            "b".to_string());
+            "b".to_string(),
+            0);
         println!("{:?}", json::stringify(game_tree.to_json().unwrap()));
+        let game_tree = GameTree::create(0.5, 9, "a".to_string(), "b".to_string(), 2);
+        let root_props = GameTree::get_node_props(&game_tree, -1);
+        assert_eq!(root_props.iter().filter(|node| node.node_key() == SgfKey::AB).count(), 2);
+        assert_eq!(root_props.last().map(|node| node.node_val()), Some("W".to_string()));
     }
 
     #[test]
@@ -809,10 +1326,76 @@ mod test {
             println!("before={}", json::stringify(game_tree.to_json()));
             GameTree::record_move(&mut game_tree, 5, GoMove::new(9, 3, 10, -1));
             println!("after={}", json::stringify(game_tree.to_json()));
-            let _ = game_tree._save_sgf("sgf/test2.sgf");
+            let _ = game_tree.save_sgf("sgf/test2.sgf");
         }
     }
 
+    #[test]
+    pub fn test_node_props() {
+        let sgf_reader = SgfReader { content: "(;SZ[19]AB[aa][bb];B[cc]TR[dd][ee]LB[ff:A];W[gg])".to_string() };
+        let sgf_tokens = sgf_reader.parse();
+        let game_tree = GameTree::from_sgf_tokens(&sgf_tokens, 0, sgf_tokens.len() - 1, true, true).unwrap();
+        let root_props = GameTree::get_node_props(&game_tree, -1);
+        assert_eq!(root_props.len(), 3);
+        let node_props = GameTree::get_node_props(&game_tree, 0);
+        assert_eq!(node_props.iter().map(|node| node.node_val()).collect::<Vec<_>>(), vec!["dd", "ee", "ff:A"]);
+        assert_eq!(GameTree::get_node_props(&game_tree, 1).len(), 0);
+        assert_eq!(game_tree._to_string().unwrap(), "(;SZ[19]AB[aa][bb];B[cc]TR[dd][ee]LB[ff:A];W[gg])");
+    }
+
+    #[test]
+    pub fn test_edit_node_props() {
+        let sgf_reader = SgfReader { content: "(;SZ[19]AB[aa:ab];B[cc]TR[dd];W[gg])".to_string() };
+        let sgf_tokens = sgf_reader.parse();
+        let mut game_tree = GameTree::from_sgf_tokens(&sgf_tokens, 0, sgf_tokens.len() - 1, true, true).unwrap();
+        let removed = GameTree::remove_node_point(&mut game_tree, -1, &[SgfKey::AB, SgfKey::AW], (0, 1));
+        assert_eq!(removed, vec![SgfKey::AB]);
+        GameTree::add_node_prop(&mut game_tree, 0, SgfNode::new(SgfKey::TR, "ee".to_string()));
+        GameTree::add_node_prop(&mut game_tree, 0, SgfNode::new(SgfKey::LB, "ff:A".to_string()));
+        GameTree::add_node_prop(&mut game_tree, 1, SgfNode::new(SgfKey::SQ, "hh".to_string()));
+        assert_eq!(game_tree._to_string().unwrap(), "(;SZ[19]AB[aa];B[cc]TR[dd][ee]LB[ff:A];W[gg]SQ[hh])");
+        GameTree::set_node_prop(&mut game_tree, 0, SgfNode::new(SgfKey::TR, "hh".to_string()));
+        GameTree::set_node_prop(&mut game_tree, -1, SgfNode::new(SgfKey::RE, "B+T".to_string()));
+        assert_eq!(game_tree._to_string().unwrap(), "(;SZ[19]AB[aa]RE[B+T];B[cc]TR[hh]LB[ff:A];W[gg]SQ[hh])");
+    }
+
+    #[test]
+    pub fn test_unknown_props() {
+        let game_tree = GameTree::from_sgf("(;SZ[9] XY[aa][bb]\nAB[cc]\n[dd];B[ee]ZZ[ff]zz[gg])").unwrap();
+        assert_eq!(game_tree._to_string().unwrap(), "(;SZ[9]XY[aa][bb]AB[cc][dd];B[ee]ZZ[ff])");
+        let from_json = GameTree::from_json(&game_tree.to_json().unwrap()).unwrap();
+        assert_eq!(from_json.to_sgf(), game_tree.to_sgf());
+        assert_eq!(GameTree::from_sgf("(;N[x])").unwrap().to_sgf(), "(;N[x])");
+        assert_eq!(GameTree::supported_board_size(&GameTree::from_sgf("(;N[x])").unwrap()), Ok(19));
+        assert_eq!(GameTree::supported_board_size(&GameTree::from_sgf("(;SZ[0])").unwrap()).err(),
+            Some("SZ[0] is not supported, square sizes from 2 to 19 are".to_string()));
+        assert_eq!(GameTree::from_sgf("(;)").unwrap().to_json().unwrap()["nodes"].len(), 0);
+        for (sgf, err) in [
+            ("(", "a game tree must start with a node"),
+            ("(;B[aa]", "the game tree is not closed"),
+            ("(;B[aa]())", "a game tree must start with a node"),
+            ("B[aa]", "no game tree found"),
+        ] {
+            assert_eq!(GameTree::from_sgf(sgf).err(), Some(err.to_string()), "{}", sgf);
+        }
+    }
+
+    #[test]
+    pub fn test_variations() {
+        let sgf_reader = SgfReader { content: "(;SZ[9];B[aa];W[bb](;B[cc])(;B[dd];W[]))".to_string() };
+        let sgf_tokens = sgf_reader.parse();
+        let game_tree = GameTree::from_sgf_tokens(&sgf_tokens, 0, sgf_tokens.len() - 1, true, true).unwrap();
+        assert_eq!(GameTree::variation_paths(&game_tree).iter().map(|path| path.len()).collect::<Vec<_>>(), vec![4, 5]);
+        let moves = GameTree::get_moves(&game_tree, 2).unwrap();
+        assert_eq!(moves.iter().map(|(go_move, selected)| (go_move.move_pos(), *selected)).collect::<Vec<_>>(),
+            vec![((2, 2, 1), true), ((3, 3, 1), false)]);
+        assert!(GameTree::select_variation(&game_tree, 1, 1));
+        assert_eq!(GameTree::get_path_nodes(&game_tree).len(), 5);
+        assert!(GameTree::get_moves(&game_tree, 3).unwrap()[0].0.is_pass());
+        assert!(GameTree::select_variation(&game_tree, 1, -1));
+        assert_eq!(GameTree::get_path_nodes(&game_tree).len(), 4);
+    }
+
     #[test]
     pub fn test_to_string() {
         let sgf_path = "sgf/test.sgf".to_string();
@@ -822,4 +1405,58 @@ mod test {
             println!("{}", game_tree._to_string().unwrap());   
         }
     }
+
+    #[test]
+    pub fn test_json_round_trip() {
+        let sgf_path = "sgf/test.sgf".to_string();
+        let content = std::fs::read_to_string(&sgf_path).unwrap();
+        let game_tree = GameTree::from_sgf(&content).unwrap();
+        // the second variation is the one followed
+        assert!(GameTree::select_variation(&game_tree, 4, 1));
+        let from_json = GameTree::from_json(&game_tree.to_json().unwrap()).unwrap();
+        assert_eq!(from_json, game_tree);
+        assert_eq!(from_json.to_sgf(), game_tree.to_sgf());
+
+        let mut game_tree = GameTree::from_sgf("(;SZ[9];B[ee]C[a \\] b])").unwrap();
+        assert_eq!(GameTree::get_node_props(&game_tree, 0), vec![SgfNode::new(SgfKey::C, "a ] b".to_string())]);
+        GameTree::set_node_prop(&mut game_tree, 0, SgfNode::new(SgfKey::C, "[x]".to_string()));
+        assert_eq!(game_tree.to_sgf(), "(;SZ[9];B[ee]C[[x\\]])");
+        assert!(GameTree::from_json(&json::parse(r#"{"nodes": [{"xx": "1"}]}"#).unwrap()).is_err());
+
+        // nodes without a move stay apart from their neighbours
+        for sgf in ["(;SZ[9];AB[aa][bb];B[cc];C[x](;AW[dd];W[ee])(;W[ff]))", "(;SZ[9]AB[aa];C[x]AE[aa];W[cc])"] {
+            let game_tree = GameTree::from_sgf(sgf).unwrap();
+            assert_eq!(game_tree.to_sgf(), sgf);
+            let from_json = GameTree::from_json(&game_tree.to_json().unwrap()).unwrap();
+            assert_eq!(from_json.to_sgf(), sgf);
+        }
+        let mut game_tree = GameTree::from_sgf("(;SZ[9];AB[aa];B[cc])").unwrap();
+        assert_eq!(GameTree::get_node_props(&game_tree, -1), vec![SgfNode::new(SgfKey::SZ, "9".to_string())]);
+        GameTree::set_node_prop(&mut game_tree, -1, SgfNode::new(SgfKey::KM, "6.5".to_string()));
+        assert_eq!(game_tree.to_sgf(), "(;SZ[9]KM[6.5];AB[aa];B[cc])");
+    }
+
+    #[test]
+    pub fn test_json_errors() {
+        let game_tree = GameTree::from_json(&json::parse(r#"{"version": 1, "nodes": [{"SZ": "9"}, {"B": "ee"}]}"#).unwrap()).unwrap();
+        assert_eq!(game_tree.to_sgf(), "(;SZ[9];B[ee])");
+        assert_eq!(game_tree.to_json().unwrap()["version"], super::JSON_VERSION);
+
+        for (json, err) in [
+            (r#"[]"#, "a game tree must be an object"),
+            (r#"{"version": 3}"#, "unsupported version 3, up to 2 can be read"),
+            (r#"{"nodes": {"B": "ee"}}"#, "nodes: must be an array"),
+            (r#"{"nodes": [{"B": "ee", "C": "x"}]}"#, "nodes[0]: a node must be an object with one property"),
+            (r#"{"nodes": [{"B2": "1"}]}"#, "nodes[0]: not a property: B2"),
+            (r#"{"nodes": [{"KM": 6.5}]}"#, "nodes[0]: the value must be a string"),
+            (r#"{"colour": "B"}"#, "colour: unknown field"),
+            (r#"{"sub_trees": [{"selected": false}, {"selected": true, "nodes": [5]}]}"#,
+                "sub_trees[1].nodes[0]: a node must be an object with one property"),
+            (r#"{"sub_trees": [{"selected": true}, {"selected": true}]}"#,
+                "sub_trees: exactly one sub tree must be selected, not 2"),
+            (r#"{"sub_trees": [{"version": 1}]}"#, "sub_trees[0].version: unknown field"),
+        ] {
+            assert_eq!(GameTree::from_json(&json::parse(json).unwrap()).err(), Some(err.to_string()), "{}", json);
+        }
+    }
 }
\ No newline at end of file
@@ -1,21 +1,45 @@
-use std::{collections::{LinkedList, HashSet}, vec};
+use std::{collections::{LinkedList, HashSet}, vec, cell::Cell};
 
-use iced::{widget::canvas::{self, Stroke, stroke, LineCap, Path, Cache}, Renderer, Theme, Point, Size, Color, mouse::Cursor};
+use iced::{widget::canvas::{self, Stroke, stroke, LineCap, Path, Cache, Text}, Renderer, Theme, Point, Size, Color, mouse::Cursor, alignment};
 
-use crate::go_move::GoMove;
+use crate::{
+    go_move::GoMove,
+    markup::Markup,
+    band_theme::BandTheme,
+    band_layout::BandLayout,
+    diagram::{Diagram, Shape},
+    game_tree::{SgfKey, SgfNode},
+};
 
 #[derive(Debug, Clone, Copy)]
 pub enum Player {
     BLACK, WHITE,
 }
 
-pub struct GoBand<const D: usize> {
+impl Player {
+    pub fn opposite(&self) -> Player {
+        match self {
+            Player::BLACK => Player::WHITE,
+            Player::WHITE => Player::BLACK,
+        }
+    }
+
+    // stone state of the player's stones
+    pub fn state(&self) -> i8 {
+        match self {
+            Player::BLACK => 1,
+            Player::WHITE => -1,
+        }
+    }
+}
+
+pub struct GoBand {
+    dim: usize,
     window_width: u32,
     window_height: u32,
     margin_x: u32,
     margin_y: u32,
-    dim: usize,
-    stone_state: [[i8; D]; D],
+    stone_state: Vec<Vec<i8>>,
     stone_block: Vec<(i32, i32, i8)>,
     stone_pos: (i32, i32),
     next_stone_pos: Vec<(i32, i32, i8, bool)>,
@@ -23,73 +47,69 @@ pub struct GoBand<const D: usize> {
     cur_player: Player,
     band_cache: Cache,
     go_moves: Vec<GoMove>,
+    markups: Vec<Markup>,
+    dimmed_points: Vec<(usize, usize)>,
+    show_coordinates: bool,
+    move_number_start: Option<usize>,
+    band_theme: BandTheme,
+    // canvas size of the last draw, hit-testing has to use the same bounds
+    band_bounds: Cell<(f32, f32)>,
 }
 
-impl<const D: usize> GoBand<D> {
+impl GoBand {
     pub fn new(
+        dim: usize,
         window_width: u32,
         window_height: u32,
         margin_x: u32,
-        margin_y: u32,
-        dim: usize) -> Self {
+        margin_y: u32) -> Self {
         GoBand {
+            dim,
             window_width,
             window_height,
             margin_x,
             margin_y,
-            dim,
-            stone_state: [[0; D]; D],
+            stone_state: vec![vec![0; dim]; dim],
             stone_block: vec![],
             next_stone_pos: vec![],
-            mouse_preview: (D as i32 / 2, D as i32 / 2),
-            stone_pos: (D as i32 / 2, D as i32 / 2),
+            mouse_preview: (dim as i32 / 2, dim as i32 / 2),
+            stone_pos: (dim as i32 / 2, dim as i32 / 2),
             cur_player: Player::BLACK,
             band_cache: Cache::default(),
             go_moves: vec![],
+            markups: vec![],
+            dimmed_points: vec![],
+            show_coordinates: false,
+            move_number_start: None,
+            band_theme: BandTheme::default(),
+            band_bounds: Cell::new((window_width as f32, window_height as f32)),
         }
     }
 
-    pub fn get_preview_pos(&mut self, pos_x: f32, pos_y: f32) {
-        let grid_size = self.window_width.min(self.window_height) as f32 / D as f32;
-        let (align_x, align_y) = if self.window_width > self.window_height {
-            let align_y = grid_size / 2.0;
-            let align_x = self.window_width.abs_diff(self.window_height) as f32 / 2.0 + align_y;
-            (align_x, align_y)
-        } else {
-            let align_x = grid_size / 2.0;
-            let align_y = self.window_width.abs_diff(self.window_height) as f32 / 2.0 + align_x;
-            (align_x, align_y)
-        };
-        let frame_position = (pos_x - align_x - self.margin_x as f32, pos_y - align_y - self.margin_y as f32);
-        let mut x_grid = (frame_position.0 / grid_size) as i32;
-        let mut y_grid = (frame_position.1 / grid_size) as i32;
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
 
-        let x_res = frame_position.0 - grid_size * x_grid as f32;
-        let y_res = frame_position.1 - grid_size * y_grid as f32;
+    // a band of another size starts out empty with the cursor in the centre
+    pub fn set_dim(&mut self, dim: usize) {
+        self.dim = dim;
+        self.reset();
+        self.set_cursor(dim as i32 / 2, dim as i32 / 2);
+    }
 
-        if x_res > align_y {
-            x_grid += 1;
-        }
-        if y_res > align_y {
-            y_grid += 1;
-        }
+    // the layout of the band inside the canvas bounds it was last drawn with
+    pub fn band_layout(&self) -> BandLayout {
+        let (width, height) = self.band_bounds.get();
+        BandLayout::new(self.dim, width, height, self.show_coordinates)
+    }
 
+    // `pos_x` and `pos_y` are window coordinates, the canvas sits at the
+    // band margins inside the window
+    pub fn get_preview_pos(&mut self, pos_x: f32, pos_y: f32) {
+        let band_layout = self.band_layout();
+        let (x_grid, y_grid) = band_layout.grid_at(pos_x - self.margin_x as f32, pos_y - self.margin_y as f32);
         self.stone_pos = (x_grid, y_grid);
-
-        if x_grid < 0 {
-            x_grid = 0;
-        }
-        if x_grid > D as i32 - 1 {
-            x_grid = D as i32 - 1;
-        }
-        if y_grid < 0 {
-            y_grid = 0;
-        }
-        if y_grid > D as i32 - 1 {
-            y_grid = D as i32 - 1;
-        }
-
-        self.mouse_preview = (x_grid, y_grid);
+        self.mouse_preview = (x_grid.clamp(0, self.dim as i32 - 1), y_grid.clamp(0, self.dim as i32 - 1));
     }
 
     pub fn set_stone_pos(&mut self, x: i32, y: i32) {
@@ -108,6 +128,28 @@ impl<const D: usize> GoBand<D> {
         self.mouse_preview
     }
 
+    // moves the preview like a keyboard cursor, placing a stone then works
+    // the same way as a click on the preview
+    pub fn move_preview(&mut self, dx: i32, dy: i32) {
+        let (x, y) = self.mouse_preview;
+        self.set_cursor(x + dx, y + dy);
+    }
+
+    pub fn set_cursor(&mut self, x: i32, y: i32) {
+        self.mouse_preview = (x.clamp(0, self.dim as i32 - 1), y.clamp(0, self.dim as i32 - 1));
+        self.stone_pos = self.mouse_preview;
+        self.clear();
+    }
+
+    // a pass is recorded like a move so move ids keep matching the game tree
+    pub fn pass(&mut self) -> GoMove {
+        let go_move = GoMove::new_pass(self.go_moves.len(), self.current_player().state());
+        self.go_moves.push(go_move.clone());
+        self.set_current_player(self.current_player().opposite());
+        self.clear();
+        go_move
+    }
+
     pub fn current_player(&self) -> Player {
         self.cur_player
     }
@@ -126,6 +168,7 @@ impl<const D: usize> GoBand<D> {
 
     pub fn set_window_width(&mut self, window_width: u32) {
         self.window_width = window_width;
+        self.band_bounds.set((window_width as f32, self.window_height as f32));
     }
 
     pub fn window_width(&self) -> u32 {
@@ -134,18 +177,118 @@ impl<const D: usize> GoBand<D> {
 
     pub fn set_window_height(&mut self, window_height: u32) {
         self.window_height = window_height;
+        self.band_bounds.set((self.window_width as f32, window_height as f32));
+    }
+
+    pub fn set_show_coordinates(&mut self, show_coordinates: bool) {
+        self.show_coordinates = show_coordinates;
+    }
+
+    pub fn show_coordinates(&self) -> bool {
+        self.show_coordinates
+    }
+
+    // stones played from move `move_number_start` on are numbered starting
+    // with 1, `None` turns move numbers off
+    pub fn set_move_number_start(&mut self, move_number_start: Option<usize>) {
+        self.move_number_start = move_number_start;
+    }
+
+    pub fn set_markups(&mut self, markups: Vec<Markup>, dimmed_points: Vec<(usize, usize)>) {
+        self.markups = markups;
+        self.dimmed_points = dimmed_points;
+    }
+
+    pub fn set_band_theme(&mut self, band_theme: BandTheme) {
+        self.band_theme = band_theme;
+    }
+
+    pub fn band_theme(&self) -> BandTheme {
+        self.band_theme
+    }
+
+    // empties the band, ready to replay a game from its first move
+    pub fn reset(&mut self) {
+        self.stone_state = vec![vec![0; self.dim]; self.dim];
+        self.go_moves.clear();
+        self.next_stone_pos.clear();
+        self.markups.clear();
+        self.dimmed_points.clear();
+        self.cur_player = Player::BLACK;
+        self.clear();
     }
 
     pub(crate) fn clear(&self) {
         self.band_cache.clear();
     }
+
+    pub fn go_moves(&self) -> Vec<GoMove> {
+        self.go_moves.clone()
+    }
+
+    // plays a move or sets up stones of a game tree node, other properties
+    // leave the band alone
+    pub fn apply_sgf_node(&mut self, sgf_node: &SgfNode) {
+        let node_val = sgf_node.node_val();
+        match sgf_node.node_key() {
+            SgfKey::B | SgfKey::W => {
+                let player = if sgf_node.node_key() == SgfKey::B { Player::BLACK } else { Player::WHITE };
+                self.set_current_player(player);
+                let dim = self.dim();
+                match SgfNode::parse_points(&node_val).unwrap_or_default().first() {
+                    Some((x, y)) if *x < dim && *y < dim && node_val != "tt" => {
+                        self.set_stone_pos(*x as i32, *y as i32);
+                        self.forward(true);
+                    },
+                    _ => {
+                        self.pass();
+                    },
+                }
+            },
+            SgfKey::AB | SgfKey::AW | SgfKey::AE => {
+                let state = match sgf_node.node_key() {
+                    SgfKey::AB => 1,
+                    SgfKey::AW => -1,
+                    _ => 0,
+                };
+                let dim = self.dim();
+                for (x, y) in SgfNode::parse_points(&node_val).unwrap_or_default() {
+                    if x < dim && y < dim {
+                        self.set_stone_state(x, y, state);
+                    }
+                }
+            },
+            SgfKey::PL => {
+                let player = if node_val == "W" { Player::WHITE } else { Player::BLACK };
+                self.set_current_player(player);
+            },
+            _ => {},
+        }
+    }
+
+    // what the band shows right now, the canvas draws its shapes
+    pub fn diagram(&self) -> Diagram {
+        let mut diagram = Diagram::new(self.dim, self.stone_state.clone(), self.show_coordinates);
+        if let Some(move_number_start) = self.move_number_start {
+            diagram.number_moves(&self.go_moves, move_number_start..=usize::MAX, move_number_start);
+        }
+        let last_move = self.go_moves.last()
+            .filter(|go_move| !go_move.is_pass())
+            .map(|go_move| (go_move.move_pos().0, go_move.move_pos().1));
+        diagram.set_last_move(last_move);
+        diagram.set_to_play(self.cur_player.state());
+        diagram.set_markups(self.markups.clone(), self.dimmed_points.clone());
+        diagram.set_hints(self.next_stone_pos.clone());
+        diagram.set_preview(Some((self.mouse_preview.0, self.mouse_preview.1, self.cur_player.state())));
+        diagram
+    }
 }
 
-impl<const D: usize> GoBand<D> {
+impl GoBand {
     pub fn can_eat_stones(&mut self, stone_state: i8) -> Option<LinkedList<(HashSet<(usize, usize)>, i8)>> {
         let mut eaten_stones_list: LinkedList<(HashSet<(usize, usize)>, i8)> = LinkedList::new();
-        for i in 0..D {
-            for j in 0..D {
+        for i in 0..self.dim {
+            for j in 0..self.dim {
                 if self.stone_state[i][j] == 0 {
                     continue;
                 } else {
@@ -205,7 +348,7 @@ impl<const D: usize> GoBand<D> {
     }
 
     fn can_connect(&mut self, pos_x: i32, pos_y: i32, stone_state: i8) -> bool {
-        let ret = pos_x >= 0 && pos_x <= D as i32 - 1 && pos_y >= 0 && pos_y <= D as i32 - 1
+        let ret = pos_x >= 0 && pos_x <= self.dim as i32 - 1 && pos_y >= 0 && pos_y <= self.dim as i32 - 1
             && self.stone_state[pos_x as usize][pos_y as usize] == stone_state
             && !self.is_in_block(pos_x, pos_y);
         ret
@@ -227,11 +370,11 @@ impl<const D: usize> GoBand<D> {
 
             if pos_x - 1 >= 0
                 && self.stone_state[pos_x as usize - 1][pos_y as usize] == 0 { return true; }
-            if pos_x + 1 < D as i32
+            if pos_x + 1 < self.dim as i32
                 && self.stone_state[pos_x as usize + 1][pos_y as usize] == 0 { return true; }
             if pos_y - 1 >= 0
                 && self.stone_state[pos_x as usize][pos_y as usize - 1] == 0 { return true; }
-            if pos_y + 1 < D as i32
+            if pos_y + 1 < self.dim as i32
                 && self.stone_state[pos_x as usize][pos_y as usize + 1] == 0 { return true; }
         }
         false
@@ -243,7 +386,7 @@ pub trait Play {
     fn back(&mut self);
 }
 
-impl<const D: usize> Play for GoBand<D> {
+impl Play for GoBand {
     fn forward(&mut self, from_sgf: bool) -> Option<GoMove> {
         let stone_pos = self.stone_pos();
         let mouse_preview = self.mouse_preview();
@@ -301,8 +444,7 @@ impl<const D: usize> Play for GoBand<D> {
                             let eaten_state = eaten_stones.1;
                             if eaten_state == cur_state {
                                 // println!("check for robbery issue");
-                                let go_record_len = self.go_moves.len();
-                                match self.go_moves.get(go_record_len - 1) {
+                                match self.go_moves.last() {
                                     Some(go_move) => {
                                         // println!("checked move: {:?}, cur move: ({}, {})", go_move, cur_x, cur_y);
                                         let record_eaten_stones = go_move.eaten_stones();
@@ -374,7 +516,6 @@ impl<const D: usize> Play for GoBand<D> {
                 let mut go_move = GoMove::new(move_id, cur_x, cur_y, cur_state);
                 go_move.set_eaten_stones(eaten_stones_vec.clone());
                 self.go_moves.push(go_move);
-                println!("{}: {:?}", move_id, self.go_moves.last());
                 Some(GoMove::new_with_eaten_stones(move_id, cur_x, cur_y, cur_state, eaten_stones_vec.clone()))
             } else {
                 None
@@ -387,9 +528,10 @@ impl<const D: usize> Play for GoBand<D> {
     fn back(&mut self) {
         match self.go_moves.pop() {
             Some(go_move) => {
-                println!("recored move: {:?}", go_move.move_id());
                 let (pos_x, pos_y, record_state) = go_move.move_pos();
-                self.set_stone_state(pos_x, pos_y, 0);
+                if !go_move.is_pass() {
+                    self.set_stone_state(pos_x, pos_y, 0);
+                }
                 match record_state {
                     1 => self.set_current_player(Player::BLACK),
                     -1 => self.set_current_player(Player::WHITE),
@@ -406,7 +548,7 @@ impl<const D: usize> Play for GoBand<D> {
     }
 }
 
-impl<Message, const D: usize> canvas::Program<Message, Renderer> for GoBand<D> {
+impl<Message> canvas::Program<Message, Renderer> for GoBand {
     type State = ();
 
     fn draw(
@@ -417,102 +559,62 @@ impl<Message, const D: usize> canvas::Program<Message, Renderer> for GoBand<D> {
         bounds: iced::Rectangle,
         _cursor: Cursor,
     ) -> Vec<canvas::Geometry> {
+        self.band_bounds.set((bounds.width, bounds.height));
         let go_band = self.band_cache.draw(renderer, bounds.size(), |frame| {
-            let center = frame.center();
-            let radius = frame.width().min(frame.height()) / 2.0;
-            let top_left = Point::new(center.x - radius, center.y - radius);
-            let background = Path::rectangle(top_left, Size::new(radius * 2.0, radius * 2.0));
-            frame.fill(&background, Color::from_rgb8(250, 189, 132));
-
-            let grid_size = (radius * 2.0) / D as f32;
-            let thin_stroke = || -> Stroke {
-                Stroke {
-                    width: 1.0,
-                    style: stroke::Style::Solid(Color::BLACK),
-                    line_cap: LineCap::Round,
-                    ..Stroke::default()
-                }
-            };
-            let wide_stroke = || -> Stroke {
-                Stroke {
-                    width: 3.0,
-                    style: stroke::Style::Solid(Color::BLACK),
-                    line_cap: LineCap::Round,
-                    ..Stroke::default()
-                }
+            let band_layout = BandLayout::new(self.dim, frame.width(), frame.height(), self.show_coordinates);
+            let point = |(x, y): (f32, f32)| Point::new(x, y);
+            let line_stroke = |width: f32, color: Color| Stroke {
+                width,
+                style: stroke::Style::Solid(color),
+                line_cap: LineCap::Round,
+                ..Stroke::default()
             };
-            let top_left = Point::new(center.x - radius + grid_size / 2.0, center.y - radius + grid_size / 2.0);
-            for _i in 0..D {
-                let row = Path::line(Point::new(top_left.x, top_left.y + _i as f32 * grid_size), Point::new(top_left.x + radius * 2.0 - grid_size, top_left.y + _i as  f32 * grid_size));
-                let col = Path::line(Point::new(top_left.x + _i as f32 * grid_size, top_left.y), Point::new(top_left.x + _i as f32 * grid_size, top_left.y + radius * 2.0 - grid_size));
-                frame.with_save(|frame| {
-                    let stroke = if _i == 0 || _i == D - 1 {
-                        wide_stroke
-                    } else {
-                        thin_stroke
-                    };
-                    frame.stroke(&row, stroke());
-                    frame.stroke(&col, stroke());
-                })
-            }
-            let star_pos_arr = match self.dim {
-                9 => vec![(2, 2), (6, 2), (2, 6), (6, 6)],
-                13 => vec![(3, 3), (3, 9), (6, 6), (9, 3), (9, 9)],
-                19 => vec![
-                    (3, 3), (3, 9), (3, 15),
-                    (9, 3), (9, 9), (9, 15),
-                    (15, 3), (15, 9), (15, 15),
-                ],
-                _ => vec![],
-            };
-
-            for (x, y) in star_pos_arr {
-                let star_pos = Path::circle(Point::new(top_left.x + x as f32 * grid_size, top_left.y + y as f32 * grid_size), 3.0);
-                frame.fill(&star_pos, Color::BLACK);
-            }
-
-            let mouse_preview = self.mouse_preview();
-            let mouse_preview = Path::rectangle(Point::new(top_left.x + mouse_preview.0 as f32 * grid_size - 10.0, top_left.y + mouse_preview.1 as f32 * grid_size - 10.0), Size::new(20.0, 20.0));
-            let cur_player = self.current_player();
-            let color = if let Player::BLACK = cur_player {
-                Color::BLACK
-            } else {
-                Color::WHITE
-            };
-            frame.fill(&mouse_preview, color);
-
-            for x in 0..D as usize {
-                for y in 0..D as usize {
-                    let band_state = self.stone_state(x, y);
-                    if band_state != 0 {
-                        let cur_pos = Path::circle(Point::new(top_left.x + x as f32 * grid_size, top_left.y + y as f32 * grid_size), grid_size / 2.0);
-                        let color = if band_state == 1 { Color::BLACK } else { Color::WHITE };
-                        frame.fill(&cur_pos, color);
-
-                        if self.go_moves.len() > 0 {
-                            let last_move = self.go_moves.last().unwrap();
-                            let (last_x, last_y, last_state) = last_move.move_pos();
-                            if (x, y) == (last_x, last_y) {
-                                let indicator_color = if last_state == -1 { Color::BLACK } else { Color::WHITE };
-                                let indicator_pos = Path::circle(Point::new(top_left.x + x as f32 * grid_size, top_left.y + y as f32 * grid_size), grid_size / 4.0);
-                                frame.fill(&indicator_pos, indicator_color);
-                            }
+            for shape in self.diagram().shapes(&band_layout, self.band_theme) {
+                match shape {
+                    Shape::Rect { origin, size, fill, stroke: outline } => {
+                        let rect = Path::rectangle(point(origin), Size::new(size.0, size.1));
+                        if let Some(color) = fill {
+                            frame.fill(&rect, color);
                         }
-                    }
-                }
-            }
-
-            for (x, y, state, selected) in self.next_stone_pos.clone() {
-                if state == 1 {
-                    let cur_pos = Path::circle(Point::new(top_left.x + x as f32 * grid_size, top_left.y + y as f32 * grid_size), grid_size / 4.0);
-                    let color = if selected { Color::BLACK } else { Color::from_rgba8(0, 0, 0, 0.85) };
-                    frame.fill(&cur_pos, color);
-                } else if state == -1 {
-                    let cur_pos = Path::circle(Point::new(top_left.x + x as f32 * grid_size, top_left.y + y as f32 * grid_size), grid_size / 4.0);
-                    let color = if selected { Color::WHITE } else { Color::from_rgba8(255, 255, 255, 0.85) };
-                    frame.fill(&cur_pos, color);
-                } else {
-                    // ignore
+                        if let Some((width, color)) = outline {
+                            frame.stroke(&rect, line_stroke(width, color));
+                        }
+                    },
+                    Shape::Circle { center, radius, fill, stroke: outline } => {
+                        let circle = Path::circle(point(center), radius);
+                        if let Some(color) = fill {
+                            frame.fill(&circle, color);
+                        }
+                        if let Some((width, color)) = outline {
+                            frame.stroke(&circle, line_stroke(width, color));
+                        }
+                    },
+                    Shape::Line { points, closed, width, color } => {
+                        let line = Path::new(|builder| {
+                            for (idx, line_point) in points.iter().enumerate() {
+                                if idx == 0 {
+                                    builder.move_to(point(*line_point));
+                                } else {
+                                    builder.line_to(point(*line_point));
+                                }
+                            }
+                            if closed {
+                                builder.close();
+                            }
+                        });
+                        frame.stroke(&line, line_stroke(width, color));
+                    },
+                    Shape::Text { content, position, size, color } => {
+                        frame.fill_text(Text {
+                            content,
+                            position: point(position),
+                            color,
+                            size,
+                            horizontal_alignment: alignment::Horizontal::Center,
+                            vertical_alignment: alignment::Vertical::Center,
+                            ..Text::default()
+                        });
+                    },
                 }
             }
         });
@@ -1,3 +1,6 @@
+// position of a pass, no band is that large
+const PASS_POS: usize = usize::MAX;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct GoMove {
     move_id: usize,
@@ -10,6 +13,14 @@ impl GoMove {
         GoMove { move_id, move_pos: (pos_x, pos_y, cur_state), eat_stones: vec![] }
     }
 
+    pub fn new_pass(move_id: usize, cur_state: i8) -> Self {
+        GoMove { move_id, move_pos: (PASS_POS, PASS_POS, cur_state), eat_stones: vec![] }
+    }
+
+    pub fn is_pass(&self) -> bool {
+        self.move_pos.0 == PASS_POS
+    }
+
     pub fn new_with_eaten_stones(move_id: usize, pos_x: usize, pos_y: usize,
         cur_state: i8, eat_stones: Vec<(usize, usize, i8)>) -> Self {
         GoMove { move_id, move_pos: (pos_x, pos_y, cur_state), eat_stones }
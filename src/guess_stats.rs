@@ -0,0 +1,96 @@
+use std::fmt;
+
+// guesses at most this many intersections away count as near misses
+pub const NEAR_MISS_DISTANCE: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuessOutcome {
+    Hit,
+    NearMiss(usize),
+    Miss(usize),
+}
+
+impl fmt::Display for GuessOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuessOutcome::Hit => write!(f, "Hit!"),
+            GuessOutcome::NearMiss(distance) => write!(f, "Near miss, {} away", distance),
+            GuessOutcome::Miss(distance) => write!(f, "Miss, {} away", distance),
+        }
+    }
+}
+
+// score of guessing the moves of one game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GuessStats {
+    guesses: usize,
+    hits: usize,
+    near_misses: usize,
+    total_distance: usize,
+}
+
+impl GuessStats {
+    // intersections between two points, diagonal steps count as one
+    pub fn distance(pos: (usize, usize), other: (usize, usize)) -> usize {
+        pos.0.abs_diff(other.0).max(pos.1.abs_diff(other.1))
+    }
+
+    pub fn record(&mut self, guess: (usize, usize), actual: (usize, usize)) -> GuessOutcome {
+        let distance = GuessStats::distance(guess, actual);
+        self.guesses += 1;
+        self.total_distance += distance;
+        if distance == 0 {
+            self.hits += 1;
+            GuessOutcome::Hit
+        } else if distance <= NEAR_MISS_DISTANCE {
+            self.near_misses += 1;
+            GuessOutcome::NearMiss(distance)
+        } else {
+            GuessOutcome::Miss(distance)
+        }
+    }
+
+    pub fn guesses(&self) -> usize {
+        self.guesses
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn near_misses(&self) -> usize {
+        self.near_misses
+    }
+
+    pub fn average_distance(&self) -> f32 {
+        if self.guesses == 0 {
+            0.0
+        } else {
+            self.total_distance as f32 / self.guesses as f32
+        }
+    }
+}
+
+impl fmt::Display for GuessStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} guesses, {} hits, {} near, {:.1} away on average",
+            self.guesses(), self.hits(), self.near_misses(), self.average_distance())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GuessOutcome, GuessStats};
+
+    #[test]
+    pub fn test_record_guesses() {
+        let mut guess_stats = GuessStats::default();
+        assert_eq!(guess_stats.record((3, 3), (3, 3)), GuessOutcome::Hit);
+        assert_eq!(guess_stats.record((3, 3), (5, 4)), GuessOutcome::NearMiss(2));
+        assert_eq!(guess_stats.record((15, 3), (3, 15)), GuessOutcome::Miss(12));
+        assert_eq!(guess_stats.guesses(), 3);
+        assert_eq!(guess_stats.hits(), 1);
+        assert_eq!(guess_stats.near_misses(), 1);
+        assert_eq!(guess_stats.average_distance(), 14.0 / 3.0);
+    }
+}
@@ -0,0 +1,275 @@
+use std::{fmt, fs, path::Path, str::FromStr};
+
+use json::JsonValue;
+
+use crate::{
+    cli::{self, CliArg, CliArgs},
+    convert::RecordFormat,
+    file_browser::FileBrowser,
+    game_tree::{GameTree, SgfKey},
+};
+
+// header of each column, in the order of `RecordInfo::fields`
+pub const COLUMNS: [&str; 11] = [
+    "file", "black", "black_rank", "white", "white_rank", "result",
+    "date", "komi", "handicap", "moves", "variations",
+];
+
+// the root properties of a record with its size
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordInfo {
+    file: String,
+    black: String,
+    black_rank: String,
+    white: String,
+    white_rank: String,
+    result: String,
+    date: String,
+    komi: Option<f32>,
+    handicap: usize,
+    // moves of the main line, passes included
+    move_count: usize,
+    // lines from the root to the end of a variation
+    variation_count: usize,
+}
+
+impl RecordInfo {
+    pub fn from_game_tree(file: &str, game_tree: &GameTree) -> Self {
+        let root_props = GameTree::get_node_props(game_tree, -1);
+        let root_val = |key: SgfKey| root_props.iter()
+            .find(|node| node.node_key() == key)
+            .map(|node| node.node_val().trim().to_string())
+            .unwrap_or_default();
+        RecordInfo {
+            file: file.to_string(),
+            black: root_val(SgfKey::PB),
+            black_rank: root_val(SgfKey::BR),
+            white: root_val(SgfKey::PW),
+            white_rank: root_val(SgfKey::WR),
+            result: root_val(SgfKey::RE),
+            date: root_val(SgfKey::DT),
+            komi: root_val(SgfKey::KM).parse().ok(),
+            handicap: root_val(SgfKey::HA).parse().unwrap_or(0),
+            move_count: GameTree::get_path_nodes(game_tree).iter().filter(|node| node.is_move()).count(),
+            variation_count: GameTree::variation_paths(game_tree).len(),
+        }
+    }
+
+    // `black 9p - white`, leaving out what is not known
+    pub fn players(&self) -> String {
+        let player = |name: &str, rank: &str, fallback: &str| {
+            let name = if name.is_empty() { fallback } else { name };
+            if rank.is_empty() { name.to_string() } else { format!("{} {}", name, rank) }
+        };
+        format!("{} - {}", player(&self.black, &self.black_rank, "Black"), player(&self.white, &self.white_rank, "White"))
+    }
+
+    // the date, komi, handicap and result that are known
+    pub fn details(&self) -> String {
+        let mut details = vec![];
+        if !self.date.is_empty() {
+            details.push(self.date.clone());
+        }
+        if let Some(komi) = self.komi {
+            details.push(format!("komi {}", komi));
+        }
+        if self.handicap > 0 {
+            details.push(format!("handicap {}", self.handicap));
+        }
+        if !self.result.is_empty() {
+            details.push(format!("result {}", self.result));
+        }
+        details.join(", ")
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.move_count
+    }
+
+    // the values of `COLUMNS`, as they are printed
+    pub fn fields(&self) -> Vec<String> {
+        vec![
+            self.file.clone(),
+            self.black.clone(),
+            self.black_rank.clone(),
+            self.white.clone(),
+            self.white_rank.clone(),
+            self.result.clone(),
+            self.date.clone(),
+            self.komi.map(|komi| komi.to_string()).unwrap_or_default(),
+            self.handicap.to_string(),
+            self.move_count.to_string(),
+            self.variation_count.to_string(),
+        ]
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        json::object! {
+            file: self.file.clone(),
+            black: self.black.clone(),
+            black_rank: self.black_rank.clone(),
+            white: self.white.clone(),
+            white_rank: self.white_rank.clone(),
+            result: self.result.clone(),
+            date: self.date.clone(),
+            komi: self.komi,
+            handicap: self.handicap,
+            moves: self.move_count,
+            variations: self.variation_count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfoFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+impl InfoFormat {
+    pub const ALL: [InfoFormat; 3] = [
+        InfoFormat::Table,
+        InfoFormat::Csv,
+        InfoFormat::Json,
+    ];
+
+    pub fn format_infos(&self, record_infos: &[RecordInfo]) -> String {
+        match self {
+            InfoFormat::Table => {
+                let rows = record_infos.iter().map(|record_info| record_info.fields()).collect::<Vec<_>>();
+                let widths = COLUMNS.iter()
+                    .enumerate()
+                    .map(|(idx, column)| rows.iter().map(|row| row[idx].chars().count()).fold(column.len(), usize::max))
+                    .collect::<Vec<_>>();
+                let format_row = |row: Vec<String>| row.iter()
+                    .zip(&widths)
+                    .map(|(field, width)| format!("{:<width$}", field, width = width))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string();
+                let mut lines = vec![format_row(COLUMNS.map(|column| column.to_string()).to_vec())];
+                lines.extend(rows.into_iter().map(format_row));
+                let move_count = record_infos.iter().map(|record_info| record_info.move_count()).sum::<usize>();
+                lines.push(format!("{} record(s), {} move(s)", record_infos.len(), move_count));
+                lines.join("\n")
+            },
+            InfoFormat::Csv => {
+                // fields with a separator, a quote or a line break are quoted
+                let format_field = |field: &str| if field.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field.to_string()
+                };
+                let mut lines = vec![COLUMNS.join(",")];
+                for record_info in record_infos {
+                    lines.push(record_info.fields().iter().map(|field| format_field(field)).collect::<Vec<_>>().join(","));
+                }
+                lines.join("\n")
+            },
+            InfoFormat::Json => {
+                let record_infos = record_infos.iter().map(|record_info| record_info.to_json()).collect::<Vec<_>>();
+                json::stringify_pretty(record_infos, 2)
+            },
+        }
+    }
+}
+
+impl fmt::Display for InfoFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InfoFormat::Table => "table",
+            InfoFormat::Csv => "csv",
+            InfoFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for InfoFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        cli::parse_format(&InfoFormat::ALL, s)
+    }
+}
+
+// the records of sgf files and directories listed together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Info {
+    paths: Vec<String>,
+    info_format: InfoFormat,
+}
+
+impl Info {
+    // `[--format FORMAT] PATH...`, directories are searched for sgf files
+    pub fn from_args(args: &[String]) -> Result<Info, String> {
+        let mut info_format = InfoFormat::Table;
+        let mut paths = vec![];
+        let mut args = CliArgs::new(args);
+        while let Some(arg) = args.next_arg() {
+            match arg {
+                CliArg::Option("--format") => info_format = args.value("FORMAT")?.parse()?,
+                CliArg::Option(_) => return Err(args.unknown_option()),
+                CliArg::Path(path) => paths.push(path),
+            }
+        }
+        if paths.is_empty() {
+            return Err("info needs a FILE or DIRECTORY".to_string());
+        }
+        Ok(Info { paths, info_format })
+    }
+
+    // records that cannot be read are left out with a note on stderr, the
+    // listing itself goes to stdout
+    pub fn record_infos(&self) -> Result<Vec<RecordInfo>, String> {
+        let mut record_infos = vec![];
+        for path in &self.paths {
+            for record_path in FileBrowser::find_records(Path::new(path), RecordFormat::Sgf)? {
+                let file = record_path.to_string_lossy().to_string();
+                let game_tree = fs::read_to_string(&record_path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|content| GameTree::from_sgf(&content));
+                match game_tree {
+                    Ok(game_tree) => record_infos.push(RecordInfo::from_game_tree(&file, &game_tree)),
+                    Err(err) => eprintln!("ignoring {}: {}", file, err),
+                }
+            }
+        }
+        Ok(record_infos)
+    }
+
+    pub fn run(&self) -> Result<(), String> {
+        let record_infos = self.record_infos()?;
+        println!("{}", self.info_format.format_infos(&record_infos));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{cli::args, game_tree::GameTree};
+
+    use super::{Info, InfoFormat, RecordInfo};
+
+    #[test]
+    pub fn test_record_info() {
+        let game_tree = GameTree::from_sgf("(;GM[1]SZ[9]PB[Go, Seigen]BR[9p]PW[Kitani]KM[5.5]RE[B+R];B[ee];W[cc](;B[gg])(;B[gc];W[]))").unwrap();
+        let record_info = RecordInfo::from_game_tree("game.sgf", &game_tree);
+        assert_eq!(record_info.players(), "Go, Seigen 9p - Kitani");
+        assert_eq!(record_info.details(), "komi 5.5, result B+R");
+        assert_eq!(record_info.fields(), vec!["game.sgf", "Go, Seigen", "9p", "Kitani", "", "B+R", "", "5.5", "0", "3", "2"]);
+        let record_infos = vec![record_info];
+        assert_eq!(InfoFormat::Csv.format_infos(&record_infos).lines().nth(1),
+            Some("game.sgf,\"Go, Seigen\",9p,Kitani,,B+R,,5.5,0,3,2"));
+        assert_eq!(json::parse(&InfoFormat::Json.format_infos(&record_infos)).unwrap()[0]["komi"].as_f32(), Some(5.5));
+        let table = InfoFormat::Table.format_infos(&record_infos);
+        assert!(table.starts_with("file      black       black_rank"));
+        assert!(table.ends_with("1 record(s), 3 move(s)"));
+
+        let record_infos = Info::from_args(&args("--format csv sgf/test.sgf")).unwrap().record_infos().unwrap();
+        assert_eq!(record_infos[0].move_count(), 9);
+        assert_eq!(Info::from_args(&args("--format")).err(), Some("--format needs a FORMAT value".to_string()));
+    }
+}
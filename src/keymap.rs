@@ -0,0 +1,240 @@
+use std::{fmt, str::FromStr};
+
+use iced::keyboard::{KeyCode, Modifiers};
+
+// keys a binding can name, matched case-insensitively
+const KEY_NAMES: [(&str, KeyCode); 46] = [
+    ("left", KeyCode::Left), ("right", KeyCode::Right),
+    ("up", KeyCode::Up), ("down", KeyCode::Down),
+    ("home", KeyCode::Home), ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp), ("pagedown", KeyCode::PageDown),
+    ("tab", KeyCode::Tab), ("space", KeyCode::Space),
+    ("enter", KeyCode::Enter), ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete), ("escape", KeyCode::Escape),
+    ("f1", KeyCode::F1), ("f2", KeyCode::F2),
+    ("f3", KeyCode::F3), ("f4", KeyCode::F4),
+    ("f5", KeyCode::F5), ("f6", KeyCode::F6),
+    ("a", KeyCode::A), ("b", KeyCode::B), ("c", KeyCode::C), ("d", KeyCode::D),
+    ("e", KeyCode::E), ("f", KeyCode::F), ("g", KeyCode::G), ("h", KeyCode::H),
+    ("i", KeyCode::I), ("j", KeyCode::J), ("k", KeyCode::K), ("l", KeyCode::L),
+    ("m", KeyCode::M), ("n", KeyCode::N), ("o", KeyCode::O), ("p", KeyCode::P),
+    ("q", KeyCode::Q), ("r", KeyCode::R), ("s", KeyCode::S), ("t", KeyCode::T),
+    ("u", KeyCode::U), ("v", KeyCode::V), ("w", KeyCode::W), ("x", KeyCode::X),
+    ("y", KeyCode::Y), ("z", KeyCode::Z),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Back,
+    Forward,
+    First,
+    Last,
+    BackTen,
+    ForwardTen,
+    PrevVariation,
+    NextVariation,
+    Pass,
+    CursorLeft,
+    CursorRight,
+    CursorUp,
+    CursorDown,
+    PlaceStone,
+    ToggleMoveNumbers,
+    NumberFromMove,
+    ToggleCoordinates,
+    NextTheme,
+    FocusCommand,
+    ToggleAutoReplay,
+    ReplayFaster,
+    ReplaySlower,
+    ToggleGuessMode,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 23] = [
+        KeyAction::Back,
+        KeyAction::Forward,
+        KeyAction::First,
+        KeyAction::Last,
+        KeyAction::BackTen,
+        KeyAction::ForwardTen,
+        KeyAction::PrevVariation,
+        KeyAction::NextVariation,
+        KeyAction::Pass,
+        KeyAction::CursorLeft,
+        KeyAction::CursorRight,
+        KeyAction::CursorUp,
+        KeyAction::CursorDown,
+        KeyAction::PlaceStone,
+        KeyAction::ToggleMoveNumbers,
+        KeyAction::NumberFromMove,
+        KeyAction::ToggleCoordinates,
+        KeyAction::NextTheme,
+        KeyAction::FocusCommand,
+        KeyAction::ToggleAutoReplay,
+        KeyAction::ReplayFaster,
+        KeyAction::ReplaySlower,
+        KeyAction::ToggleGuessMode,
+    ];
+}
+
+impl fmt::Display for KeyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KeyAction::Back => "back",
+            KeyAction::Forward => "forward",
+            KeyAction::First => "first",
+            KeyAction::Last => "last",
+            KeyAction::BackTen => "back10",
+            KeyAction::ForwardTen => "forward10",
+            KeyAction::PrevVariation => "prev-variation",
+            KeyAction::NextVariation => "next-variation",
+            KeyAction::Pass => "pass",
+            KeyAction::CursorLeft => "cursor-left",
+            KeyAction::CursorRight => "cursor-right",
+            KeyAction::CursorUp => "cursor-up",
+            KeyAction::CursorDown => "cursor-down",
+            KeyAction::PlaceStone => "place",
+            KeyAction::ToggleMoveNumbers => "numbers",
+            KeyAction::NumberFromMove => "number-from",
+            KeyAction::ToggleCoordinates => "coordinates",
+            KeyAction::NextTheme => "theme",
+            KeyAction::FocusCommand => "command",
+            KeyAction::ToggleAutoReplay => "replay",
+            KeyAction::ReplayFaster => "replay-faster",
+            KeyAction::ReplaySlower => "replay-slower",
+            KeyAction::ToggleGuessMode => "guess",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for KeyAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KeyAction::ALL.iter()
+            .find(|key_action| key_action.to_string() == s)
+            .copied()
+            .ok_or(format!("unknown key action: {}", s))
+    }
+}
+
+// a key with the modifiers that have to be held, e.g. `shift+left`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    key_code: KeyCode,
+    shift: bool,
+    control: bool,
+}
+
+impl KeyBinding {
+    pub fn new(key_code: KeyCode) -> Self {
+        KeyBinding { key_code, shift: false, control: false }
+    }
+
+    pub fn with_shift(key_code: KeyCode) -> Self {
+        KeyBinding { key_code, shift: true, control: false }
+    }
+
+    pub fn with_control(key_code: KeyCode) -> Self {
+        KeyBinding { key_code, shift: false, control: true }
+    }
+
+    pub fn matches(&self, key_code: KeyCode, modifiers: Modifiers) -> bool {
+        self.key_code == key_code
+            && self.shift == modifiers.shift()
+            && self.control == modifiers.control()
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut shift = false;
+        let mut control = false;
+        let mut key_code = None;
+        for part in s.split('+').map(|part| part.trim().to_lowercase()) {
+            match part.as_str() {
+                "shift" => shift = true,
+                "ctrl" | "control" => control = true,
+                name => {
+                    key_code = KEY_NAMES.iter()
+                        .find(|(key_name, _)| *key_name == name)
+                        .map(|(_, key_code)| *key_code);
+                    if key_code.is_none() {
+                        return Err(format!("unknown key: {}", name));
+                    }
+                },
+            }
+        }
+        match key_code {
+            Some(key_code) => Ok(KeyBinding { key_code, shift, control }),
+            None => Err(format!("no key in binding: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, KeyAction)>,
+}
+
+impl Keymap {
+    pub fn action(&self, key_code: KeyCode, modifiers: Modifiers) -> Option<KeyAction> {
+        self.bindings.iter()
+            .find(|(key_binding, _)| key_binding.matches(key_code, modifiers))
+            .map(|(_, key_action)| *key_action)
+    }
+
+    // a key triggers one action only, binding it again replaces the old action
+    pub fn bind(&mut self, key_binding: KeyBinding, key_action: KeyAction) {
+        self.bindings.retain(|(bound, _)| *bound != key_binding);
+        self.bindings.push((key_binding, key_action));
+    }
+
+    // overrides the defaults with `action=key` pairs separated by commas,
+    // e.g. `back=h,forward=l,pass=shift+p`
+    pub fn from_spec(spec: &str) -> Result<Self, String> {
+        let mut keymap = Keymap::default();
+        for pair in spec.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (action, key) = pair.split_once('=').ok_or(format!("expected action=key: {}", pair))?;
+            keymap.bind(key.parse()?, action.trim().parse()?);
+        }
+        Ok(keymap)
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = vec![
+            (KeyBinding::new(KeyCode::Left), KeyAction::Back),
+            (KeyBinding::new(KeyCode::Right), KeyAction::Forward),
+            (KeyBinding::new(KeyCode::Home), KeyAction::First),
+            (KeyBinding::new(KeyCode::End), KeyAction::Last),
+            (KeyBinding::new(KeyCode::PageUp), KeyAction::BackTen),
+            (KeyBinding::new(KeyCode::PageDown), KeyAction::ForwardTen),
+            (KeyBinding::new(KeyCode::Up), KeyAction::PrevVariation),
+            (KeyBinding::new(KeyCode::Down), KeyAction::NextVariation),
+            (KeyBinding::new(KeyCode::Tab), KeyAction::NextVariation),
+            (KeyBinding::new(KeyCode::P), KeyAction::Pass),
+            (KeyBinding::with_shift(KeyCode::Left), KeyAction::CursorLeft),
+            (KeyBinding::with_shift(KeyCode::Right), KeyAction::CursorRight),
+            (KeyBinding::with_shift(KeyCode::Up), KeyAction::CursorUp),
+            (KeyBinding::with_shift(KeyCode::Down), KeyAction::CursorDown),
+            (KeyBinding::new(KeyCode::Enter), KeyAction::PlaceStone),
+            (KeyBinding::new(KeyCode::Space), KeyAction::PlaceStone),
+            (KeyBinding::new(KeyCode::N), KeyAction::ToggleMoveNumbers),
+            (KeyBinding::new(KeyCode::M), KeyAction::NumberFromMove),
+            (KeyBinding::new(KeyCode::C), KeyAction::ToggleCoordinates),
+            (KeyBinding::new(KeyCode::T), KeyAction::NextTheme),
+            (KeyBinding::new(KeyCode::G), KeyAction::FocusCommand),
+            (KeyBinding::new(KeyCode::R), KeyAction::ToggleAutoReplay),
+            (KeyBinding::with_control(KeyCode::Up), KeyAction::ReplayFaster),
+            (KeyBinding::with_control(KeyCode::Down), KeyAction::ReplaySlower),
+            (KeyBinding::new(KeyCode::Q), KeyAction::ToggleGuessMode),
+        ];
+        Keymap { bindings }
+    }
+}
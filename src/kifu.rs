@@ -0,0 +1,384 @@
+use std::{collections::HashMap, fmt, path::Path, str::FromStr};
+
+use iced::Color;
+
+use crate::{
+    band_layout::BandLayout,
+    band_theme::BandTheme,
+    cli::{self, CliArg, CliArgs},
+    convert::{self, RecordFormat, STDIO_PATH},
+    diagram::{Diagram, Shape},
+    game_tree::GameTree,
+    go_band::GoBand,
+    info::RecordInfo,
+    pdf,
+    svg,
+};
+
+// a4 in points
+pub const PAGE_SIZE: (f32, f32) = (595.0, 842.0);
+const PAGE_MARGIN: f32 = 48.0;
+pub const DEFAULT_FIGURE_MOVES: usize = 50;
+pub const DEFAULT_FIGURES_PER_PAGE: usize = 2;
+const TITLE_SIZE: f32 = 16.0;
+const CAPTION_SIZE: f32 = 11.0;
+const NOTE_SIZE: f32 = 9.0;
+const LINE_SPACING: f32 = 1.4;
+
+// the moves of one diagram of a game record, numbered on the position
+// before the first of them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Figure {
+    figure_number: usize,
+    first_move: usize,
+    last_move: usize,
+    diagram: Diagram,
+    // moves that could not be shown at their point, as `12 at 5`
+    notes: Vec<String>,
+}
+
+impl Figure {
+    pub fn caption(&self) -> String {
+        if self.last_move < self.first_move {
+            return format!("Figure {}", self.figure_number);
+        }
+        format!("Figure {} ({}-{})", self.figure_number, self.first_move, self.last_move)
+    }
+
+    pub fn notes(&self) -> Vec<String> {
+        self.notes.clone()
+    }
+
+    pub fn diagram(&self) -> Diagram {
+        self.diagram.clone()
+    }
+}
+
+// the main line in figures of up to `figure_moves` moves, with the stones
+// each player took over the whole game; moves are numbered by the move nodes
+// of the record, as the figures start from `Diagram::from_game_tree`, and a
+// record without moves gets one figure of its position
+pub fn figures(game_tree: &GameTree, figure_moves: usize, show_coordinates: bool) -> (Vec<Figure>, (usize, usize)) {
+    let dim = GameTree::board_size(game_tree);
+    let point_label = |x: usize, y: usize| format!("{}{}", BandLayout::column_label(x), dim - y);
+    // what each move node did on the band, none when it was not legal
+    let mut go_band = GoBand::new(dim, 0, 0, 0, 0);
+    let mut played_moves = vec![];
+    for sgf_node in GameTree::get_path_nodes(game_tree) {
+        let move_count = go_band.go_moves().len();
+        go_band.apply_sgf_node(&sgf_node);
+        if sgf_node.is_move() {
+            played_moves.push(go_band.go_moves().get(move_count).cloned());
+        }
+    }
+
+    let figure_moves = figure_moves.max(1);
+    let mut figures = vec![];
+    let mut prisoners = (0, 0);
+    let mut figure_chunks = played_moves.chunks(figure_moves).collect::<Vec<_>>();
+    if figure_chunks.is_empty() {
+        figure_chunks.push(&[]);
+    }
+    for (figure_idx, figure_played_moves) in figure_chunks.into_iter().enumerate() {
+        let first_move = figure_idx * figure_moves + 1;
+        let mut diagram = Diagram::from_game_tree(game_tree, first_move - 1, None);
+        diagram.set_markups(vec![], vec![]);
+        diagram.set_show_coordinates(show_coordinates);
+        // what each point shows so far, the move number or none for a stone
+        // from before the figure
+        let mut shown = HashMap::new();
+        for y in 0..dim {
+            for x in 0..dim {
+                if diagram.stone_state(x, y) != 0 {
+                    shown.insert((x, y), None);
+                }
+            }
+        }
+        let mut notes = vec![];
+        for (move_idx, go_move) in figure_played_moves.iter().enumerate() {
+            let move_number = first_move + move_idx;
+            let go_move = match go_move {
+                Some(go_move) => go_move,
+                None => {
+                    notes.push(format!("{} illegal", move_number));
+                    continue;
+                },
+            };
+            let (x, y, state) = go_move.move_pos();
+            let eaten_count = go_move.eaten_stones().len();
+            if state == 1 { prisoners.0 += eaten_count } else { prisoners.1 += eaten_count }
+            if go_move.is_pass() {
+                notes.push(format!("{} pass", move_number));
+                continue;
+            }
+            // a point is played again once its stone was taken
+            match shown.get(&(x, y)) {
+                Some(Some(shown_number)) => notes.push(format!("{} at {}", move_number, shown_number)),
+                Some(None) => notes.push(format!("{} at {}", move_number, point_label(x, y))),
+                None => {
+                    shown.insert((x, y), Some(move_number));
+                    diagram.place_numbered(x, y, state, move_number);
+                },
+            }
+        }
+        figures.push(Figure {
+            figure_number: figure_idx + 1,
+            first_move,
+            last_move: first_move + figure_played_moves.len() - 1,
+            diagram,
+            notes,
+        });
+    }
+    (figures, prisoners)
+}
+
+// `notes` joined by commas into lines of about `line_width` points
+fn wrap_notes(notes: &[String], line_width: f32) -> Vec<String> {
+    let max_chars = (line_width / (NOTE_SIZE * 0.55)) as usize;
+    let mut lines: Vec<String> = vec![];
+    for (note_idx, note) in notes.iter().enumerate() {
+        let note = if note_idx + 1 < notes.len() { format!("{},", note) } else { note.clone() };
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + note.len() <= max_chars => {
+                line.push(' ');
+                line.push_str(&note);
+            },
+            _ => lines.push(note),
+        }
+    }
+    lines
+}
+
+fn text(content: String, position: (f32, f32), size: f32) -> Shape {
+    Shape::Text { content, position, size, color: Color::BLACK }
+}
+
+// the game info on top of the first page, then `figures_per_page` figures
+// to a page with their captions and notes below them
+pub fn pages(record_info: &RecordInfo, figures: &[Figure], prisoners: (usize, usize), figures_per_page: usize, band_theme: BandTheme) -> Vec<Vec<Shape>> {
+    let (page_width, page_height) = PAGE_SIZE;
+    let content_width = page_width - PAGE_MARGIN * 2.0;
+    let center_x = page_width / 2.0;
+    let figures_per_page = figures_per_page.max(1);
+    let header_height = (TITLE_SIZE + NOTE_SIZE * 2.0) * LINE_SPACING + NOTE_SIZE;
+    // every diagram gets the same size, whatever the notes below it need
+    let note_lines = figures.iter().map(|figure| wrap_notes(&figure.notes, content_width).len()).max().unwrap_or(0);
+    let caption_height = (CAPTION_SIZE + note_lines as f32 * NOTE_SIZE) * LINE_SPACING + NOTE_SIZE;
+    let slot_height = (page_height - PAGE_MARGIN * 2.0 - header_height) / figures_per_page as f32;
+    let diagram_width = content_width.min(slot_height - caption_height).max(PAGE_MARGIN);
+
+    let mut pages = vec![];
+    for (page_idx, page_figures) in figures.chunks(figures_per_page).enumerate() {
+        let mut shapes = vec![];
+        let mut top = PAGE_MARGIN;
+        if page_idx == 0 {
+            shapes.push(text(record_info.players(), (center_x, top + TITLE_SIZE / 2.0), TITLE_SIZE));
+            top += TITLE_SIZE * LINE_SPACING;
+            shapes.push(text(record_info.details(), (center_x, top + NOTE_SIZE / 2.0), NOTE_SIZE));
+            top += NOTE_SIZE * LINE_SPACING;
+            let summary = format!("{} moves, black took {} and white took {} stones", record_info.move_count(), prisoners.0, prisoners.1);
+            shapes.push(text(summary, (center_x, top + NOTE_SIZE / 2.0), NOTE_SIZE));
+        }
+        top = PAGE_MARGIN + header_height;
+        for figure in page_figures {
+            let band_layout = figure.diagram.layout_for_width(diagram_width);
+            let (view_x, view_y, view_width, view_height) = figure.diagram.viewport(&band_layout);
+            let (offset_x, offset_y) = (center_x - view_width / 2.0 - view_x, top - view_y);
+            shapes.extend(figure.diagram.shapes(&band_layout, band_theme).iter().map(|shape| shape.translated(offset_x, offset_y)));
+            let mut line_top = top + view_height + NOTE_SIZE;
+            shapes.push(text(figure.caption(), (center_x, line_top + CAPTION_SIZE / 2.0), CAPTION_SIZE));
+            line_top += CAPTION_SIZE * LINE_SPACING;
+            for line in wrap_notes(&figure.notes, content_width) {
+                shapes.push(text(line, (center_x, line_top + NOTE_SIZE / 2.0), NOTE_SIZE));
+                line_top += NOTE_SIZE * LINE_SPACING;
+            }
+            top += slot_height;
+        }
+        shapes.push(text((page_idx + 1).to_string(), (center_x, page_height - PAGE_MARGIN / 2.0), NOTE_SIZE));
+        pages.push(shapes);
+    }
+    pages
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KifuFormat {
+    Svg,
+    Pdf,
+}
+
+impl KifuFormat {
+    pub const ALL: [KifuFormat; 2] = [
+        KifuFormat::Svg,
+        KifuFormat::Pdf,
+    ];
+}
+
+impl fmt::Display for KifuFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KifuFormat::Svg => "svg",
+            KifuFormat::Pdf => "pdf",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for KifuFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        cli::parse_format(&KifuFormat::ALL, s)
+    }
+}
+
+// `game.svg` for a single page, `game-1.svg`, `game-2.svg` and so on
+// otherwise
+fn page_path(output: &str, page_idx: usize, page_count: usize) -> String {
+    if page_count == 1 {
+        return output.to_string();
+    }
+    let path = Path::new(output);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, page_idx + 1, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, page_idx + 1),
+    };
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+// a game record printed as a series of figures
+#[derive(Debug, Clone, PartialEq)]
+pub struct KifuExport {
+    input: String,
+    output: String,
+    kifu_format: KifuFormat,
+    figure_moves: usize,
+    figures_per_page: usize,
+    show_coordinates: bool,
+    band_theme: BandTheme,
+}
+
+impl KifuExport {
+    // `[--format FORMAT] [--moves N] [--per-page N] [--coordinates]
+    // [--theme THEME] INPUT OUTPUT`
+    pub fn from_args(args: &[String]) -> Result<KifuExport, String> {
+        let mut kifu_format = None;
+        let mut figure_moves = DEFAULT_FIGURE_MOVES;
+        let mut figures_per_page = DEFAULT_FIGURES_PER_PAGE;
+        let mut show_coordinates = false;
+        let mut band_theme = BandTheme::Monochrome;
+        let mut paths = vec![];
+        let count = |value: String| value.parse::<usize>()
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or(format!("not a count: {}", value));
+        let mut args = CliArgs::new(args);
+        while let Some(arg) = args.next_arg() {
+            match arg {
+                CliArg::Option("--format") => kifu_format = Some(args.value("FORMAT")?.parse()?),
+                CliArg::Option("--moves") => figure_moves = count(args.value("N")?)?,
+                CliArg::Option("--per-page") => figures_per_page = count(args.value("N")?)?,
+                CliArg::Option("--coordinates") => show_coordinates = true,
+                CliArg::Option("--theme") => band_theme = args.value("THEME")?.parse()?,
+                CliArg::Option(_) => return Err(args.unknown_option()),
+                CliArg::Path(path) => paths.push(path),
+            }
+        }
+        let (input, output) = match paths.as_slice() {
+            [input, output] => (input.clone(), output.clone()),
+            _ => return Err("kifu needs an INPUT record and an OUTPUT file".to_string()),
+        };
+        let kifu_format = kifu_format
+            .or(cli::format_from_path(&output))
+            .ok_or(format!("give --format for {}", output))?;
+        Ok(KifuExport { input, output, kifu_format, figure_moves, figures_per_page, show_coordinates, band_theme })
+    }
+
+    pub fn pages(&self, game_tree: &GameTree) -> Result<Vec<Vec<Shape>>, String> {
+        GameTree::supported_board_size(game_tree)?;
+        let (figures, prisoners) = figures(game_tree, self.figure_moves, self.show_coordinates);
+        let record_info = RecordInfo::from_game_tree(&self.input, game_tree);
+        Ok(pages(&record_info, &figures, prisoners, self.figures_per_page, self.band_theme))
+    }
+
+    pub fn run(&self) -> Result<(), String> {
+        let content = convert::read_input(&self.input)?;
+        let record_format = RecordFormat::from_path(&self.input).unwrap_or(RecordFormat::Sgf);
+        let game_tree = record_format.parse_record(&content)
+            .map_err(|err| format!("{}: {}", self.input, err))?;
+        let pages = self.pages(&game_tree).map_err(|err| format!("{}: {}", self.input, err))?;
+        match self.kifu_format {
+            KifuFormat::Pdf => convert::write_output(&self.output, &pdf::pages_pdf(&pages, PAGE_SIZE)),
+            KifuFormat::Svg => {
+                if self.output == STDIO_PATH && pages.len() > 1 {
+                    return Err(format!("{} pages need an OUTPUT file", pages.len()));
+                }
+                for (page_idx, shapes) in pages.iter().enumerate() {
+                    let page = svg::shapes_svg(shapes, (0.0, 0.0, PAGE_SIZE.0, PAGE_SIZE.1));
+                    convert::write_output(&page_path(&self.output, page_idx, pages.len()), page.as_bytes())?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{band_theme::BandTheme, cli::args, game_tree::GameTree, info::RecordInfo};
+
+    use super::{figures as figures_of, page_path, pages, KifuExport, KifuFormat};
+
+    #[test]
+    pub fn test_figures() {
+        // black takes the ko at cb with 1, white takes back at bb with 4
+        // and black again at cb with 7
+        let game_tree = GameTree::from_sgf("(;SZ[9]AB[ba][ab][bc]AW[ca][bb][db][cc];B[cb];W[gg];B[gf];W[bb];B[ee];W[];B[cb];W[hh])").unwrap();
+        let (figures, prisoners) = figures_of(&game_tree, 8, false);
+        assert_eq!(figures.len(), 1);
+        assert_eq!(figures[0].caption(), "Figure 1 (1-8)");
+        assert_eq!(figures[0].notes(), vec!["4 at B8", "6 pass", "7 at 1"]);
+        assert_eq!(prisoners, (2, 1));
+
+        let (figures, _) = figures_of(&game_tree, 5, false);
+        assert_eq!(figures.len(), 2);
+        assert_eq!(figures[0].notes(), vec!["4 at B8"]);
+        assert_eq!(figures[0].diagram().move_number(2, 1), Some(1));
+        assert_eq!(figures[0].diagram().stone_state(1, 1), -1);
+        assert_eq!(figures[1].caption(), "Figure 2 (6-8)");
+        assert_eq!(figures[1].notes(), vec!["6 pass"]);
+        assert_eq!(figures[1].diagram().move_number(2, 1), Some(7));
+
+        let record_info = RecordInfo::from_game_tree("game.sgf", &game_tree);
+        let pages = pages(&record_info, &figures, prisoners, 1, BandTheme::Monochrome);
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].iter().any(|shape| matches!(shape, crate::diagram::Shape::Text { content, .. } if content == "Black - White")));
+
+        // white 2 on the stone of 1 is not played but keeps its number
+        let game_tree = GameTree::from_sgf("(;SZ[9];B[ee];W[ee];B[ff];W[ee])").unwrap();
+        let (figures, _) = figures_of(&game_tree, 8, false);
+        assert_eq!(figures[0].notes(), vec!["2 illegal", "4 illegal"]);
+        assert_eq!(figures[0].diagram().move_number(5, 5), Some(3));
+        let (figures, _) = figures_of(&game_tree, 2, false);
+        assert_eq!(figures[1].caption(), "Figure 2 (3-4)");
+        assert_eq!(figures[1].diagram().move_number(5, 5), Some(3));
+
+        // a record without moves still gets its position and header page
+        let game_tree = GameTree::from_sgf("(;SZ[9]AB[ee])").unwrap();
+        let (figures, prisoners) = figures_of(&game_tree, 8, false);
+        assert_eq!(figures.len(), 1);
+        assert_eq!(figures[0].caption(), "Figure 1");
+        assert_eq!(figures[0].diagram().stone_state(4, 4), 1);
+        let record_info = RecordInfo::from_game_tree("game.sgf", &game_tree);
+        assert_eq!(super::pages(&record_info, &figures, prisoners, 1, BandTheme::Monochrome).len(), 1);
+    }
+
+    #[test]
+    pub fn test_kifu_args() {
+        let kifu_export = KifuExport::from_args(&args("--moves=100 --per-page 3 sgf/test.sgf game.PDF")).unwrap();
+        assert_eq!((kifu_export.kifu_format, kifu_export.figure_moves, kifu_export.figures_per_page), (KifuFormat::Pdf, 100, 3));
+        assert_eq!(KifuExport::from_args(&args("--moves 0 a.sgf b.pdf")).err(), Some("not a count: 0".to_string()));
+        assert_eq!(page_path("out/game.svg", 1, 3), "out/game-2.svg");
+        assert_eq!(page_path("game.svg", 0, 1), "game.svg");
+    }
+}
@@ -1,122 +1,1062 @@
+use auto_replay::{AutoReplay, MIN_INTERVAL_MS, MAX_INTERVAL_MS};
+use band_theme::BandTheme;
+use autosave::{Autosave, AUTOSAVE_INTERVAL};
+use cli::CliCommand;
+use convert::RecordFormat;
+use prefs::Prefs;
 use config::Config;
-use game_tree::{GameTree, SgfReader, ReadFile};
-use go_band::{GoBand, Play};
+use coordinate::Coordinate;
+use game_clock::GameClock;
+use game_result::GameResult;
+use guess_stats::GuessStats;
+use problem_set::{ProblemSet, ProblemStatus};
+use file_browser::FileBrowser;
+use new_game::{NewGameField, NewGameForm};
+use edit_tool::EditTool;
+use export::DEFAULT_DIAGRAM_WIDTH;
+use game_tree::{GameTree, SgfNode, SgfKey};
+use go_band::{GoBand, Play, Player};
+use go_move::GoMove;
+use keymap::{KeyAction, Keymap};
+use markup::{Markup, MarkupKind};
 
-use iced::keyboard::KeyCode;
+use chrono::Utc;
 use iced::mouse::Button;
-use iced::{executor, Settings, Event, window, subscription, theme, application};
-use iced::widget::{canvas, container, row, text};
+use iced::{executor, Settings, Event, Subscription, window, subscription, theme, application, event, time};
+use iced::widget::{button, canvas, checkbox, column, container, pick_list, row, scrollable, slider, text, text_input};
 use iced::{
-    Application, Color, Command, Element, Length, Theme,
+    Application, Command, Element, Length, Theme,
 };
 
-use crate::game_tree::Parse;
-
 mod go_band;
 mod go_move;
 mod game_tree;
 mod config;
+mod markup;
+mod edit_tool;
+mod band_theme;
+mod band_layout;
+mod keymap;
+mod coordinate;
+mod game_clock;
+mod game_result;
+mod auto_replay;
+mod guess_stats;
+mod problem_set;
+mod file_browser;
+mod new_game;
+mod cli;
+mod prefs;
+mod autosave;
+mod convert;
+mod validate;
+mod info;
+mod diagram;
+mod svg;
+mod png;
+mod export;
+mod ascii;
+mod pdf;
+mod kifu;
 
-macro_rules! GoBand {
-    ($go_sz: expr, $settings: expr) => {
-        match $go_sz {
-            9 => GoBandView::<9>::run($settings),
-            13 => GoBandView::<13>::run($settings),
-            19 => GoBandView::<19>::run($settings),
-            _ => Ok(())
-        }
-    };
+use std::{path::{Path, PathBuf}, time::{Duration, Instant}};
+
+// sgf or json records, told apart by the extension
+fn load_game_tree(sgf_path: String) -> Option<GameTree> {
+    let content = std::fs::read_to_string(&sgf_path).ok()?;
+    RecordFormat::from_path(&sgf_path).unwrap_or(RecordFormat::Sgf).parse_record(&content).ok()
+}
+
+fn command_bar_id() -> text_input::Id {
+    text_input::Id::new("command_bar")
 }
 
 fn main() -> iced::Result {
-    let args: Vec<String> = std::env::args().collect();
-    let config = if args.len() == 0 {
-        Config::default()
-    } else {
-        Config::from(args)
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let prefs = Prefs::load();
+    let config = match cli::parse_args(&args, &prefs.config()) {
+        Ok(CliCommand::Play(config)) => config,
+        Ok(CliCommand::Convert(conversion)) => {
+            if let Err(err) = conversion.run() {
+                eprintln!("fastgo convert: {}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        },
+        Ok(CliCommand::Validate(validation)) => {
+            match validation.run() {
+                Ok(true) => return Ok(()),
+                Ok(false) => std::process::exit(1),
+                Err(err) => {
+                    eprintln!("fastgo validate: {}", err);
+                    std::process::exit(2);
+                },
+            }
+        },
+        Ok(CliCommand::Info(info)) => {
+            if let Err(err) = info.run() {
+                eprintln!("fastgo info: {}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        },
+        Ok(CliCommand::Diagram(diagram_export)) => {
+            if let Err(err) = diagram_export.run() {
+                eprintln!("fastgo diagram: {}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        },
+        Ok(CliCommand::Kifu(kifu_export)) => {
+            if let Err(err) = kifu_export.run() {
+                eprintln!("fastgo kifu: {}", err);
+                std::process::exit(1);
+            }
+            return Ok(());
+        },
+        Ok(CliCommand::Help(help)) => {
+            println!("{}", help);
+            return Ok(());
+        },
+        Err(err) => {
+            eprintln!("fastgo: {}\nSee 'fastgo --help'.", err);
+            std::process::exit(2);
+        },
     };
-    println!("config={:?}", config);
-    let go_sz = config.go_sz();
 
     let window_width = config.window_width();
     let window_height = config.window_height();
-    let mut settings = Settings::with_flags(config);
+    let mut settings = Settings::with_flags((config, prefs));
     settings.window.size = (window_width, window_height);
-    GoBand!(go_sz, settings)
+    // unsaved changes are asked about before closing
+    settings.exit_on_close_request = false;
+    GoBandView::run(settings)
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     EventOccurred(Event),
+    EditToolSelected(EditTool),
+    BandThemeSelected(BandTheme),
+    CommandChanged(String),
+    CommandSubmitted,
+    ClockTick(Instant),
+    ClockToggled,
+    GameEnded(GameResult),
+    ReplayToggled,
+    ReplayTick(Instant),
+    ReplaySpeedChanged(u32),
+    StopAtCommentsToggled(bool),
+    StopAtBranchesToggled(bool),
+    GuessModeToggled,
+    ProblemSelected(i32),
+    FileBrowserToggled,
+    FileBrowserOpened(PathBuf),
+    RecentFileOpened(String),
+    NewGameToggled,
+    NewGameFieldChanged(NewGameField, String),
+    NewGameSubmitted,
+    SaveRequested,
+    ExportRequested,
+    CopyAsciiRequested,
+    PasteAsciiRequested,
+    AsciiPasted(Option<String>),
+    AutosaveTick(Instant),
+    RecoveryAnswered(bool),
+    UnsavedSaving,
+    UnsavedDiscarding,
+    UnsavedCancelled,
 }
 
-struct GoBandView<const D: usize> {
+struct GoBandView {
+    config: Config,
     window_width: u32,
     window_height: u32,
     scale_factor: f32,
-    go_band: GoBand<D>,
+    go_band: GoBand,
     game_tree: GameTree,
     move_count: i32,
+    edit_tool: EditTool,
+    show_move_numbers: bool,
+    number_from_move: Option<usize>,
+    keymap: Keymap,
+    command: String,
+    command_feedback: String,
+    game_clock: Option<GameClock>,
+    game_result: Option<GameResult>,
+    auto_replay: AutoReplay,
+    // Some while guessing the next moves of the loaded game
+    guess_stats: Option<GuessStats>,
+    problem_set: Option<ProblemSet>,
+    // Some while the file browser is shown
+    file_browser: Option<FileBrowser>,
+    // preferences saved between launches, without command line overrides
+    prefs: Prefs,
+    // the window size changed since the preferences were saved, they are
+    // written on closing rather than on every resize
+    prefs_unsaved: bool,
+    // Some while the new game form is shown
+    new_game_form: Option<NewGameForm>,
+    // the file the game was opened from or saved to, empty for a new game
+    sgf_path: String,
+    autosave: Autosave,
+    // a game left by an earlier session, kept until the user restores or discards it
+    recovery_found: bool,
+    // what asked to close or replace the game, run once unsaved changes
+    // are saved or given up
+    unsaved_request: Option<Message>,
 }
 
-impl<const D: usize> GoBandView<D> {
+impl GoBandView {
     fn clear_band_view(&self) {
         self.go_band.clear();
     }
+
+    fn refresh_markups(&mut self) {
+        let path_nodes = GameTree::get_path_nodes_until(&self.game_tree, self.move_count - 1);
+        let node_props = GameTree::get_node_props(&self.game_tree, self.move_count - 1);
+        self.go_band.set_markups(Markup::from_sgf_nodes(&node_props), Markup::dimmed_points(&path_nodes));
+        // numbering follows the variation unless a start move was picked
+        let move_number_start = if self.show_move_numbers {
+            Some(self.number_from_move.unwrap_or(GameTree::get_variation_start(&self.game_tree)))
+        } else {
+            None
+        };
+        self.go_band.set_move_number_start(move_number_start);
+        self.clear_band_view();
+    }
+
+    // rebuilds the band from the game tree: setup stones and moves of the
+    // selected variation up to the current move
+    fn replay_band(&mut self) {
+        self.go_band.reset();
+        for sgf_node in GameTree::get_path_nodes_until(&self.game_tree, self.move_count - 1) {
+            self.go_band.apply_sgf_node(&sgf_node);
+        }
+        self.go_band.set_next_stone_pos(self.variation_hints());
+        self.refresh_markups();
+    }
+
+    // the other variations of the current move, shown as hints
+    fn variation_hints(&self) -> Vec<(i32, i32, i8, bool)> {
+        if self.move_count == 0 {
+            return vec![];
+        }
+        GameTree::get_moves(&self.game_tree, self.move_count - 1)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(go_move, selected)| !selected && !go_move.is_pass())
+            .map(|(go_move, selected)| {
+                let (x, y, state) = go_move.move_pos();
+                (x as i32, y as i32, state, selected)
+            })
+            .collect()
+    }
+
+    fn path_move_count(&self) -> i32 {
+        GameTree::get_path_nodes(&self.game_tree).iter().filter(|sgf_node| sgf_node.is_move()).count() as i32
+    }
+
+    // goes forward move by move so captures and hints stay right, going
+    // back replays the band
+    fn jump_to(&mut self, move_count: i32) {
+        let move_count = move_count.clamp(0, self.path_move_count());
+        if move_count < self.move_count {
+            self.move_count = move_count;
+            self.replay_band();
+        } else {
+            while self.move_count < move_count {
+                let prev_move_count = self.move_count;
+                self.step_forward();
+                if self.move_count == prev_move_count {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn select_variation(&mut self, step: i32) {
+        if GameTree::select_variation(&self.game_tree, self.move_count - 1, step) {
+            self.move_count = self.move_count.min(self.path_move_count());
+            self.replay_band();
+        }
+    }
+
+    // plays the next move of the selected variation, the first moves of the
+    // other variations at a branch point are shown as hints
+    fn step_forward(&mut self) {
+        let sgf_moves = GameTree::get_moves(&self.game_tree, self.move_count);
+        match sgf_moves {
+            Some(moves) if moves.iter().any(|go_move| go_move.1) => {
+                self.move_count += 1;
+                for (go_move, selected) in moves {
+                    let (x, y, state) = go_move.move_pos();
+                    if selected {
+                        let player = if state == 1 { Player::BLACK } else { Player::WHITE };
+                        self.go_band.set_current_player(player);
+                        if go_move.is_pass() {
+                            self.go_band.pass();
+                        } else {
+                            self.go_band.set_stone_pos(x as i32, y as i32);
+                            self.go_band.forward(true);
+                        }
+                    }
+                }
+                for sgf_node in GameTree::get_node_props(&self.game_tree, self.move_count - 1) {
+                    self.go_band.apply_sgf_node(&sgf_node);
+                }
+                self.go_band.set_next_stone_pos(self.variation_hints());
+                self.refresh_markups();
+            },
+            _ => {},
+        }
+    }
+
+    fn step_back(&mut self) {
+        if self.move_count > 0 {
+            self.move_count -= 1;
+            self.replay_band();
+        }
+    }
+
+    // plays or edits at the previewed point, for clicks and the keyboard cursor alike
+    fn click_band(&mut self) {
+        if self.problem_set.is_some() && self.edit_tool == EditTool::Play {
+            self.solve_move();
+            return;
+        }
+        if self.guess_stats.is_some() && self.edit_tool == EditTool::Play {
+            self.guess_move();
+            return;
+        }
+        if self.is_locked() {
+            return;
+        }
+        if self.edit_tool != EditTool::Play {
+            if self.go_band.stone_pos() == self.go_band.mouse_preview() {
+                self.edit_band();
+            }
+        } else if let Some(go_move) = self.go_band.forward(false) {
+            self.record_go_move(go_move);
+        }
+    }
+
+    fn record_go_move(&mut self, go_move: GoMove) {
+        self.move_count += 1;
+        let move_id = go_move.move_id();
+        let res_move_id = if self.move_count == move_id as i32 + 1 {
+            move_id as i32
+        } else {
+            move_id as i32 - 1
+        };
+        let (_, _, state) = go_move.move_pos();
+        GameTree::record_move(&mut self.game_tree, res_move_id, go_move);
+        self.clock_move_played(state);
+        self.mark_dirty();
+        self.autosave();
+        self.go_band.set_next_stone_pos(self.variation_hints());
+        self.refresh_markups();
+    }
+
+    // settles the clock of the player who moved and records the time left
+    // in the move's node, the first move starts the clock
+    fn clock_move_played(&mut self, state: i8) {
+        let game_clock = match self.game_clock.as_mut() {
+            Some(game_clock) if game_clock.flagged().is_none() => game_clock,
+            _ => return,
+        };
+        game_clock.tick(Instant::now());
+        game_clock.move_played(state);
+        if game_clock.running().is_none() {
+            game_clock.start(-state);
+        }
+        let (time_key, overtime_key) = if state == 1 { (SgfKey::BL, SgfKey::OB) } else { (SgfKey::WL, SgfKey::OW) };
+        let time_left = format!("{:.1}", game_clock.time_left(state).as_secs_f32());
+        let overtime_left = game_clock.overtime_left(state);
+        let move_id = self.move_count - 1;
+        GameTree::set_node_prop(&mut self.game_tree, move_id, SgfNode::new(time_key, time_left));
+        if let Some(overtime_left) = overtime_left {
+            GameTree::set_node_prop(&mut self.game_tree, move_id, SgfNode::new(overtime_key, overtime_left.to_string()));
+        }
+    }
+
+    fn clock_tick(&mut self, now: Instant) {
+        let game_clock = match self.game_clock.as_mut() {
+            Some(game_clock) => game_clock,
+            None => return,
+        };
+        game_clock.tick(now);
+        if let Some(flagged) = game_clock.flagged() {
+            self.end_game(GameResult::Time(-flagged));
+        }
+    }
+
+    // scores the previewed point against the next move of the selected
+    // variation, then reveals that move
+    fn guess_move(&mut self) {
+        let expected = GameTree::get_moves(&self.game_tree, self.move_count)
+            .and_then(|moves| moves.into_iter().find(|(_, selected)| *selected))
+            .map(|(go_move, _)| go_move);
+        let expected = match expected {
+            Some(expected) => expected,
+            None => {
+                self.command_feedback = "no moves left to guess".to_string();
+                return;
+            },
+        };
+        let (expected_x, expected_y, expected_state) = expected.move_pos();
+        if expected.is_pass() {
+            self.command_feedback = "a pass was played".to_string();
+            self.step_forward();
+            return;
+        }
+        // only legal moves of the player to move count as guesses
+        let player = if expected_state == 1 { Player::BLACK } else { Player::WHITE };
+        self.go_band.set_current_player(player);
+        match self.go_band.forward(false) {
+            Some(_) => self.go_band.back(),
+            None => return,
+        }
+        let (guess_x, guess_y) = self.go_band.mouse_preview();
+        if let Some(guess_stats) = self.guess_stats.as_mut() {
+            let guess_outcome = guess_stats.record((guess_x as usize, guess_y as usize), (expected_x, expected_y));
+            self.command_feedback = guess_outcome.to_string();
+        }
+        self.step_forward();
+    }
+
+    // replaces the game with the one in `sgf_path`, failures are reported
+    // in the feedback line and leave the current game alone
+    fn open_sgf(&mut self, sgf_path: String) {
+        let game_tree = match load_game_tree(sgf_path.clone()) {
+            Some(game_tree) => game_tree,
+            None => {
+                self.command_feedback = format!("cannot read {}", sgf_path);
+                return;
+            },
+        };
+        if let Err(err) = GameTree::supported_board_size(&game_tree) {
+            self.command_feedback = format!("{}: {}", sgf_path, err);
+            return;
+        }
+        self.open_game_tree(game_tree);
+        self.sgf_path = sgf_path.clone();
+        let mut recent_files = self.prefs.recent_files();
+        file_browser::add_recent_file(&mut recent_files, sgf_path);
+        self.prefs.set_recent_files(recent_files);
+        self.save_prefs();
+        self.file_browser = None;
+    }
+
+    // starts over with `game_tree`, nothing of the previous game is kept
+    fn open_game_tree(&mut self, game_tree: GameTree) {
+        self.sgf_path = String::default();
+        self.go_band.set_dim(GameTree::board_size(&game_tree));
+        self.game_tree = game_tree;
+        self.move_count = 0;
+        self.number_from_move = None;
+        self.game_result = None;
+        self.game_clock = self.game_clock.as_ref().map(|game_clock| GameClock::new(game_clock.time_control()));
+        self.auto_replay.set_running(false);
+        if self.guess_stats.is_some() {
+            self.guess_stats = Some(GuessStats::default());
+        }
+        self.command.clear();
+        self.command_feedback = String::default();
+        self.replay_band();
+    }
+
+    // a fresh game from the settings of the form, which stays open with
+    // the reason when a value is invalid
+    fn start_new_game(&mut self) {
+        let new_game_form = match self.new_game_form.as_ref() {
+            Some(new_game_form) => new_game_form,
+            None => return,
+        };
+        match new_game_form.to_config(&self.config) {
+            Ok(config) => {
+                let (go_sz, go_km, go_pb, go_pw, go_ha) = (config.go_sz(), config.go_km(), config.go_pb(), config.go_pw(), config.go_ha());
+                self.config = config;
+                self.change_pref(|config| *config = config.for_new_game(go_sz, go_km, go_pb.clone(), go_pw.clone(), go_ha));
+                self.problem_set = None;
+                self.new_game_form = None;
+                self.open_game_tree(GameTree::from(self.config.clone()));
+                self.write_time_control();
+            },
+            Err(err) => self.command_feedback = err,
+        }
+    }
+
+    // a setting the user changed applies to this session and is saved for
+    // the next ones
+    fn change_pref(&mut self, change: impl Fn(&mut Config)) {
+        change(&mut self.config);
+        self.prefs.update_config(change);
+        self.save_prefs();
+    }
+
+    fn save_prefs(&mut self) {
+        self.prefs_unsaved = false;
+        if let Err(err) = self.prefs.save() {
+            eprintln!("cannot save preferences: {}", err);
+        }
+    }
+
+    fn mark_dirty(&mut self) {
+        self.autosave.mark_dirty();
+    }
+
+    // writes the game to the recovery file, unless one of an earlier
+    // session still waits for the user
+    fn autosave(&mut self) {
+        if self.recovery_found || !self.autosave.pending() {
+            return;
+        }
+        if let Err(err) = self.autosave.save(&self.game_tree) {
+            eprintln!("cannot autosave: {}", err);
+        }
+    }
+
+    fn clear_recovery(&mut self) {
+        // the file of the earlier session stays until the user answers for it
+        if self.recovery_found {
+            self.autosave.mark_clean();
+            return;
+        }
+        if let Err(err) = self.autosave.clear() {
+            eprintln!("cannot remove the recovery file: {}", err);
+        }
+    }
+
+    fn answer_recovery(&mut self, restore: bool) {
+        let recovery_path = match self.autosave.found() {
+            Some(recovery_path) => recovery_path,
+            None => {
+                self.recovery_found = false;
+                return;
+            },
+        };
+        if !restore {
+            // the game on the band keeps its own unsaved changes
+            self.recovery_found = false;
+            if let Err(err) = std::fs::remove_file(&recovery_path) {
+                eprintln!("cannot remove the recovery file: {}", err);
+            }
+            return;
+        }
+        // an unreadable recovery file is left for the user to discard
+        match load_game_tree(recovery_path.to_string_lossy().to_string()) {
+            Some(game_tree) => {
+                self.recovery_found = false;
+                self.problem_set = None;
+                self.open_game_tree(game_tree);
+                // it is only in the recovery file still
+                self.mark_dirty();
+                self.autosave();
+                self.command_feedback = "restored the unsaved game".to_string();
+            },
+            None => self.command_feedback = format!("cannot read {}", recovery_path.display()),
+        }
+    }
+
+    // whether `message` closes the window or replaces the game
+    fn drops_game(message: &Message) -> bool {
+        match message {
+            Message::FileBrowserOpened(path) => !path.is_dir(),
+            Message::RecentFileOpened(_) | Message::NewGameSubmitted | Message::AsciiPasted(_) => true,
+            Message::RecoveryAnswered(restore) => *restore,
+            Message::EventOccurred(Event::Window(window::Event::CloseRequested | window::Event::FileDropped(_))) => true,
+            _ => false,
+        }
+    }
+
+    // runs the message that waited for the unsaved changes to be saved or
+    // given up
+    fn resume_unsaved_request(&mut self) -> Command<Message> {
+        match self.unsaved_request.take() {
+            Some(message) => self.update(message),
+            None => Command::none(),
+        }
+    }
+
+    // a new game gets a dated file in the working directory, a record opened
+    // from elsewhere a `.fastgo` file beside it, as writing it back could lose
+    // what fastgo does not read (other games of a collection, say)
+    fn save_path(&self) -> String {
+        if self.sgf_path.is_empty() {
+            let file_name = format!("fastgo-{}.sgf", Utc::now().format("%Y%m%d-%H%M%S"));
+            return std::env::current_dir().unwrap_or_default().join(file_name).to_string_lossy().to_string();
+        }
+        let path = Path::new(&self.sgf_path);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        if stem.ends_with(".fastgo") || stem.starts_with("fastgo-") {
+            return self.sgf_path.clone();
+        }
+        let extension = path.extension().map_or("sgf".to_string(), |extension| extension.to_string_lossy().to_string());
+        path.with_file_name(format!("{}.fastgo.{}", stem, extension)).to_string_lossy().to_string()
+    }
+
+    fn save_game(&mut self) -> bool {
+        let sgf_path = self.save_path();
+        let record_format = RecordFormat::from_path(&sgf_path).unwrap_or(RecordFormat::Sgf);
+        match std::fs::write(&sgf_path, record_format.format_record(&self.game_tree)) {
+            Ok(_) => {
+                self.clear_recovery();
+                self.command_feedback = format!("saved {}", sgf_path);
+                self.sgf_path = sgf_path;
+                true
+            },
+            Err(err) => {
+                self.command_feedback = format!("cannot save {}: {}", sgf_path, err);
+                false
+            },
+        }
+    }
+
+    // the position on show as an svg beside the game record, or in the
+    // working directory for a new game
+    fn export_diagram(&mut self) {
+        let file_name = if self.sgf_path.is_empty() {
+            format!("fastgo-{}-{}.svg", Utc::now().format("%Y%m%d-%H%M%S"), self.move_count)
+        } else {
+            let stem = Path::new(&self.sgf_path).file_stem().unwrap_or_default().to_string_lossy().to_string();
+            format!("{}-{}.svg", stem, self.move_count)
+        };
+        let svg_path = match Path::new(&self.sgf_path).parent() {
+            Some(dir) if !self.sgf_path.is_empty() => dir.join(file_name),
+            _ => std::env::current_dir().unwrap_or_default().join(file_name),
+        };
+        let diagram = self.go_band.diagram().printable();
+        let svg = svg::diagram_svg(&diagram, self.go_band.band_theme(), DEFAULT_DIAGRAM_WIDTH);
+        self.command_feedback = match std::fs::write(&svg_path, svg) {
+            Ok(_) => format!("exported {}", svg_path.display()),
+            Err(err) => format!("cannot export {}: {}", svg_path.display(), err),
+        };
+    }
+
+    // a diagram from the clipboard becomes a new game, the current one stays
+    // when it cannot be read
+    fn paste_ascii(&mut self, content: &str) {
+        let game_tree = match ascii::parse_ascii(content) {
+            Ok(game_tree) => game_tree,
+            Err(err) => {
+                self.command_feedback = format!("cannot paste the diagram: {}", err);
+                return;
+            },
+        };
+        if let Err(err) = GameTree::supported_board_size(&game_tree) {
+            self.command_feedback = format!("cannot paste the diagram: {}", err);
+            return;
+        }
+        self.problem_set = None;
+        self.open_game_tree(game_tree);
+        self.mark_dirty();
+        self.command_feedback = "pasted the diagram as a new game".to_string();
+    }
+
+    fn write_time_control(&mut self) {
+        if let Some(time_control) = self.config.time_control() {
+            GameTree::set_node_prop(&mut self.game_tree, -1, SgfNode::new(SgfKey::TM, time_control.main_time().as_secs().to_string()));
+            if let Some(overtime) = time_control.overtime() {
+                GameTree::set_node_prop(&mut self.game_tree, -1, SgfNode::new(SgfKey::OT, overtime));
+            }
+        }
+    }
+
+    fn browse(&mut self, dir: &Path) {
+        match FileBrowser::open(dir) {
+            Ok(file_browser) => self.file_browser = Some(file_browser),
+            Err(err) => self.command_feedback = err,
+        }
+    }
+
+    // opens the problem `step` problems on from the current one, 0 retries it
+    fn select_problem(&mut self, step: i32) {
+        let problem_set = match self.problem_set.as_mut() {
+            Some(problem_set) => problem_set,
+            None => return,
+        };
+        problem_set.select(step);
+        let sgf_path = problem_set.current_path();
+        match load_game_tree(sgf_path.clone()).filter(|game_tree| GameTree::supported_board_size(game_tree).is_ok()) {
+            Some(game_tree) => {
+                self.go_band.set_dim(GameTree::board_size(&game_tree));
+                self.game_tree = game_tree;
+                self.move_count = 0;
+                self.replay_band();
+                // the solver plays the colour of the first move in the tree
+                if let Some(first_move) = GameTree::get_moves(&self.game_tree, 0)
+                    .and_then(|moves| moves.into_iter().next()) {
+                    let (_, _, state) = first_move.0.move_pos();
+                    self.go_band.set_current_player(if state == 1 { Player::BLACK } else { Player::WHITE });
+                }
+                self.go_band.set_next_stone_pos(vec![]);
+                self.command_feedback = String::default();
+            },
+            None => self.command_feedback = format!("cannot read {}", sgf_path),
+        }
+    }
+
+    // plays the solver's move if the problem knows it and answers with the
+    // opponent's reply from the tree, anything else is wrong
+    fn solve_move(&mut self) {
+        let go_move = match self.go_band.forward(false) {
+            Some(go_move) => go_move,
+            None => return,
+        };
+        let on_tree = GameTree::get_moves(&self.game_tree, self.move_count)
+            .unwrap_or_default()
+            .iter()
+            .any(|(tree_move, _)| tree_move.move_pos() == go_move.move_pos());
+        self.go_band.back();
+        if !on_tree {
+            self.finish_problem(ProblemStatus::Failed);
+            return;
+        }
+        // selects the variation starting with the move, the tree is left as it is
+        GameTree::record_move(&mut self.game_tree, self.move_count, go_move);
+        self.step_forward();
+        self.go_band.set_next_stone_pos(vec![]);
+        if let Some(problem_status) = self.problem_status() {
+            self.finish_problem(problem_status);
+            return;
+        }
+        self.step_forward();
+        self.go_band.set_next_stone_pos(vec![]);
+        if let Some(problem_status) = self.problem_status() {
+            self.finish_problem(problem_status);
+        }
+    }
+
+    // solved once a correct comment is reached, failed at the end of a line
+    // without one
+    fn problem_status(&self) -> Option<ProblemStatus> {
+        let correct = GameTree::get_node_props(&self.game_tree, self.move_count - 1)
+            .iter()
+            .any(|sgf_node| sgf_node.node_key() == SgfKey::C && ProblemSet::is_correct_comment(&sgf_node.node_val()));
+        if correct {
+            Some(ProblemStatus::Solved)
+        } else if GameTree::get_moves(&self.game_tree, self.move_count).is_none() {
+            Some(ProblemStatus::Failed)
+        } else {
+            None
+        }
+    }
+
+    fn finish_problem(&mut self, problem_status: ProblemStatus) {
+        if let Some(problem_set) = self.problem_set.as_mut() {
+            problem_set.set_status(problem_status);
+        }
+        self.command_feedback = match problem_status {
+            ProblemStatus::Solved => "Correct!".to_string(),
+            _ => "Wrong, try again".to_string(),
+        };
+    }
+
+    // one step of the auto-replay
+    fn replay_tick(&mut self) {
+        let move_count = self.move_count;
+        self.step_forward();
+        if self.auto_replay.should_stop(&self.game_tree, move_count, self.move_count) {
+            self.auto_replay.set_running(false);
+        }
+    }
+
+    // once a result is reached the band only serves for review
+    fn is_locked(&self) -> bool {
+        self.game_result.is_some()
+    }
+
+    // writes the result and the end date to the root node, the start date
+    // written when the game was created is kept
+    fn end_game(&mut self, game_result: GameResult) {
+        if self.is_locked() {
+            return;
+        }
+        GameTree::set_node_prop(&mut self.game_tree, -1, SgfNode::new(SgfKey::RE, game_result.to_sgf()));
+        let end_date = format!("{}", Utc::now().format("%Y-%m-%d"));
+        let date = GameTree::get_node_props(&self.game_tree, -1)
+            .into_iter()
+            .find(|sgf_node| sgf_node.node_key() == SgfKey::DT)
+            .map(|sgf_node| sgf_node.node_val());
+        let date = match date {
+            Some(date) if !date.is_empty() => {
+                // older records carry the time of day after the start date
+                let date = date.split(' ').next().unwrap_or_default().to_string();
+                if date.split(',').any(|date| date == end_date) { date } else { format!("{},{}", date, end_date) }
+            },
+            _ => end_date,
+        };
+        GameTree::set_node_prop(&mut self.game_tree, -1, SgfNode::new(SgfKey::DT, date));
+        if let Some(game_clock) = self.game_clock.as_mut() {
+            game_clock.pause();
+        }
+        self.game_result = Some(game_result);
+        self.mark_dirty();
+    }
+
+    // plays or jumps to what was typed into the command bar, the feedback
+    // line tells what happened
+    fn run_command(&mut self) {
+        let coordinate = match Coordinate::parse(&self.command, self.go_band.dim()) {
+            Ok(coordinate) => coordinate,
+            Err(err) => {
+                self.command_feedback = err;
+                return;
+            },
+        };
+        let move_count = self.move_count;
+        match coordinate {
+            Coordinate::Point(..) | Coordinate::Pass if self.is_locked() => {
+                self.command_feedback = "the game is over".to_string();
+                return;
+            },
+            Coordinate::Point(x, y) => {
+                self.go_band.set_cursor(x as i32, y as i32);
+                self.click_band();
+                if self.edit_tool == EditTool::Play && self.move_count == move_count {
+                    self.command_feedback = format!("cannot play at {}", self.command.trim());
+                    return;
+                }
+            },
+            Coordinate::MoveNumber(move_number) => {
+                let path_move_count = self.path_move_count();
+                if move_number as i32 > path_move_count {
+                    self.command_feedback = format!("only {} moves in this variation", path_move_count);
+                    return;
+                }
+                self.jump_to(move_number as i32);
+            },
+            Coordinate::Pass => self.key_pressed(KeyAction::Pass),
+        }
+        self.command_feedback = String::default();
+        self.command.clear();
+    }
+
+    fn key_pressed(&mut self, key_action: KeyAction) {
+        match key_action {
+            KeyAction::Back => self.step_back(),
+            KeyAction::Forward => self.step_forward(),
+            KeyAction::First => self.jump_to(0),
+            KeyAction::Last => self.jump_to(i32::MAX),
+            KeyAction::BackTen => self.jump_to(self.move_count - 10),
+            KeyAction::ForwardTen => self.jump_to(self.move_count + 10),
+            KeyAction::PrevVariation => self.select_variation(-1),
+            KeyAction::NextVariation => self.select_variation(1),
+            KeyAction::Pass => {
+                if self.edit_tool == EditTool::Play && !self.is_locked() {
+                    let go_move = self.go_band.pass();
+                    self.record_go_move(go_move);
+                }
+            },
+            KeyAction::CursorLeft => self.go_band.move_preview(-1, 0),
+            KeyAction::CursorRight => self.go_band.move_preview(1, 0),
+            KeyAction::CursorUp => self.go_band.move_preview(0, -1),
+            KeyAction::CursorDown => self.go_band.move_preview(0, 1),
+            KeyAction::PlaceStone => {
+                // the mouse may have left the band since the cursor last moved
+                self.go_band.move_preview(0, 0);
+                self.click_band();
+            },
+            KeyAction::ToggleMoveNumbers => {
+                self.show_move_numbers = !self.show_move_numbers;
+                let show_move_numbers = self.show_move_numbers;
+                self.change_pref(|config| config.set_show_move_numbers(show_move_numbers));
+                self.refresh_markups();
+            },
+            KeyAction::NumberFromMove => {
+                // number from the next move, pressing again goes back to the variation start
+                let next_move = self.move_count as usize;
+                self.number_from_move = if self.number_from_move == Some(next_move) {
+                    None
+                } else {
+                    Some(next_move)
+                };
+                self.show_move_numbers = true;
+                self.refresh_markups();
+            },
+            KeyAction::NextTheme => {
+                let band_theme = self.go_band.band_theme().next();
+                self.go_band.set_band_theme(band_theme);
+                self.change_pref(|config| config.set_band_theme(band_theme));
+                self.clear_band_view();
+            },
+            KeyAction::ToggleCoordinates => {
+                let show_coordinates = self.go_band.show_coordinates();
+                self.go_band.set_show_coordinates(!show_coordinates);
+                self.change_pref(|config| config.set_show_coordinates(!show_coordinates));
+                self.clear_band_view();
+            },
+            // needs a command to focus the bar, see `update`
+            KeyAction::FocusCommand => {},
+            KeyAction::ToggleAutoReplay => {
+                let running = self.auto_replay.running();
+                self.auto_replay.set_running(!running);
+            },
+            KeyAction::ReplayFaster => {
+                self.auto_replay.faster();
+                let interval_ms = self.auto_replay.interval_ms();
+                self.change_pref(|config| config.set_replay_interval_ms(interval_ms));
+            },
+            KeyAction::ReplaySlower => {
+                self.auto_replay.slower();
+                let interval_ms = self.auto_replay.interval_ms();
+                self.change_pref(|config| config.set_replay_interval_ms(interval_ms));
+            },
+            KeyAction::ToggleGuessMode => {
+                // every round of guessing starts with fresh statistics
+                self.guess_stats = match self.guess_stats {
+                    Some(_) => None,
+                    None => Some(GuessStats::default()),
+                };
+                self.command_feedback = String::default();
+            },
+        }
+    }
+
+    // applies the selected edit tool at the previewed point, writing the
+    // property into the node of the current move
+    fn edit_band(&mut self) {
+        let sgf_key = match self.edit_tool.sgf_key() {
+            Some(sgf_key) => sgf_key,
+            None => return,
+        };
+        let (x, y) = self.go_band.mouse_preview();
+        let pos = (x as usize, y as usize);
+        let move_id = self.move_count - 1;
+        let keys = if self.edit_tool.is_setup() {
+            vec![SgfKey::AB, SgfKey::AW, SgfKey::AE]
+        } else {
+            vec![SgfKey::CR, SgfKey::MA, SgfKey::SQ, SgfKey::TR, SgfKey::LB]
+        };
+        let removed_keys = GameTree::remove_node_point(&mut self.game_tree, move_id, &keys, pos);
+        // clicking a point again with the same tool takes the property off
+        if !removed_keys.contains(&sgf_key) {
+            let node_val = match self.edit_tool {
+                EditTool::Letter | EditTool::Number => {
+                    let labels = Markup::from_sgf_nodes(&GameTree::get_node_props(&self.game_tree, move_id))
+                        .into_iter()
+                        .filter_map(|markup| match markup.kind() {
+                            MarkupKind::Label(label) => Some(label),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
+                    self.edit_tool.next_label(&labels)
+                        .map(|label| format!("{}:{}", SgfNode::format_point(pos.0, pos.1), label))
+                },
+                _ => Some(SgfNode::format_point(pos.0, pos.1)),
+            };
+            if let Some(node_val) = node_val {
+                GameTree::add_node_prop(&mut self.game_tree, move_id, SgfNode::new(sgf_key, node_val));
+            }
+        }
+        self.mark_dirty();
+        if self.edit_tool.is_setup() {
+            self.replay_band();
+        } else {
+            self.refresh_markups();
+        }
+    }
 }
 
-impl<const D: usize> Application for GoBandView<D> {
+impl Application for GoBandView {
     type Executor = executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = Config;
+    type Flags = (Config, Prefs);
 
-    fn new(config: Config) -> (Self, Command<Message>) {
+    fn new((config, prefs): (Config, Prefs)) -> (Self, Command<Message>) {
         let window_width = config.window_width();
         let window_height = config.window_height();
         let scale_factor = config.scale_factor();
         let go_band_width = (window_width as f32 * scale_factor) as u32;
-        let go_sz = config.go_sz();
         let sgf_path = config.sgf_path();
+        let show_move_numbers = config.show_move_numbers();
+        let show_coordinates = config.show_coordinates();
+        let band_theme = config.band_theme();
+        let keymap = config.keymap();
+        let time_control = config.time_control();
+        let replay_interval_ms = config.replay_interval_ms();
 
-        let game_tree = if let Ok(sgf_reader) = SgfReader::read_from(sgf_path) {
-            let sgf_tokens = sgf_reader.parse();
-            let game_tree = GameTree::from_sgf_tokens(&sgf_tokens, 0, sgf_tokens.len() - 1, true, true);
-            match game_tree {
-                Some(game_tree) => game_tree,
-                None => GameTree::from(config)
-            }
-        } else {
-            GameTree::from(config)
+        let problem_set = match config.problem_dir().as_str() {
+            "" => None,
+            problem_dir => ProblemSet::from_dir(problem_dir)
+                .map_err(|err| eprintln!("ignoring --tsumego: {}", err))
+                .ok(),
         };
-        (
-            GoBandView {
-                window_width,
+
+        let game_tree = load_game_tree(sgf_path.clone())
+            .filter(|game_tree| GameTree::supported_board_size(game_tree).is_ok());
+        let (game_tree, sgf_path) = match game_tree {
+            Some(game_tree) => (game_tree, sgf_path),
+            None => (GameTree::from(config.clone()), String::default()),
+        };
+        let go_sz = GameTree::board_size(&game_tree);
+        let autosave = Autosave::new(Autosave::recovery_path());
+        let recovery_found = autosave.found().is_some();
+        let mut go_band_view = GoBandView {
+            config,
+            window_width,
+            window_height,
+            scale_factor,
+            go_band: GoBand::new(
+                go_sz,
+                go_band_width,
                 window_height,
-                scale_factor,
-                go_band: GoBand::new(
-                    go_band_width,
-                    window_height,
-                    0,
-                    0,
-                    go_sz as usize,
-                ),
-                game_tree,
-                move_count: 0,
-            },
+                0,
+                0,
+            ),
+            game_tree,
+            move_count: 0,
+            edit_tool: EditTool::Play,
+            show_move_numbers,
+            number_from_move: None,
+            keymap,
+            command: String::default(),
+            command_feedback: String::default(),
+            game_clock: time_control.map(GameClock::new),
+            game_result: None,
+            auto_replay: AutoReplay::new(replay_interval_ms),
+            guess_stats: None,
+            problem_set,
+            file_browser: None,
+            prefs,
+            prefs_unsaved: false,
+            new_game_form: None,
+            sgf_path,
+            autosave,
+            recovery_found,
+            unsaved_request: None,
+        };
+        // an opened record keeps the time settings it was played with
+        if go_band_view.sgf_path.is_empty() {
+            go_band_view.write_time_control();
+        }
+        go_band_view.go_band.set_show_coordinates(show_coordinates);
+        go_band_view.go_band.set_band_theme(band_theme);
+        go_band_view.replay_band();
+        go_band_view.select_problem(0);
+        (
+            go_band_view,
             Command::none(),
         )
     }
 
     fn style(&self) -> theme::Application {
-        fn dark_background(_theme: &Theme) -> application::Appearance {
-            application::Appearance {
-                background_color: Color::BLACK,
-                text_color: Color::WHITE,
+        struct BandBackground(BandTheme);
+
+        impl application::StyleSheet for BandBackground {
+            type Style = Theme;
+
+            fn appearance(&self, _style: &Self::Style) -> application::Appearance {
+                application::Appearance {
+                    background_color: self.0.background_color(),
+                    text_color: self.0.text_color(),
+                }
             }
         }
 
-        theme::Application::from(dark_background as fn(&Theme) -> _)
+        theme::Application::Custom(Box::new(BandBackground(self.go_band.band_theme())))
     }
 
     fn title(&self) -> String {
@@ -124,9 +1064,162 @@ impl<const D: usize> Application for GoBandView<D> {
     }
 
     fn update(&mut self, message: Self::Message) -> iced::Command<Self::Message> {
+        // unsaved changes are asked about first, the recovery file stays
+        // until then
+        if GoBandView::drops_game(&message) && self.autosave.dirty() {
+            self.unsaved_request = Some(message);
+            return Command::none();
+        }
         match message {
+            Message::EditToolSelected(edit_tool) => {
+                self.edit_tool = edit_tool;
+                Command::none()
+            },
+            Message::BandThemeSelected(band_theme) => {
+                self.go_band.set_band_theme(band_theme);
+                self.change_pref(|config| config.set_band_theme(band_theme));
+                self.clear_band_view();
+                Command::none()
+            },
+            Message::CommandChanged(command) => {
+                self.command = command;
+                Command::none()
+            },
+            Message::CommandSubmitted => {
+                self.run_command();
+                Command::none()
+            },
+            Message::ClockTick(now) => {
+                self.clock_tick(now);
+                Command::none()
+            },
+            Message::ReplayToggled => {
+                self.key_pressed(KeyAction::ToggleAutoReplay);
+                Command::none()
+            },
+            Message::ReplayTick(_) => {
+                self.replay_tick();
+                Command::none()
+            },
+            Message::ReplaySpeedChanged(interval_ms) => {
+                self.auto_replay.set_interval_ms(interval_ms);
+                let interval_ms = self.auto_replay.interval_ms();
+                self.change_pref(|config| config.set_replay_interval_ms(interval_ms));
+                Command::none()
+            },
+            Message::StopAtCommentsToggled(stop_at_comments) => {
+                self.auto_replay.set_stop_at_comments(stop_at_comments);
+                Command::none()
+            },
+            Message::StopAtBranchesToggled(stop_at_branches) => {
+                self.auto_replay.set_stop_at_branches(stop_at_branches);
+                Command::none()
+            },
+            Message::GuessModeToggled => {
+                self.key_pressed(KeyAction::ToggleGuessMode);
+                Command::none()
+            },
+            Message::FileBrowserToggled => {
+                match self.file_browser {
+                    Some(_) => self.file_browser = None,
+                    None => self.browse(&std::env::current_dir().unwrap_or_default()),
+                }
+                Command::none()
+            },
+            Message::FileBrowserOpened(path) => {
+                if path.is_dir() {
+                    self.browse(&path);
+                } else {
+                    self.open_sgf(path.to_string_lossy().to_string());
+                }
+                Command::none()
+            },
+            Message::RecentFileOpened(sgf_path) => {
+                self.open_sgf(sgf_path);
+                Command::none()
+            },
+            Message::NewGameToggled => {
+                self.new_game_form = match self.new_game_form {
+                    Some(_) => None,
+                    None => Some(NewGameForm::from_config(&self.config)),
+                };
+                Command::none()
+            },
+            Message::NewGameFieldChanged(field, value) => {
+                if let Some(new_game_form) = self.new_game_form.as_mut() {
+                    new_game_form.set_value(field, value);
+                }
+                Command::none()
+            },
+            Message::NewGameSubmitted => {
+                self.start_new_game();
+                Command::none()
+            },
+            Message::SaveRequested => {
+                self.save_game();
+                Command::none()
+            },
+            Message::ExportRequested => {
+                self.export_diagram();
+                Command::none()
+            },
+            Message::CopyAsciiRequested => {
+                self.command_feedback = "copied the position as ascii".to_string();
+                iced::clipboard::write(ascii::diagram_ascii(&self.go_band.diagram().printable()))
+            },
+            Message::PasteAsciiRequested => iced::clipboard::read(Message::AsciiPasted),
+            Message::AsciiPasted(content) => {
+                self.paste_ascii(&content.unwrap_or_default());
+                Command::none()
+            },
+            Message::AutosaveTick(_) => {
+                self.autosave();
+                Command::none()
+            },
+            Message::RecoveryAnswered(restore) => {
+                self.answer_recovery(restore);
+                Command::none()
+            },
+            Message::UnsavedSaving => {
+                if self.save_game() {
+                    self.resume_unsaved_request()
+                } else {
+                    self.unsaved_request = None;
+                    Command::none()
+                }
+            },
+            Message::UnsavedDiscarding => {
+                self.clear_recovery();
+                self.resume_unsaved_request()
+            },
+            Message::UnsavedCancelled => {
+                self.unsaved_request = None;
+                Command::none()
+            },
+            Message::ProblemSelected(step) => {
+                self.select_problem(step);
+                Command::none()
+            },
+            Message::GameEnded(game_result) => {
+                self.end_game(game_result);
+                Command::none()
+            },
+            Message::ClockToggled => {
+                let state = self.go_band.current_player().state();
+                if let Some(game_clock) = self.game_clock.as_mut().filter(|_| self.game_result.is_none()) {
+                    match game_clock.running() {
+                        Some(_) => game_clock.pause(),
+                        None => game_clock.start(state),
+                    }
+                }
+                Command::none()
+            },
             Message::EventOccurred(event) => {
                 if let Event::Window(window::Event::CloseRequested) = event {
+                    self.clear_recovery();
+                    if self.prefs_unsaved {
+                        self.save_prefs();
+                    }
                     window::close()
                 } else {
                     if let Event::Mouse(mouse) = event {
@@ -137,26 +1230,9 @@ impl<const D: usize> Application for GoBandView<D> {
                             },
                             iced::mouse::Event::ButtonPressed(button) => {
                                 match button {
-                                    Button::Left => {
-                                        let recorded_move = self.go_band.forward(false);
-                                        match recorded_move {
-                                            Some(go_move) => {
-                                                self.move_count += 1;
-                                                let move_id = go_move.move_id();
-                                                let res_move_id = if self.move_count == move_id as i32 + 1 {
-                                                    move_id as i32
-                                                } else {
-                                                    move_id as i32 - 1
-                                                };
-                                                GameTree::record_move(&mut self.game_tree, res_move_id, go_move);
-                                                println!("game_tree={}", json::stringify(self.game_tree.to_json()))
-                                            },
-                                            None => {},
-                                        }
-                                    },
+                                    Button::Left => self.click_band(),
                                     Button::Right => {
-                                        self.go_band.back();
-                                        self.move_count -= 1;
+                                        self.step_back();
                                     }
                                     _ => {},
                                 }
@@ -169,43 +1245,17 @@ impl<const D: usize> Application for GoBandView<D> {
                             self.go_band.set_window_height(height);
                             self.window_width = width;
                             self.window_height = height;
+                            self.config.set_window_size(width, height);
+                            self.prefs.update_config(|config| config.set_window_size(width, height));
+                            self.prefs_unsaved = true;
+                        } else if let Event::Window(window::Event::FileDropped(path)) = event {
+                            self.open_sgf(path.to_string_lossy().to_string());
                         } else {
-                            if let Event::Keyboard(iced::keyboard::Event::KeyPressed { key_code, .. }) = event {
-                                match key_code {
-                                    KeyCode::Up | KeyCode::Down => {
-                                        if key_code == KeyCode::Up {
-                                            self.move_count -= 1; 
-                                        } else {
-                                            self.move_count += 1;
-                                        }
-                                        let sgf_moves = GameTree::get_moves(&self.game_tree, self.move_count - 1);
-                                        println!("sgf move {}={:?}", self.move_count, sgf_moves);
-                                        match sgf_moves {
-                                            Some(moves) => {
-                                                let next_stone_poses = moves.into_iter().map(|go_move| {
-                                                        let (x, y, state) = go_move.0.move_pos();
-                                                        (x as i32, y as i32, state, go_move.1)
-                                                    }).collect::<Vec<_>>();
-                                                let mut potential_stone_poses = vec![];
-                                                for next_stone_pos in next_stone_poses {
-                                                    if next_stone_pos.3 {
-                                                        self.go_band.set_stone_pos(next_stone_pos.0, next_stone_pos.1);
-                                                        if key_code == KeyCode::Down {
-                                                            self.go_band.forward(true);
-                                                        } else {
-                                                            self.go_band.back();
-                                                        }
-                                                    } else {
-                                                        potential_stone_poses.push(next_stone_pos);
-                                                    }
-                                                }
-                                                self.go_band.set_next_stone_pos(potential_stone_poses);
-                                                self.clear_band_view();
-                                            },
-                                            None => {},
-                                        }
-                                    },
-                                    _ => {},
+                            if let Event::Keyboard(iced::keyboard::Event::KeyPressed { key_code, modifiers }) = event {
+                                match self.keymap.action(key_code, modifiers) {
+                                    Some(KeyAction::FocusCommand) => return text_input::focus(command_bar_id()),
+                                    Some(key_action) => self.key_pressed(key_action),
+                                    None => {},
                                 }
                             }
                         }
@@ -217,7 +1267,23 @@ impl<const D: usize> Application for GoBandView<D> {
     }
 
     fn subscription(&self) -> iced::Subscription<Self::Message> {
-        subscription::events().map(Message::EventOccurred)
+        // events already handled by a widget (e.g. a toolbar button) must not
+        // reach the band as well
+        let events = subscription::events_with(|event, status| match status {
+            event::Status::Ignored => Some(Message::EventOccurred(event)),
+            event::Status::Captured => None,
+        });
+        let mut subscriptions = vec![events];
+        if self.game_clock.as_ref().and_then(|game_clock| game_clock.running()).is_some() {
+            subscriptions.push(time::every(Duration::from_millis(100)).map(Message::ClockTick));
+        }
+        if self.auto_replay.running() {
+            subscriptions.push(time::every(self.auto_replay.interval()).map(Message::ReplayTick));
+        }
+        if self.autosave.pending() && !self.recovery_found {
+            subscriptions.push(time::every(AUTOSAVE_INTERVAL).map(Message::AutosaveTick));
+        }
+        Subscription::batch(subscriptions)
     }
 
     fn view(&self) -> Element<Message> {
@@ -225,13 +1291,157 @@ impl<const D: usize> Application for GoBandView<D> {
         let canvas = canvas(&self.go_band)
             .width(Length::Fixed(go_band_width as f32))
             .height(Length::Fill);
+        let mut notice_bar = column![].spacing(5);
+        if self.unsaved_request.is_some() {
+            notice_bar = notice_bar
+                .push(text("The game has unsaved changes"))
+                .push(row![
+                    button(text("Save")).on_press(Message::UnsavedSaving),
+                    button(text("Discard")).on_press(Message::UnsavedDiscarding),
+                    button(text("Cancel")).on_press(Message::UnsavedCancelled),
+                ].spacing(5));
+        }
+        if self.recovery_found {
+            notice_bar = notice_bar
+                .push(text("An unsaved game of the last session was found"))
+                .push(row![
+                    button(text("Restore")).on_press(Message::RecoveryAnswered(true)),
+                    button(text("Discard")).on_press(Message::RecoveryAnswered(false)),
+                ].spacing(5));
+        }
+        let browser_label = if self.file_browser.is_some() { "Close files" } else { "Open..." };
+        let save_label = if self.autosave.dirty() { "Save *" } else { "Save" };
+        let mut file_bar = column![
+            row![
+                button(text(browser_label)).on_press(Message::FileBrowserToggled),
+                button(text(save_label)).on_press(Message::SaveRequested),
+                button(text("Export SVG")).on_press(Message::ExportRequested),
+            ].spacing(5),
+            row![
+                button(text("Copy ASCII")).on_press(Message::CopyAsciiRequested),
+                button(text("Paste ASCII")).on_press(Message::PasteAsciiRequested),
+            ].spacing(5)
+        ].spacing(5);
+        if let Some(file_browser) = self.file_browser.as_ref() {
+            file_bar = file_bar.push(text(file_browser.dir()));
+            if let Some(parent) = file_browser.parent() {
+                file_bar = file_bar.push(button(text("..")).width(Length::Fill).on_press(Message::FileBrowserOpened(parent)));
+            }
+            for entry in file_browser.entries() {
+                let label = if entry.is_dir() { format!("{}/", entry.name()) } else { entry.name() };
+                file_bar = file_bar.push(button(text(label))
+                    .width(Length::Fill)
+                    .on_press(Message::FileBrowserOpened(file_browser.entry_path(&entry.name()))));
+            }
+        } else {
+            for recent_file in self.prefs.recent_files() {
+                let name = Path::new(&recent_file)
+                    .file_name()
+                    .map_or(recent_file.clone(), |name| name.to_string_lossy().to_string());
+                file_bar = file_bar.push(button(text(name))
+                    .width(Length::Fill)
+                    .on_press(Message::RecentFileOpened(recent_file)));
+            }
+        }
+        let new_game_label = if self.new_game_form.is_some() { "Cancel new game" } else { "New game" };
+        let mut new_game_bar = column![
+            button(text(new_game_label)).width(Length::Fill).on_press(Message::NewGameToggled)
+        ].spacing(5);
+        if let Some(new_game_form) = self.new_game_form.as_ref() {
+            for field in NewGameField::ALL {
+                new_game_bar = new_game_bar.push(row![
+                    text(field.to_string()).width(Length::Fixed(70.0)),
+                    text_input("", &new_game_form.value(field))
+                        .on_input(move |value| Message::NewGameFieldChanged(field, value))
+                        .on_submit(Message::NewGameSubmitted),
+                ].spacing(5));
+            }
+            new_game_bar = new_game_bar.push(button(text("Start")).width(Length::Fill).on_press(Message::NewGameSubmitted));
+        }
+        let tool_bar = EditTool::ALL.iter().fold(column![notice_bar, text("Sgf"), new_game_bar, file_bar].spacing(5), |tool_bar, edit_tool| {
+            let label = if *edit_tool == self.edit_tool {
+                format!("> {}", edit_tool)
+            } else {
+                edit_tool.to_string()
+            };
+            tool_bar.push(
+                button(text(label))
+                .width(Length::Fill)
+                .on_press(Message::EditToolSelected(*edit_tool)))
+        });
+        let tool_bar = tool_bar.push(pick_list(
+            &BandTheme::ALL[..],
+            Some(self.go_band.band_theme()),
+            Message::BandThemeSelected,
+        ));
+        let tool_bar = tool_bar
+            .push(text_input("Q16, pd or #120", &self.command)
+                .id(command_bar_id())
+                .on_input(Message::CommandChanged)
+                .on_submit(Message::CommandSubmitted))
+            .push(text(&self.command_feedback));
+        let tool_bar = match self.game_clock.as_ref() {
+            Some(game_clock) => {
+                let toggle_label = if game_clock.running().is_some() { "Pause clock" } else { "Start clock" };
+                tool_bar
+                    .push(text(game_clock.time_control().to_string()))
+                    .push(text(format!("Black {}", game_clock.clock_text(1))))
+                    .push(text(format!("White {}", game_clock.clock_text(-1))))
+                    .push(button(text(toggle_label)).width(Length::Fill).on_press(Message::ClockToggled))
+            },
+            None => tool_bar,
+        };
+        let replay_label = if self.auto_replay.running() { "Pause replay" } else { "Replay" };
+        let tool_bar = tool_bar
+            .push(button(text(replay_label)).width(Length::Fill).on_press(Message::ReplayToggled))
+            .push(text(format!("Every {:.1}s", self.auto_replay.interval_ms() as f32 / 1000.0)))
+            .push(slider(MIN_INTERVAL_MS..=MAX_INTERVAL_MS, self.auto_replay.interval_ms(), Message::ReplaySpeedChanged).step(100))
+            .push(checkbox("Stop at comments", self.auto_replay.stop_at_comments(), Message::StopAtCommentsToggled))
+            .push(checkbox("Stop at branches", self.auto_replay.stop_at_branches(), Message::StopAtBranchesToggled));
+        let tool_bar = match self.problem_set.as_ref() {
+            Some(problem_set) => tool_bar
+                .push(text(format!("Problem {}/{}: {}{}",
+                    problem_set.current() + 1, problem_set.problem_count(), problem_set.current_name(),
+                    match problem_set.current_status() {
+                        ProblemStatus::Unsolved => "",
+                        ProblemStatus::Solved => " (solved)",
+                        ProblemStatus::Failed => " (failed)",
+                    })))
+                .push(text(format!("Solved {}, failed {}",
+                    problem_set.count(ProblemStatus::Solved), problem_set.count(ProblemStatus::Failed))))
+                .push(row![
+                    button(text("Prev")).on_press(Message::ProblemSelected(-1)),
+                    button(text("Retry")).on_press(Message::ProblemSelected(0)),
+                    button(text("Next")).on_press(Message::ProblemSelected(1)),
+                ].spacing(5)),
+            None => tool_bar,
+        };
+        let tool_bar = match self.guess_stats {
+            Some(guess_stats) => tool_bar
+                .push(button(text("Stop guessing")).width(Length::Fill).on_press(Message::GuessModeToggled))
+                .push(text(guess_stats.to_string())),
+            None => tool_bar
+                .push(button(text("Guess moves")).width(Length::Fill).on_press(Message::GuessModeToggled)),
+        };
+        let tool_bar = match self.game_result {
+            Some(game_result) => tool_bar.push(text(game_result.to_string())),
+            None => {
+                // the player to move resigns
+                let winner = -self.go_band.current_player().state();
+                tool_bar.push(row![
+                    button(text("Resign")).on_press(Message::GameEnded(GameResult::Resign(winner))),
+                    button(text("Draw")).on_press(Message::GameEnded(GameResult::Draw)),
+                    button(text("No result")).on_press(Message::GameEnded(GameResult::NoResult)),
+                ].spacing(5))
+            },
+        };
         container(
             row![
                 container(canvas)
                 .width(Length::Fixed(go_band_width as f32))
                 .height(Length::Fill),
-                container(text("Sgf"))
-                .width(Length::Fixed((self.window_width - go_band_width) as f32 / 2.0))
+                container(scrollable(tool_bar))
+                .width(Length::Fixed(self.window_width.saturating_sub(go_band_width) as f32))
                 .height(Length::Fill)
             ])
             .width(Length::Fill)
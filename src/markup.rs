@@ -0,0 +1,80 @@
+use crate::game_tree::{SgfKey, SgfNode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkupKind {
+    Circle,
+    Cross,
+    Square,
+    Triangle,
+    Label(String),
+    TerritoryBlack,
+    TerritoryWhite,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Markup {
+    pos: (usize, usize),
+    kind: MarkupKind,
+}
+
+impl Markup {
+    pub fn new(pos_x: usize, pos_y: usize, kind: MarkupKind) -> Self {
+        Markup { pos: (pos_x, pos_y), kind }
+    }
+
+    pub fn pos(&self) -> (usize, usize) {
+        self.pos
+    }
+
+    pub fn kind(&self) -> MarkupKind {
+        self.kind.clone()
+    }
+
+    pub fn from_sgf_nodes(sgf_nodes: &Vec<SgfNode>) -> Vec<Markup> {
+        let mut markups = vec![];
+        for sgf_node in sgf_nodes {
+            let node_val = sgf_node.node_val();
+            let kind = match sgf_node.node_key() {
+                SgfKey::CR => MarkupKind::Circle,
+                SgfKey::MA => MarkupKind::Cross,
+                SgfKey::SQ => MarkupKind::Square,
+                SgfKey::TR => MarkupKind::Triangle,
+                SgfKey::TB => MarkupKind::TerritoryBlack,
+                SgfKey::TW => MarkupKind::TerritoryWhite,
+                SgfKey::LB => {
+                    // LB[aa:text], the text itself may contain colons
+                    if let Some((point, label)) = node_val.split_once(':') {
+                        for (x, y) in SgfNode::parse_points(point).unwrap_or_default() {
+                            markups.push(Markup::new(x, y, MarkupKind::Label(label.to_string())));
+                        }
+                    }
+                    continue;
+                },
+                _ => continue,
+            };
+            for (x, y) in SgfNode::parse_points(&node_val).unwrap_or_default() {
+                markups.push(Markup::new(x, y, kind.clone()));
+            }
+        }
+        markups
+    }
+
+    // DD is inherited: the last DD property on the path replaces every earlier
+    // one, and an empty `DD[]` switches dimming off again
+    pub fn dimmed_points(path_nodes: &Vec<SgfNode>) -> Vec<(usize, usize)> {
+        let mut dimmed_points = vec![];
+        let mut in_dd_group = false;
+        for sgf_node in path_nodes {
+            if sgf_node.node_key() == SgfKey::DD {
+                if !in_dd_group {
+                    dimmed_points.clear();
+                    in_dd_group = true;
+                }
+                dimmed_points.extend(SgfNode::parse_points(&sgf_node.node_val()).unwrap_or_default());
+            } else {
+                in_dd_group = false;
+            }
+        }
+        dimmed_points
+    }
+}
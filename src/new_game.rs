@@ -0,0 +1,121 @@
+use std::fmt;
+
+use crate::config::{Config, MIN_GO_SZ, MAX_GO_SZ};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewGameField {
+    Size,
+    Komi,
+    Black,
+    White,
+    Handicap,
+}
+
+impl NewGameField {
+    pub const ALL: [NewGameField; 5] = [
+        NewGameField::Size,
+        NewGameField::Komi,
+        NewGameField::Black,
+        NewGameField::White,
+        NewGameField::Handicap,
+    ];
+}
+
+impl fmt::Display for NewGameField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NewGameField::Size => "Size",
+            NewGameField::Komi => "Komi",
+            NewGameField::Black => "Black",
+            NewGameField::White => "White",
+            NewGameField::Handicap => "Handicap",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// the values of the new game form as typed, checked when the game is created
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewGameForm {
+    go_sz: String,
+    go_km: String,
+    go_pb: String,
+    go_pw: String,
+    go_ha: String,
+}
+
+impl NewGameForm {
+    pub fn from_config(config: &Config) -> Self {
+        NewGameForm {
+            go_sz: config.go_sz().to_string(),
+            go_km: config.go_km().to_string(),
+            go_pb: config.go_pb(),
+            go_pw: config.go_pw(),
+            go_ha: config.go_ha().to_string(),
+        }
+    }
+
+    pub fn value(&self, field: NewGameField) -> String {
+        match field {
+            NewGameField::Size => self.go_sz.clone(),
+            NewGameField::Komi => self.go_km.clone(),
+            NewGameField::Black => self.go_pb.clone(),
+            NewGameField::White => self.go_pw.clone(),
+            NewGameField::Handicap => self.go_ha.clone(),
+        }
+    }
+
+    pub fn set_value(&mut self, field: NewGameField, value: String) {
+        match field {
+            NewGameField::Size => self.go_sz = value,
+            NewGameField::Komi => self.go_km = value,
+            NewGameField::Black => self.go_pb = value,
+            NewGameField::White => self.go_pw = value,
+            NewGameField::Handicap => self.go_ha = value,
+        }
+    }
+
+    // `config` with the game settings of the form, the first invalid value
+    // is reported otherwise
+    pub fn to_config(&self, config: &Config) -> Result<Config, String> {
+        let go_sz = self.go_sz.trim().parse::<i32>()
+            .map_err(|_| format!("size must be a number from {} to {}", MIN_GO_SZ, MAX_GO_SZ))?;
+        let go_km = self.go_km.trim().parse::<f32>().map_err(|_| "komi must be a multiple of 0.5".to_string())?;
+        let go_ha = self.go_ha.trim().parse::<usize>().map_err(|_| "handicap must be a number".to_string())?;
+        let go_pb = self.go_pb.trim().to_string();
+        let go_pw = self.go_pw.trim().to_string();
+        let config = config.for_new_game(go_sz, go_km, go_pb, go_pw, go_ha);
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::Config;
+
+    use super::{NewGameField, NewGameForm};
+
+    #[test]
+    pub fn test_new_game_form() {
+        let config = Config::default();
+        let mut new_game_form = NewGameForm::from_config(&config);
+        new_game_form.set_value(NewGameField::Size, " 9".to_string());
+        new_game_form.set_value(NewGameField::Handicap, "3".to_string());
+        new_game_form.set_value(NewGameField::Black, "Lee".to_string());
+        let new_config = new_game_form.to_config(&config).unwrap();
+        assert_eq!((new_config.go_sz(), new_config.go_ha(), new_config.go_pb()), (9, 3, "Lee".to_string()));
+        assert_eq!(new_config.window_width(), config.window_width());
+
+        for (field, value) in [
+            (NewGameField::Size, "21"),
+            (NewGameField::Komi, "6.3"),
+            (NewGameField::Handicap, "1"),
+            (NewGameField::White, "A]"),
+        ] {
+            let mut invalid_form = new_game_form.clone();
+            invalid_form.set_value(field, value.to_string());
+            assert!(invalid_form.to_config(&config).is_err(), "{} = {}", field, value);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use iced::Color;
+
+use crate::{diagram::Shape, svg::number};
+
+// control point distance of the four curves that make a circle
+const CIRCLE_KAPPA: f32 = 0.552_284_8;
+
+// `r g b rg` for filling, `RG` for stroking
+fn color_operator(color: Color, operator: &str) -> String {
+    format!("{} {} {} {}", number(color.r), number(color.g), number(color.b), operator)
+}
+
+// width of `text` in Helvetica, in ems
+fn text_width(text: &str) -> f32 {
+    text.chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' => 0.222,
+            'I' | ' ' | ',' | '.' | ':' | 'f' | 't' => 0.278,
+            '(' | ')' | '-' | 'r' => 0.333,
+            'm' => 0.833,
+            'M' => 0.833,
+            'W' => 0.944,
+            'w' => 0.722,
+            'A'..='Z' => 0.667,
+            'a'..='z' => 0.5,
+            _ => 0.556,
+        })
+        .sum()
+}
+
+// a string in WinAnsi, the encoding of the standard fonts, characters it
+// lacks are replaced
+fn text_string(text: &str) -> String {
+    let mut string = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                string.push('\\');
+                string.push(c);
+            },
+            ' '..='~' => string.push(c),
+            '\u{a0}'..='\u{ff}' => string.push_str(&format!("\\{:03o}", c as u32)),
+            _ => string.push('?'),
+        }
+    }
+    string.push(')');
+    string
+}
+
+fn circle_path(center: (f32, f32), radius: f32) -> String {
+    let (x, y) = center;
+    let k = radius * CIRCLE_KAPPA;
+    let point = |x: f32, y: f32| format!("{} {}", number(x), number(y));
+    [
+        format!("{} m", point(x + radius, y)),
+        format!("{} {} {} c", point(x + radius, y + k), point(x + k, y + radius), point(x, y + radius)),
+        format!("{} {} {} c", point(x - k, y + radius), point(x - radius, y + k), point(x - radius, y)),
+        format!("{} {} {} c", point(x - radius, y - k), point(x - k, y - radius), point(x, y - radius)),
+        format!("{} {} {} c h", point(x + k, y - radius), point(x + radius, y - k), point(x + radius, y)),
+    ].join(" ")
+}
+
+// the operators drawing a shape, on a page whose y axis points down
+fn shape_operators(shape: &Shape) -> Vec<String> {
+    let mut operators = vec![];
+    // see-through colours use the graphics state of their alpha, named by
+    // it in the page resources
+    let mut paint = |path: &str, color: Color, operator: &str, stroke_width: Option<f32>| {
+        operators.push("q".to_string());
+        if color.a < 1.0 {
+            operators.push(format!("/A{} gs", (color.a * 100.0).round()));
+        }
+        if let Some(width) = stroke_width {
+            operators.push(format!("{} w 1 J 1 j {}", number(width), color_operator(color, "RG")));
+        } else {
+            operators.push(color_operator(color, "rg"));
+        }
+        operators.push(format!("{} {}", path, operator).trim_end().to_string());
+        operators.push("Q".to_string());
+    };
+    match shape {
+        Shape::Rect { origin, size, fill, stroke } => {
+            let path = format!("{} {} {} {} re", number(origin.0), number(origin.1), number(size.0), number(size.1));
+            if let Some(color) = fill {
+                paint(&path, *color, "f", None);
+            }
+            if let Some((width, color)) = stroke {
+                paint(&path, *color, "S", Some(*width));
+            }
+        },
+        Shape::Circle { center, radius, fill, stroke } => {
+            let path = circle_path(*center, *radius);
+            if let Some(color) = fill {
+                paint(&path, *color, "f", None);
+            }
+            if let Some((width, color)) = stroke {
+                paint(&path, *color, "S", Some(*width));
+            }
+        },
+        Shape::Line { points, closed, width, color } => {
+            let path = points.iter()
+                .enumerate()
+                .map(|(idx, (x, y))| format!("{} {} {}", number(*x), number(*y), if idx == 0 { "m" } else { "l" }))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let path = if *closed { format!("{} h", path) } else { path };
+            paint(&path, *color, "S", Some(*width));
+        },
+        Shape::Text { content, position, size, color } => {
+            // the text matrix turns the glyphs upright again, the baseline
+            // sits below the centre
+            let left = position.0 - text_width(content) * size / 2.0;
+            let baseline = position.1 + size * 0.35;
+            paint(
+                &format!("BT /F1 {} Tf 1 0 0 -1 {} {} Tm {} Tj ET", number(*size), number(left), number(baseline), text_string(content)),
+                *color, "", None);
+        },
+    }
+    operators
+}
+
+// a pdf document with a page of `page_size` points for each list of shapes,
+// drawn with the top left corner as origin
+pub fn pages_pdf(pages: &[Vec<Shape>], page_size: (f32, f32)) -> Vec<u8> {
+    // catalog, page tree and font come first, then a page and its content
+    // for each page
+    let page_id = |page_idx: usize| 4 + page_idx * 2;
+    let kids = (0..pages.len()).map(|page_idx| format!("{} 0 R", page_id(page_idx))).collect::<Vec<_>>().join(" ");
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, pages.len()),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+    ];
+    for (page_idx, shapes) in pages.iter().enumerate() {
+        let mut alphas = vec![];
+        for shape in shapes {
+            let colors = match shape {
+                Shape::Rect { fill, stroke, .. } | Shape::Circle { fill, stroke, .. } => vec![*fill, stroke.map(|(_, color)| color)],
+                Shape::Line { color, .. } | Shape::Text { color, .. } => vec![Some(*color)],
+            };
+            for color in colors.into_iter().flatten().filter(|color| color.a < 1.0) {
+                let alpha = (color.a * 100.0).round();
+                if !alphas.contains(&alpha) {
+                    alphas.push(alpha);
+                }
+            }
+        }
+        let graphics_states = alphas.iter()
+            .map(|alpha| format!("/A{} << /ca {} /CA {} >>", alpha, number(alpha / 100.0), number(alpha / 100.0)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R /Resources << /Font << /F1 3 0 R >> /ExtGState << {} >> >> >>",
+            number(page_size.0), number(page_size.1), page_id(page_idx) + 1, graphics_states));
+        let mut content = vec![format!("1 0 0 -1 0 {} cm", number(page_size.1))];
+        content.extend(shapes.iter().flat_map(shape_operators));
+        let content = content.join("\n");
+        objects.push(format!("<< /Length {} >>\nstream\n{}\nendstream", content.len(), content));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+    for (object_idx, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n{}\nendobj\n", object_idx + 1, object).into_bytes());
+    }
+    let xref_offset = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref_offset).into_bytes());
+    pdf
+}
+
+#[cfg(test)]
+mod test {
+    use iced::Color;
+
+    use crate::diagram::Shape;
+
+    use super::{pages_pdf, text_string};
+
+    #[test]
+    pub fn test_pages_pdf() {
+        assert_eq!(text_string("(a\\b) é 碁"), "(\\(a\\\\b\\) \\351 ?)");
+        let pages = vec![
+            vec![Shape::Text { content: "1".to_string(), position: (10.0, 10.0), size: 10.0, color: Color::BLACK }],
+            vec![Shape::Circle { center: (20.0, 20.0), radius: 5.0, fill: Some(Color { a: 0.5, ..Color::WHITE }), stroke: None }],
+        ];
+        let pdf = String::from_utf8(pages_pdf(&pages, (100.0, 200.0))).unwrap();
+        assert!(pdf.contains("/Kids [4 0 R 6 0 R] /Count 2"));
+        assert!(pdf.contains("BT /F1 10 Tf 1 0 0 -1 7.22 13.5 Tm (1) Tj ET"));
+        assert!(pdf.contains("/ExtGState << /A50 << /ca 0.5 /CA 0.5 >> >>"));
+        // every object is where the cross reference table says
+        let xref = &pdf[pdf.find("xref\n").unwrap()..];
+        for (object_idx, line) in xref.lines().skip(3).take(7).enumerate() {
+            let offset = line[..10].parse::<usize>().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", object_idx + 1)));
+        }
+        let startxref = pdf.lines().rev().nth(1).unwrap().parse::<usize>().unwrap();
+        assert!(pdf[startxref..].starts_with("xref"));
+    }
+}
@@ -0,0 +1,308 @@
+use iced::Color;
+
+use crate::{band_theme::BandTheme, diagram::{Diagram, Shape}};
+
+// samples per pixel side, the share of samples inside a shape sets its
+// coverage of the pixel
+const SUPERSAMPLING: usize = 4;
+
+// rows of 5 pixel wide glyphs, lower case letters are drawn in upper case
+// and other characters are left blank
+const GLYPH_ROWS: usize = 7;
+const GLYPHS: [(char, [u8; GLYPH_ROWS]); 36] = [
+    ('0', [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e]),
+    ('1', [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('2', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f]),
+    ('3', [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e]),
+    ('4', [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02]),
+    ('5', [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e]),
+    ('6', [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e]),
+    ('7', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e]),
+    ('9', [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c]),
+    ('A', [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11]),
+    ('B', [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e]),
+    ('C', [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e]),
+    ('D', [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c]),
+    ('E', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f]),
+    ('F', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10]),
+    ('G', [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f]),
+    ('H', [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('I', [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f]),
+    ('M', [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('P', [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10]),
+    ('Q', [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d]),
+    ('R', [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11]),
+    ('S', [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e]),
+    ('T', [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a]),
+    ('X', [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04]),
+    ('Z', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f]),
+];
+
+fn glyph(c: char) -> Option<[u8; GLYPH_ROWS]> {
+    let c = c.to_ascii_uppercase();
+    GLYPHS.iter().find(|(glyph_char, _)| *glyph_char == c).map(|(_, rows)| *rows)
+}
+
+fn distance(from: (f32, f32), to: (f32, f32)) -> f32 {
+    (to.0 - from.0).hypot(to.1 - from.1)
+}
+
+fn segment_distance(point: (f32, f32), start: (f32, f32), end: (f32, f32)) -> f32 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = dx * dx + dy * dy;
+    if length == 0.0 {
+        return distance(point, start);
+    }
+    let t = (((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length).clamp(0.0, 1.0);
+    distance(point, (start.0 + t * dx, start.1 + t * dy))
+}
+
+// rgba pixels of a viewport, painted in the logical pixels of a band layout
+struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>,
+    origin: (f32, f32),
+    // logical pixels per raster pixel
+    scale: f32,
+}
+
+impl Raster {
+    fn new(width: usize, height: usize, origin: (f32, f32), scale: f32) -> Self {
+        Raster { width, height, pixels: vec![[0.0; 4]; width * height], origin, scale }
+    }
+
+    // blends `color` over the pixels touched by `bounds` (left, top, right,
+    // bottom) as much as their samples are `inside` the shape
+    fn paint(&mut self, bounds: (f32, f32, f32, f32), color: Color, inside: impl Fn((f32, f32)) -> bool) {
+        let to_pixel = |value: f32, origin: f32| ((value - origin) / self.scale).floor();
+        let left = to_pixel(bounds.0, self.origin.0).max(0.0) as usize;
+        let top = to_pixel(bounds.1, self.origin.1).max(0.0) as usize;
+        let right = (to_pixel(bounds.2, self.origin.0) + 1.0).clamp(0.0, self.width as f32) as usize;
+        let bottom = (to_pixel(bounds.3, self.origin.1) + 1.0).clamp(0.0, self.height as f32) as usize;
+        let step = self.scale / SUPERSAMPLING as f32;
+        for y in top..bottom {
+            for x in left..right {
+                let (pixel_x, pixel_y) = (self.origin.0 + x as f32 * self.scale, self.origin.1 + y as f32 * self.scale);
+                let mut samples = 0;
+                for i in 0..SUPERSAMPLING {
+                    for j in 0..SUPERSAMPLING {
+                        if inside((pixel_x + (i as f32 + 0.5) * step, pixel_y + (j as f32 + 0.5) * step)) {
+                            samples += 1;
+                        }
+                    }
+                }
+                if samples > 0 {
+                    let alpha = color.a * samples as f32 / (SUPERSAMPLING * SUPERSAMPLING) as f32;
+                    self.blend(x, y, color, alpha);
+                }
+            }
+        }
+    }
+
+    // `color` over the pixel
+    fn blend(&mut self, x: usize, y: usize, color: Color, alpha: f32) {
+        let pixel = &mut self.pixels[y * self.width + x];
+        let below = pixel[3] * (1.0 - alpha);
+        let out_alpha = alpha + below;
+        if out_alpha <= 0.0 {
+            return;
+        }
+        for (channel, value) in [color.r, color.g, color.b].into_iter().enumerate() {
+            pixel[channel] = (value * alpha + pixel[channel] * below) / out_alpha;
+        }
+        pixel[3] = out_alpha;
+    }
+
+    fn draw(&mut self, shape: &Shape) {
+        match shape {
+            Shape::Rect { origin, size, fill, stroke } => {
+                let (left, top, right, bottom) = (origin.0, origin.1, origin.0 + size.0, origin.1 + size.1);
+                if let Some(color) = fill {
+                    self.paint((left, top, right, bottom), *color, |(x, y)| x >= left && x < right && y >= top && y < bottom);
+                }
+                if let Some((width, color)) = stroke {
+                    let corners = [(left, top), (right, top), (right, bottom), (left, bottom)];
+                    self.draw_line(&corners, true, *width, *color);
+                }
+            },
+            Shape::Circle { center, radius, fill, stroke } => {
+                let (center, radius) = (*center, *radius);
+                if let Some(color) = fill {
+                    let bounds = (center.0 - radius, center.1 - radius, center.0 + radius, center.1 + radius);
+                    self.paint(bounds, *color, |point| distance(point, center) <= radius);
+                }
+                if let Some((width, color)) = stroke {
+                    let outer = radius + width / 2.0;
+                    let bounds = (center.0 - outer, center.1 - outer, center.0 + outer, center.1 + outer);
+                    self.paint(bounds, *color, |point| (distance(point, center) - radius).abs() <= width / 2.0);
+                }
+            },
+            Shape::Line { points, closed, width, color } => self.draw_line(points, *closed, *width, *color),
+            Shape::Text { content, position, size, color } => self.draw_text(content, *position, *size, *color),
+        }
+    }
+
+    // round capped and joined, like the canvas strokes
+    fn draw_line(&mut self, points: &[(f32, f32)], closed: bool, width: f32, color: Color) {
+        let mut segments = points.windows(2).map(|pair| (pair[0], pair[1])).collect::<Vec<_>>();
+        if let (true, Some(first), Some(last)) = (closed, points.first(), points.last()) {
+            segments.push((*last, *first));
+        }
+        if segments.is_empty() {
+            return;
+        }
+        let half_width = width / 2.0;
+        let bounds = points.iter().fold((f32::MAX, f32::MAX, f32::MIN, f32::MIN), |bounds, (x, y)| {
+            (bounds.0.min(x - half_width), bounds.1.min(y - half_width), bounds.2.max(x + half_width), bounds.3.max(y + half_width))
+        });
+        self.paint(bounds, color, |point| segments.iter().any(|(start, end)| segment_distance(point, *start, *end) <= half_width));
+    }
+
+    // capital letters of `GLYPHS` about as tall as those of a `size` font
+    fn draw_text(&mut self, content: &str, position: (f32, f32), size: f32, color: Color) {
+        let unit = size * 0.7 / GLYPH_ROWS as f32;
+        let char_count = content.chars().count() as f32;
+        let left = position.0 - (char_count * 6.0 - 1.0) * unit / 2.0;
+        let top = position.1 - GLYPH_ROWS as f32 * unit / 2.0;
+        for (char_idx, c) in content.chars().enumerate() {
+            let rows = match glyph(c) {
+                Some(rows) => rows,
+                None => continue,
+            };
+            let glyph_left = left + char_idx as f32 * 6.0 * unit;
+            let bounds = (glyph_left, top, glyph_left + 5.0 * unit, top + GLYPH_ROWS as f32 * unit);
+            self.paint(bounds, color, |(x, y)| {
+                let (column, row) = (((x - glyph_left) / unit).floor(), ((y - top) / unit).floor());
+                (0.0..5.0).contains(&column) && (0.0..GLYPH_ROWS as f32).contains(&row)
+                    && rows[row as usize] & (0x10 >> column as usize) != 0
+            });
+        }
+    }
+
+    fn rgba(&self) -> Vec<u8> {
+        self.pixels.iter()
+            .flat_map(|pixel| pixel.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect()
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn push_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+// 8 bit rgba, the image data is stored without compression so no deflate
+// implementation is needed
+pub fn encode_png(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+    let mut scanlines = Vec::with_capacity((width * 4 + 1) * height);
+    for row in rgba.chunks(width * 4).take(height) {
+        // no filter
+        scanlines.push(0);
+        scanlines.extend(row);
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let blocks = scanlines.chunks(0xffff).collect::<Vec<_>>();
+    for (block_idx, block) in blocks.iter().enumerate() {
+        zlib.push(if block_idx + 1 == blocks.len() { 1 } else { 0 });
+        zlib.extend((block.len() as u16).to_le_bytes());
+        zlib.extend((!(block.len() as u16)).to_le_bytes());
+        zlib.extend(*block);
+    }
+    zlib.extend(adler32(&scanlines).to_be_bytes());
+
+    let mut header = vec![];
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // bit depth, rgba colour, deflate, adaptive filtering, no interlace
+    header.extend([8, 6, 0, 0, 0]);
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    push_chunk(&mut png, b"IHDR", &header);
+    push_chunk(&mut png, b"IDAT", &zlib);
+    push_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+// the rgba pixels of the diagram drawn with the shapes of the canvas, as
+// `(width, height, pixels)`
+fn rasterize(diagram: &Diagram, band_theme: BandTheme, width: u32) -> (usize, usize, Vec<u8>) {
+    let band_layout = diagram.layout_for_width(width as f32);
+    let (view_x, view_y, view_width, view_height) = diagram.viewport(&band_layout);
+    let image_width = (view_width.round() as usize).max(1);
+    let image_height = (view_height.round() as usize).max(1);
+    let mut raster = Raster::new(image_width, image_height, (view_x, view_y), view_width / image_width as f32);
+    for shape in diagram.shapes(&band_layout, band_theme) {
+        raster.draw(&shape);
+    }
+    (image_width, image_height, raster.rgba())
+}
+
+// a png of the diagram, `width` pixels wide
+pub fn diagram_png(diagram: &Diagram, band_theme: BandTheme, width: u32) -> Vec<u8> {
+    let (image_width, image_height, rgba) = rasterize(diagram, band_theme, width);
+    encode_png(image_width, image_height, &rgba)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{band_theme::BandTheme, diagram::{Diagram, Region}, game_tree::GameTree};
+
+    use super::{crc32, diagram_png, rasterize};
+
+    #[test]
+    pub fn test_diagram_png() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        let game_tree = GameTree::from_sgf("(;SZ[9];B[cc];W[dd])").unwrap();
+        let diagram = Diagram::from_game_tree(&game_tree, 2, None);
+        let (width, height, rgba) = rasterize(&diagram, BandTheme::Monochrome, 90);
+        assert_eq!((width, height), (90, 90));
+        let pixel = |x: usize, y: usize| rgba[(y * width + x) * 4..(y * width + x) * 4 + 4].to_vec();
+        // a black stone, the inside of a white one and an empty square
+        assert_eq!(pixel(25, 25), vec![0, 0, 0, 255]);
+        assert_eq!(pixel(35, 35), vec![255, 255, 255, 255]);
+        assert_eq!(pixel(20, 20), vec![255, 255, 255, 255]);
+
+        let png = diagram_png(&diagram, BandTheme::ClassicWood, 90);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR\0\0\0\x5a\0\0\0\x5a"));
+        assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+
+        let mut diagram = diagram;
+        diagram.set_region(Region::parse("aa:ec", 9).unwrap());
+        let (width, height, _) = rasterize(&diagram, BandTheme::Monochrome, 50);
+        assert_eq!((width, height), (50, 30));
+    }
+}
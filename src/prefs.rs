@@ -0,0 +1,107 @@
+use std::{env, fs, io, path::PathBuf};
+
+use json::JsonValue;
+
+use crate::config::{Config, RECENT_FILES_LIMIT};
+
+// what is remembered between launches: the config without command line
+// overrides, and the recently opened files
+#[derive(Debug, Clone)]
+pub struct Prefs {
+    config: Config,
+    recent_files: Vec<String>,
+}
+
+impl Prefs {
+    // `fastgo/prefs.json` in the user's config directory
+    pub fn path() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+        Some(config_dir.join("fastgo").join("prefs.json"))
+    }
+
+    // the defaults when there is no preferences file yet, or it cannot be used
+    pub fn load() -> Self {
+        let prefs = Prefs { config: Config::default(), recent_files: vec![] };
+        let prefs_path = match Prefs::path() {
+            Some(prefs_path) if prefs_path.is_file() => prefs_path,
+            _ => return prefs,
+        };
+        let prefs_json = fs::read_to_string(&prefs_path)
+            .map_err(|err| err.to_string())
+            .and_then(|content| json::parse(&content).map_err(|err| err.to_string()));
+        match prefs_json.and_then(|prefs_json| prefs.with_json(&prefs_json)) {
+            Ok(prefs) => prefs,
+            Err(err) => {
+                eprintln!("ignoring {}: {}", prefs_path.display(), err);
+                prefs
+            },
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let prefs_path = Prefs::path().ok_or(io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(prefs_dir) = prefs_path.parent() {
+            fs::create_dir_all(prefs_dir)?;
+        }
+        fs::write(prefs_path, json::stringify_pretty(self.to_json(), 2))
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut prefs_json = self.config.to_json();
+        prefs_json["recent_files"] = self.recent_files.clone().into();
+        prefs_json
+    }
+
+    pub fn with_json(&self, prefs_json: &JsonValue) -> Result<Prefs, String> {
+        let config = self.config.with_json(prefs_json)?;
+        let recent_files = match &prefs_json["recent_files"] {
+            JsonValue::Null => self.recent_files.clone(),
+            JsonValue::Array(recent_files) => recent_files.iter()
+                .filter_map(|recent_file| recent_file.as_str())
+                .map(|recent_file| recent_file.to_string())
+                .take(RECENT_FILES_LIMIT)
+                .collect(),
+            _ => return Err("invalid recent_files in preferences".to_string()),
+        };
+        Ok(Prefs { config, recent_files })
+    }
+
+    pub fn config(&self) -> Config {
+        self.config.clone()
+    }
+
+    pub fn recent_files(&self) -> Vec<String> {
+        self.recent_files.clone()
+    }
+
+    pub fn update_config(&mut self, change: impl Fn(&mut Config)) {
+        change(&mut self.config);
+    }
+
+    pub fn set_recent_files(&mut self, recent_files: Vec<String>) {
+        self.recent_files = recent_files;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::config::{Config, RECENT_FILES_LIMIT};
+
+    use super::Prefs;
+
+    #[test]
+    pub fn test_recent_files_json() {
+        let prefs = Prefs { config: Config::default(), recent_files: vec!["a.sgf".to_string()] };
+        let prefs_json = json::parse(r#"{"size": 9}"#).unwrap();
+        assert_eq!(prefs.with_json(&prefs_json).unwrap().recent_files(), vec!["a.sgf".to_string()]);
+        let recent_files = (0..RECENT_FILES_LIMIT + 2).map(|idx| format!("{}.sgf", idx)).collect::<Vec<_>>();
+        let prefs_json = json::object! { "recent_files": recent_files.clone() };
+        assert_eq!(prefs.with_json(&prefs_json).unwrap().recent_files(), recent_files[..RECENT_FILES_LIMIT].to_vec());
+
+        let prefs_json = json::parse(r#"{"recent_files": "sgf/test.sgf"}"#).unwrap();
+        assert!(prefs.with_json(&prefs_json).is_err());
+    }
+}
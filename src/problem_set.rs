@@ -0,0 +1,112 @@
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemStatus {
+    Unsolved,
+    Solved,
+    Failed,
+}
+
+// the sgf problems of a directory in name order, with how each went
+#[derive(Debug, Clone)]
+pub struct ProblemSet {
+    sgf_paths: Vec<String>,
+    statuses: Vec<ProblemStatus>,
+    current: usize,
+}
+
+impl ProblemSet {
+    pub fn from_dir(dir: &str) -> Result<Self, String> {
+        let mut sgf_paths = fs::read_dir(dir)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "sgf"))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        if sgf_paths.is_empty() {
+            return Err(format!("no sgf problems in {}", dir));
+        }
+        sgf_paths.sort();
+        let statuses = vec![ProblemStatus::Unsolved; sgf_paths.len()];
+        Ok(ProblemSet { sgf_paths, statuses, current: 0 })
+    }
+
+    pub fn problem_count(&self) -> usize {
+        self.sgf_paths.len()
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn current_path(&self) -> String {
+        self.sgf_paths[self.current].clone()
+    }
+
+    pub fn current_name(&self) -> String {
+        Path::new(&self.sgf_paths[self.current])
+            .file_stem()
+            .map_or(String::default(), |name| name.to_string_lossy().to_string())
+    }
+
+    pub fn current_status(&self) -> ProblemStatus {
+        self.statuses[self.current]
+    }
+
+    // moves `step` problems on, wrapping around at either end
+    pub fn select(&mut self, step: i32) {
+        self.current = (self.current as i32 + step).rem_euclid(self.problem_count() as i32) as usize;
+    }
+
+    // the first outcome of a problem counts, retrying after a failure does
+    // not turn it into a solved one
+    pub fn set_status(&mut self, status: ProblemStatus) {
+        if self.statuses[self.current] == ProblemStatus::Unsolved {
+            self.statuses[self.current] = status;
+        }
+    }
+
+    pub fn count(&self, status: ProblemStatus) -> usize {
+        self.statuses.iter().filter(|problem_status| **problem_status == status).count()
+    }
+
+    // problem collections mark the end of a correct line with a comment
+    // leading with `Correct`, `RIGHT` or `+`
+    pub fn is_correct_comment(comment: &str) -> bool {
+        let comment = comment.trim_start();
+        let first_word = comment.split(|c: char| !c.is_alphabetic()).next().unwrap_or_default().to_lowercase();
+        comment.starts_with('+') || first_word == "correct" || first_word == "right"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ProblemSet, ProblemStatus};
+
+    #[test]
+    pub fn test_correct_comment() {
+        assert!(ProblemSet::is_correct_comment("Correct!"));
+        assert!(ProblemSet::is_correct_comment("RIGHT"));
+        assert!(!ProblemSet::is_correct_comment("Incorrect, white lives"));
+        assert!(!ProblemSet::is_correct_comment("Wrong"));
+        assert!(ProblemSet::is_correct_comment("  right, black lives"));
+        assert!(ProblemSet::is_correct_comment("+"));
+        for comment in ["bright idea", "Copyright 2023", "not right", "Alright", "Correctly placed?"] {
+            assert!(!ProblemSet::is_correct_comment(comment), "{}", comment);
+        }
+    }
+
+    #[test]
+    pub fn test_problem_statuses() {
+        let mut problem_set = ProblemSet::from_dir("sgf").unwrap();
+        assert!(problem_set.current_path().ends_with(".sgf"));
+        problem_set.set_status(ProblemStatus::Failed);
+        problem_set.set_status(ProblemStatus::Solved);
+        assert_eq!(problem_set.current_status(), ProblemStatus::Failed);
+        assert_eq!(problem_set.count(ProblemStatus::Failed), 1);
+        problem_set.select(-1);
+        assert_eq!(problem_set.current(), problem_set.problem_count() - 1);
+        assert!(ProblemSet::from_dir("src/no_such_dir").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use iced::Color;
+
+use crate::{band_theme::BandTheme, diagram::{Diagram, Shape}};
+
+// up to two decimals, without trailing zeros
+pub(crate) fn number(value: f32) -> String {
+    let number = format!("{:.2}", value);
+    let number = number.trim_end_matches('0').trim_end_matches('.');
+    if number == "-0" { "0".to_string() } else { number.to_string() }
+}
+
+// `fill="#rrggbb"`, with an opacity when the colour is see-through
+fn paint(attribute: &str, color: Option<Color>) -> String {
+    let color = match color {
+        Some(color) => color,
+        None => return format!("{}=\"none\"", attribute),
+    };
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut paint = format!("{}=\"#{:02x}{:02x}{:02x}\"", attribute, channel(color.r), channel(color.g), channel(color.b));
+    if color.a < 1.0 {
+        paint.push_str(&format!(" {}-opacity=\"{}\"", attribute, number(color.a)));
+    }
+    paint
+}
+
+fn outline(stroke: Option<(f32, Color)>) -> String {
+    match stroke {
+        Some((width, color)) => format!("{} stroke-width=\"{}\"", paint("stroke", Some(color)), number(width)),
+        None => paint("stroke", None),
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+pub fn shape_element(shape: &Shape) -> String {
+    match shape {
+        Shape::Rect { origin, size, fill, stroke } => format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {} {}/>",
+            number(origin.0), number(origin.1), number(size.0), number(size.1), paint("fill", *fill), outline(*stroke)),
+        Shape::Circle { center, radius, fill, stroke } => format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {} {}/>",
+            number(center.0), number(center.1), number(*radius), paint("fill", *fill), outline(*stroke)),
+        Shape::Line { points, closed, width, color } => {
+            let points = points.iter()
+                .map(|(x, y)| format!("{},{}", number(*x), number(*y)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!(
+                "<{} points=\"{}\" fill=\"none\" {} stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+                if *closed { "polygon" } else { "polyline" }, points, outline(Some((*width, *color))))
+        },
+        Shape::Text { content, position, size, color } => format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"sans-serif\" text-anchor=\"middle\" dominant-baseline=\"central\" {}>{}</text>",
+            number(position.0), number(position.1), number(*size), paint("fill", Some(*color)), escape_text(content)),
+    }
+}
+
+// a standalone svg document showing `viewport` (x, y, width, height) of the
+// shapes at its own size
+pub fn shapes_svg(shapes: &[Shape], viewport: (f32, f32, f32, f32)) -> String {
+    let (view_x, view_y, view_width, view_height) = viewport;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">\n",
+        view_width.round(), view_height.round(), number(view_x), number(view_y), number(view_width), number(view_height));
+    for shape in shapes {
+        svg.push_str("  ");
+        svg.push_str(&shape_element(shape));
+        svg.push('\n');
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// a standalone svg document of the diagram, `width` pixels wide
+pub fn diagram_svg(diagram: &Diagram, band_theme: BandTheme, width: u32) -> String {
+    let band_layout = diagram.layout_for_width(width as f32);
+    shapes_svg(&diagram.shapes(&band_layout, band_theme), diagram.viewport(&band_layout))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{band_theme::BandTheme, diagram::{Diagram, Region}, game_tree::GameTree};
+
+    use super::diagram_svg;
+
+    #[test]
+    pub fn test_diagram_svg() {
+        let game_tree = GameTree::from_sgf("(;SZ[9];B[cc];W[dd]CR[cc]LB[ee:<1>])").unwrap();
+        let diagram = Diagram::from_game_tree(&game_tree, 2, None);
+        let svg = diagram_svg(&diagram, BandTheme::Monochrome, 180);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"180\" height=\"180\" viewBox=\"0 0 180 180\">"));
+        assert!(svg.contains("<circle cx=\"50\" cy=\"50\" r=\"5\" fill=\"none\" stroke=\"#ffffff\" stroke-width=\"2\"/>"));
+        assert!(svg.contains(">&lt;1&gt;</text>"));
+        assert_eq!(svg.matches("<polyline").count(), 18);
+
+        let mut diagram = Diagram::from_game_tree(&game_tree, 2, Some(1..=2));
+        diagram.set_region(Region::parse("aa:ee", 9).unwrap());
+        let svg = diagram_svg(&diagram, BandTheme::Monochrome, 200);
+        assert!(svg.contains("width=\"200\" height=\"200\" viewBox=\"0 0 200 200\""));
+        assert_eq!(svg.matches("<polyline").count(), 10);
+        assert!(svg.contains(">2</text>"));
+    }
+}
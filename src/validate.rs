@@ -0,0 +1,570 @@
+use std::{fmt, fs, path::Path, str::FromStr};
+
+use json::JsonValue;
+
+use crate::{
+    cli::{self, CliArg, CliArgs},
+    config::{MIN_GO_SZ, MAX_GO_SZ},
+    convert::RecordFormat,
+    coordinate::Coordinate,
+    file_browser::FileBrowser,
+    game_tree::{GameTree, SgfKey, SgfNode},
+    go_band::{GoBand, Play, Player},
+    go_move::GoMove,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    Unreadable,
+    Syntax,
+    UnknownProperty,
+    Duplicate,
+    MissingRoot,
+    Unsupported,
+    OutOfBounds,
+    IllegalMove,
+    WrongColour,
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IssueKind::Unreadable => "unreadable",
+            IssueKind::Syntax => "syntax",
+            IssueKind::UnknownProperty => "unknown-property",
+            IssueKind::Duplicate => "duplicate",
+            IssueKind::MissingRoot => "missing-root",
+            IssueKind::Unsupported => "unsupported",
+            IssueKind::OutOfBounds => "out-of-bounds",
+            IssueKind::IllegalMove => "illegal-move",
+            IssueKind::WrongColour => "wrong-colour",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// one problem of a record, `location` is `line:column` in the text for
+// syntax issues, else `root` or `move N` along a variation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    file: String,
+    location: String,
+    severity: Severity,
+    kind: IssueKind,
+    message: String,
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        json::object! {
+            file: self.file.clone(),
+            location: self.location.clone(),
+            severity: self.severity.to_string(),
+            kind: self.kind.to_string(),
+            message: self.message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}: {} [{}]", self.file, self.location, self.severity, self.message, self.kind)
+    }
+}
+
+// collects the issues of one record, each one once even when several
+// variations run into it
+struct RecordCheck {
+    file: String,
+    issues: Vec<Issue>,
+}
+
+impl RecordCheck {
+    fn report(&mut self, location: &str, severity: Severity, kind: IssueKind, message: String) {
+        let issue = Issue { file: self.file.clone(), location: location.to_string(), severity, kind, message };
+        if !self.issues.contains(&issue) {
+            self.issues.push(issue);
+        }
+    }
+
+    fn has_errors(&self) -> bool {
+        self.issues.iter().any(|issue| issue.severity == Severity::Error)
+    }
+
+    // the game tree reader skips what it cannot make sense of, so the raw
+    // text is checked first
+    fn check_syntax(&mut self, content: &str) {
+        let chs = content.chars().collect::<Vec<_>>();
+        let at = |idx: usize| {
+            let line_start = chs[..idx].iter().rposition(|ch| *ch == '\n').map_or(0, |pos| pos + 1);
+            let line = chs[..line_start].iter().filter(|ch| **ch == '\n').count() + 1;
+            format!("{}:{}", line, idx - line_start + 1)
+        };
+        let mut depth = 0;
+        let mut tree_count = 0;
+        // a game tree opened but no node started in it yet
+        let mut expect_node = false;
+        // the property taking values and whether it got one
+        let mut prop_key: Option<(String, usize, bool)> = None;
+        let mut node_keys: Vec<String> = vec![];
+        let mut outside_reported = false;
+        let mut i = 0;
+        while i < chs.len() {
+            let ch = chs[i];
+            if ch.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            if ch != '[' {
+                if let Some((key, key_idx, false)) = prop_key.take() {
+                    self.report(&at(key_idx), Severity::Error, IssueKind::Syntax, format!("{} has no value", key));
+                }
+            }
+            match ch {
+                '(' => {
+                    if depth == 0 {
+                        tree_count += 1;
+                        if tree_count == 2 {
+                            self.report(&at(i), Severity::Warning, IssueKind::Syntax, "only the first game tree is read".to_string());
+                        }
+                        outside_reported = false;
+                    }
+                    if expect_node {
+                        self.report(&at(i), Severity::Error, IssueKind::Syntax, "a game tree must start with a node".to_string());
+                    }
+                    depth += 1;
+                    expect_node = true;
+                },
+                ')' if depth == 0 => self.report(&at(i), Severity::Error, IssueKind::Syntax, "unbalanced )".to_string()),
+                ')' => {
+                    if expect_node {
+                        self.report(&at(i), Severity::Error, IssueKind::Syntax, "empty game tree".to_string());
+                    }
+                    depth -= 1;
+                    expect_node = false;
+                },
+                ';' if depth > 0 => {
+                    expect_node = false;
+                    node_keys.clear();
+                },
+                '[' if depth > 0 => {
+                    let mut j = i + 1;
+                    while j < chs.len() && chs[j] != ']' {
+                        if chs[j] == '\\' {
+                            j += 1;
+                        }
+                        j += 1;
+                    }
+                    if j >= chs.len() {
+                        self.report(&at(i), Severity::Error, IssueKind::Syntax, "unterminated value".to_string());
+                        return;
+                    }
+                    match prop_key.as_mut() {
+                        Some((_, _, has_value)) => *has_value = true,
+                        None => self.report(&at(i), Severity::Error, IssueKind::Syntax, "value without a property".to_string()),
+                    }
+                    i = j + 1;
+                    continue;
+                },
+                _ if ch.is_ascii_alphabetic() && depth > 0 => {
+                    let key = chs[i..].iter().take_while(|ch| ch.is_ascii_alphabetic()).collect::<String>();
+                    if expect_node {
+                        self.report(&at(i), Severity::Error, IssueKind::Syntax, format!("{} is outside a node", key));
+                    }
+                    match SgfKey::from_key(&key) {
+                        Err(_) => self.report(&at(i), Severity::Warning, IssueKind::UnknownProperty, format!("unknown property {} is ignored", key)),
+                        Ok(SgfKey::OTHER(_)) => self.report(&at(i), Severity::Warning, IssueKind::UnknownProperty, format!("unknown property {} is kept as it is", key)),
+                        Ok(sgf_key) if node_keys.contains(&key) && !sgf_key.is_list_sgf_key() =>
+                            self.report(&at(i), Severity::Error, IssueKind::Duplicate, format!("{} appears twice in a node", key)),
+                        Ok(_) => {},
+                    }
+                    node_keys.push(key.clone());
+                    prop_key = Some((key.clone(), i, false));
+                    i += key.len();
+                    continue;
+                },
+                _ if depth == 0 => {
+                    if !outside_reported {
+                        self.report(&at(i), Severity::Error, IssueKind::Syntax, "text outside the game tree".to_string());
+                        outside_reported = true;
+                    }
+                },
+                _ => self.report(&at(i), Severity::Error, IssueKind::Syntax, format!("unexpected '{}'", ch)),
+            }
+            i += 1;
+        }
+        if let Some((key, key_idx, false)) = prop_key {
+            self.report(&at(key_idx), Severity::Error, IssueKind::Syntax, format!("{} has no value", key));
+        }
+        if tree_count == 0 {
+            self.report("1:1", Severity::Error, IssueKind::Syntax, "no game tree found".to_string());
+        } else if depth > 0 {
+            self.report(&at(chs.len()), Severity::Error, IssueKind::Syntax, format!("{} game tree(s) not closed", depth));
+        }
+    }
+
+    // the band size, or None when the record cannot be replayed
+    fn check_root(&mut self, root_props: &[SgfNode]) -> Option<usize> {
+        let root_val = |key: SgfKey| root_props.iter().find(|node| node.node_key() == key).map(|node| node.node_val());
+        match root_val(SgfKey::GM) {
+            None => self.report("root", Severity::Warning, IssueKind::MissingRoot, "GM is missing, go is assumed".to_string()),
+            Some(gm) if gm.trim() != "1" => {
+                self.report("root", Severity::Error, IssueKind::Unsupported, format!("GM[{}] is not a go record", gm));
+                return None;
+            },
+            Some(_) => {},
+        }
+        if root_val(SgfKey::FF).is_none() {
+            self.report("root", Severity::Warning, IssueKind::MissingRoot, "FF is missing, FF[4] is assumed".to_string());
+        }
+        let sz = match root_val(SgfKey::SZ) {
+            Some(sz) => sz,
+            None => {
+                self.report("root", Severity::Warning, IssueKind::MissingRoot, "SZ is missing, 19x19 is assumed".to_string());
+                return Some(MAX_GO_SZ as usize);
+            },
+        };
+        // `SZ[19]`, or `SZ[19:19]` for a rectangular board
+        let dims = sz.split(':').map(|dim| dim.trim().parse::<i32>().ok()).collect::<Vec<_>>();
+        match dims.as_slice() {
+            [Some(dim)] | [Some(dim), Some(_)] if dims.iter().all(|other| *other == Some(*dim)) && (MIN_GO_SZ..=MAX_GO_SZ).contains(dim) =>
+                Some(*dim as usize),
+            _ => {
+                self.report("root", Severity::Error, IssueKind::Unsupported,
+                    format!("SZ[{}] is not supported, square sizes from {} to {} are", sz, MIN_GO_SZ, MAX_GO_SZ));
+                None
+            },
+        }
+    }
+
+    // the points of a value that are on the band
+    fn check_points(&mut self, location: &str, sgf_node: &SgfNode, dim: usize) -> Vec<(usize, usize)> {
+        let node_key = sgf_node.node_key();
+        let node_val = sgf_node.node_val();
+        // `LB[aa:text]`, a label names one point
+        let point_val = match node_key {
+            SgfKey::LB => match node_val.split_once(':') {
+                Some((point_val, _)) => point_val.to_string(),
+                None => {
+                    self.report(location, Severity::Error, IssueKind::Syntax, format!("LB[{}] needs a point and a text", node_val));
+                    return vec![];
+                },
+            },
+            _ => node_val.clone(),
+        };
+        // letters past `s` are points, though off any band
+        let corners = point_val.split(':').collect::<Vec<_>>();
+        let in_letters = corners.len() <= 2
+            && corners.iter().all(|point| point.len() == 2 && point.chars().all(|ch| ch.is_ascii_lowercase()));
+        let points = match SgfNode::parse_points(&point_val) {
+            Some(points) => points,
+            None if in_letters => {
+                self.report(location, Severity::Error, IssueKind::OutOfBounds,
+                    format!("{:?}[{}] is off the {}x{} band", node_key, node_val, dim, dim));
+                return vec![];
+            },
+            None => {
+                self.report(location, Severity::Error, IssueKind::Syntax, format!("{:?}[{}] is not a point", node_key, node_val));
+                return vec![];
+            },
+        };
+        let (on_band, off_band): (Vec<_>, Vec<_>) = points.into_iter().partition(|(x, y)| *x < dim && *y < dim);
+        if !off_band.is_empty() {
+            self.report(location, Severity::Error, IssueKind::OutOfBounds,
+                format!("{:?}[{}] is off the {}x{} band", node_key, node_val, dim, dim));
+        }
+        on_band
+    }
+
+    // plays one variation through the rules of the band
+    fn check_variation(&mut self, path_nodes: &[SgfNode], dim: usize) {
+        let mut go_band = GoBand::new(dim, 0, 0, 0, 0);
+        // nobody in particular before the first move or `PL`
+        let mut to_play: Option<Player> = None;
+        let mut last_move: Option<GoMove> = None;
+        let mut move_count = 0;
+        for sgf_node in path_nodes {
+            let node_key = sgf_node.node_key();
+            let node_val = sgf_node.node_val();
+            if sgf_node.is_move() {
+                move_count += 1;
+            }
+            let location = if move_count == 0 { "root".to_string() } else { format!("move {}", move_count) };
+            match node_key {
+                SgfKey::B | SgfKey::W => {
+                    let player = if node_key == SgfKey::B { Player::BLACK } else { Player::WHITE };
+                    if let Some(expected) = to_play.filter(|expected| expected.state() != player.state()) {
+                        self.report(&location, Severity::Warning, IssueKind::WrongColour,
+                            format!("{:?} played but {:?} was to play", node_key, if expected.state() == 1 { SgfKey::B } else { SgfKey::W }));
+                    }
+                    to_play = Some(player.opposite());
+                    go_band.set_current_player(player);
+                    // `B[]` and, up to 19x19, `B[tt]` are passes
+                    if node_val.is_empty() || (node_val == "tt" && dim <= 19) {
+                        go_band.pass();
+                        last_move = None;
+                        continue;
+                    }
+                    let (x, y) = match self.check_points(&location, sgf_node, dim).first() {
+                        Some(point) => *point,
+                        None => continue,
+                    };
+                    let coordinate = Coordinate::Point(x, y);
+                    if go_band.stone_state(x, y) != 0 {
+                        self.report(&location, Severity::Error, IssueKind::IllegalMove,
+                            format!("{:?} at {} is on an occupied point", node_key, coordinate));
+                        continue;
+                    }
+                    go_band.set_stone_pos(x as i32, y as i32);
+                    match go_band.forward(true) {
+                        Some(go_move) => last_move = Some(go_move),
+                        None => {
+                            // a move is refused for suicide, or for taking back
+                            // the single stone that was just captured
+                            let retakes_ko = last_move.as_ref()
+                                .is_some_and(|go_move| go_move.eaten_stones().iter().map(|(i, j, _)| (*i, *j)).eq([(x, y)]));
+                            let rule = if retakes_ko { "retakes the ko" } else { "is suicide" };
+                            self.report(&location, Severity::Error, IssueKind::IllegalMove,
+                                format!("{:?} at {} {}", node_key, coordinate, rule));
+                            last_move = None;
+                        },
+                    }
+                },
+                SgfKey::AB | SgfKey::AW | SgfKey::AE => {
+                    let state = match node_key {
+                        SgfKey::AB => 1,
+                        SgfKey::AW => -1,
+                        _ => 0,
+                    };
+                    for (x, y) in self.check_points(&location, sgf_node, dim) {
+                        go_band.set_stone_state(x, y, state);
+                    }
+                },
+                SgfKey::PL => match node_val.as_str() {
+                    "B" => to_play = Some(Player::BLACK),
+                    "W" => to_play = Some(Player::WHITE),
+                    _ => self.report(&location, Severity::Error, IssueKind::Syntax, format!("PL[{}] is neither B nor W", node_val)),
+                },
+                // an empty `DD[]` switches dimming off
+                SgfKey::DD if node_val.is_empty() => {},
+                SgfKey::CR | SgfKey::MA | SgfKey::SQ | SgfKey::TR | SgfKey::LB
+                | SgfKey::TB | SgfKey::TW | SgfKey::DD => {
+                    self.check_points(&location, sgf_node, dim);
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+// the issues of one sgf record, the moves are only replayed when its
+// syntax is right
+pub fn check_record(file: &str, content: &str) -> Vec<Issue> {
+    let mut record_check = RecordCheck { file: file.to_string(), issues: vec![] };
+    record_check.check_syntax(content);
+    if record_check.has_errors() {
+        return record_check.issues;
+    }
+    let game_tree = match GameTree::from_sgf(content) {
+        Ok(game_tree) => game_tree,
+        Err(err) => {
+            record_check.report("1:1", Severity::Error, IssueKind::Syntax, err);
+            return record_check.issues;
+        },
+    };
+    if let Some(dim) = record_check.check_root(&GameTree::get_node_props(&game_tree, -1)) {
+        for path_nodes in GameTree::variation_paths(&game_tree) {
+            record_check.check_variation(&path_nodes, dim);
+        }
+    }
+    record_check.issues
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl ReportFormat {
+    pub const ALL: [ReportFormat; 2] = [
+        ReportFormat::Text,
+        ReportFormat::Json,
+    ];
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReportFormat::Text => "text",
+            ReportFormat::Json => "json",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        cli::parse_format(&ReportFormat::ALL, s)
+    }
+}
+
+// sgf files and directories checked together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validation {
+    paths: Vec<String>,
+    report_format: ReportFormat,
+}
+
+impl Validation {
+    // `[--format FORMAT] PATH...`, directories are searched for sgf files
+    pub fn from_args(args: &[String]) -> Result<Validation, String> {
+        let mut report_format = ReportFormat::Text;
+        let mut paths = vec![];
+        let mut args = CliArgs::new(args);
+        while let Some(arg) = args.next_arg() {
+            match arg {
+                CliArg::Option("--format") => report_format = args.value("FORMAT")?.parse()?,
+                CliArg::Option(_) => return Err(args.unknown_option()),
+                CliArg::Path(path) => paths.push(path),
+            }
+        }
+        if paths.is_empty() {
+            return Err("validate needs a FILE or DIRECTORY".to_string());
+        }
+        Ok(Validation { paths, report_format })
+    }
+
+    pub fn check(&self) -> Result<(usize, Vec<Issue>), String> {
+        let mut file_count = 0;
+        let mut issues = vec![];
+        for path in &self.paths {
+            for record_path in FileBrowser::find_records(Path::new(path), RecordFormat::Sgf)? {
+                let file = record_path.to_string_lossy().to_string();
+                file_count += 1;
+                match fs::read_to_string(&record_path) {
+                    Ok(content) => issues.extend(check_record(&file, &content)),
+                    Err(err) => issues.push(Issue {
+                        file,
+                        location: "1:1".to_string(),
+                        severity: Severity::Error,
+                        kind: IssueKind::Unreadable,
+                        message: format!("cannot read: {}", err),
+                    }),
+                }
+            }
+        }
+        Ok((file_count, issues))
+    }
+
+    // prints the report, true when no file has errors
+    pub fn run(&self) -> Result<bool, String> {
+        let (file_count, issues) = self.check()?;
+        let count = |severity: Severity| issues.iter().filter(|issue| issue.severity() == severity).count();
+        let (error_count, warning_count) = (count(Severity::Error), count(Severity::Warning));
+        match self.report_format {
+            ReportFormat::Text => {
+                for issue in &issues {
+                    println!("{}", issue);
+                }
+                println!("{} file(s) checked, {} error(s), {} warning(s)", file_count, error_count, warning_count);
+            },
+            ReportFormat::Json => {
+                let report = json::object! {
+                    files: file_count,
+                    errors: error_count,
+                    warnings: warning_count,
+                    issues: issues.iter().map(|issue| issue.to_json()).collect::<Vec<_>>(),
+                };
+                println!("{}", json::stringify_pretty(report, 2));
+            },
+        }
+        Ok(error_count == 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::args;
+
+    use super::{check_record, IssueKind, Severity, Validation};
+
+    fn issues(content: &str) -> Vec<(String, Severity, IssueKind)> {
+        check_record("test.sgf", content).iter()
+            .map(|issue| (issue.location.clone(), issue.severity, issue.kind))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_syntax() {
+        assert_eq!(issues("(;GM[1]FF[4]SZ[9];B[aa];W[bb])"), vec![]);
+        assert_eq!(issues("(;GM[1]FF[4]SZ[9];B[aa]\n;W[bb]"), vec![("2:7".to_string(), Severity::Error, IssueKind::Syntax)]);
+        assert_eq!(issues("(;GM[1]FF[4]SZ[9];B[aa];C[x)"), vec![("1:26".to_string(), Severity::Error, IssueKind::Syntax)]);
+        assert_eq!(issues("(;GM[1]FF[4]SZ[9]XY[1];B[aa]B[bb])"), vec![
+            ("1:18".to_string(), Severity::Warning, IssueKind::UnknownProperty),
+            ("1:29".to_string(), Severity::Error, IssueKind::Duplicate),
+        ]);
+        assert_eq!(issues("GM[1]"), vec![
+            ("1:1".to_string(), Severity::Error, IssueKind::Syntax),
+            ("1:1".to_string(), Severity::Error, IssueKind::Syntax),
+        ]);
+    }
+
+    #[test]
+    pub fn test_replay() {
+        assert_eq!(issues("(;SZ[9];B[aa])"), vec![
+            ("root".to_string(), Severity::Warning, IssueKind::MissingRoot),
+            ("root".to_string(), Severity::Warning, IssueKind::MissingRoot),
+        ]);
+        assert_eq!(issues("(;GM[1]FF[4]SZ[9]AB[jj];B[aa];B[bb];W[aa])"), vec![
+            ("root".to_string(), Severity::Error, IssueKind::OutOfBounds),
+            ("move 2".to_string(), Severity::Warning, IssueKind::WrongColour),
+            ("move 3".to_string(), Severity::Error, IssueKind::IllegalMove),
+        ]);
+        let messages = |content: &str| check_record("test.sgf", content).iter().map(|issue| issue.message.clone()).collect::<Vec<_>>();
+        assert_eq!(messages("(;GM[1]FF[4]SZ[9]AB[ba][ab];W[aa])"), vec!["W at aa is suicide".to_string()]);
+        // black takes the ko in the corner, white may not take back at once
+        assert_eq!(messages("(;GM[1]FF[4]SZ[9]AB[ca][bb]AW[ba][ab];B[aa];W[ba])"), vec!["W at ba retakes the ko".to_string()]);
+        // the same mistake in two variations is reported once
+        assert_eq!(issues("(;GM[1]FF[4]SZ[9];B[aa];W[aa](;B[bb])(;B[cc]))").len(), 1);
+        assert_eq!(issues("(;GM[1]FF[4]SZ[23])"), vec![("root".to_string(), Severity::Error, IssueKind::Unsupported)]);
+        // points that are not coordinates are not taken as `aa`
+        assert_eq!(issues("(;GM[1]FF[4]SZ[9]AB[a?];B[1!];W[zz];B[aa:bb:cc])"), vec![
+            ("root".to_string(), Severity::Error, IssueKind::Syntax),
+            ("move 1".to_string(), Severity::Error, IssueKind::Syntax),
+            ("move 2".to_string(), Severity::Error, IssueKind::OutOfBounds),
+            ("move 3".to_string(), Severity::Error, IssueKind::Syntax),
+        ]);
+        assert_eq!(messages("(;GM[1]FF[4]SZ[9];B[1!])"), vec!["B[1!] is not a point".to_string()]);
+    }
+
+    #[test]
+    pub fn test_validation_args() {
+        let validation = Validation::from_args(&args("--format=json sgf/test.sgf")).unwrap();
+        assert_eq!(validation.check().unwrap(), (1, vec![]));
+        assert_eq!(Validation::from_args(&args("--format xml sgf")).err(), Some("unknown format: xml".to_string()));
+        assert_eq!(Validation::from_args(&[]).err(), Some("validate needs a FILE or DIRECTORY".to_string()));
+    }
+}